@@ -0,0 +1,34 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use nekosbest::sans_io;
+
+const SINGLE: &str = r#"{"results":[{"artist_href":"https://www.pixiv.net/en/users/1","artist_name":"someone","source_url":"https://www.pixiv.net/en/artworks/2","url":"https://nekos.best/api/v2/neko/1efcda2d-d0d3-4e96-9b40-86852374b4bc.png"}]}"#;
+
+fn gif_body(amount: usize) -> String {
+    let results = (0..amount)
+        .map(|i| {
+            format!(
+                r#"{{"anime_name":"Sewayaki Kitsune no Senko-san","url":"https://nekos.best/api/v2/pat/{i:08}-d0d3-4e96-9b40-86852374b4bc.gif"}}"#
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+    format!(r#"{{"results":[{results}]}}"#)
+}
+
+fn parse(c: &mut Criterion) {
+    c.bench_function("single image", |b| {
+        b.iter(|| sans_io::parse_response_single(black_box(SINGLE.as_bytes())).unwrap())
+    });
+
+    c.bench_function("single image, as amount", |b| {
+        b.iter(|| sans_io::parse_response(black_box(SINGLE.as_bytes())).unwrap())
+    });
+
+    let amount = gif_body(20);
+    c.bench_function("20 gifs", |b| {
+        b.iter(|| sans_io::parse_response(black_box(amount.as_bytes())).unwrap())
+    });
+}
+
+criterion_group!(benches, parse);
+criterion_main!(benches);
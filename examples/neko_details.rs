@@ -5,8 +5,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .details
         .try_into_image()
         .unwrap();
-    println!("Source: {}", details.source_url);
-    println!("Artist: {}", details.artist_name);
-    println!("Artist link: {}", details.artist_href);
+    println!("Artist: {}", details.artist.name);
+    println!("Artist link: {}", details.artist.href);
+    if let Some(source) = details.source {
+        println!("Source: {}", source.url);
+    }
     Ok(())
 }
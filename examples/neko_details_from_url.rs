@@ -4,8 +4,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         "https://nekos.best/api/v2/neko/1ee4ad62-d0fd-4956-adeb-05b6917f0a31.png",
     )
     .await?;
-    println!("Source: {}", details.source_url);
-    println!("Artist: {}", details.artist_name);
-    println!("Artist link: {}", details.artist_href);
+    println!("Artist: {}", details.artist.name);
+    println!("Artist link: {}", details.artist.href);
+    if let Some(source) = details.source {
+        println!("Source: {}", source.url);
+    }
     Ok(())
 }
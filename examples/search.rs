@@ -1,8 +1,11 @@
+use nekosbest::client::{Client, ClientConfig};
 use nekosbest::{SearchQuery, SearchQueryKind};
 
 #[tokio::main]
 async fn main() {
-    let r = nekosbest::search(
+    let client = Client::new(ClientConfig::default());
+    let r = nekosbest::search_with_client(
+        &client,
         SearchQuery::new("Senko", SearchQueryKind::Gif)
             .amount(2)
             .category(nekosbest::Category::Pat),
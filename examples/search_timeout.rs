@@ -20,8 +20,8 @@ async fn main() {
             Ok(r) => {
                 dbg!(&r.0);
             }
-            Err(nekosbest::NekosBestError::RateLimited) => {
-                println!("Rate limited on iteration {i}");
+            Err(nekosbest::NekosBestError::RateLimited { retry_after }) => {
+                println!("Rate limited on iteration {i}, retry after {retry_after:?}");
                 return;
             }
             Err(e) => {
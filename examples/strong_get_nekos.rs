@@ -4,8 +4,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let img_url = resp.url();
     println!("{img_url}");
     let details = resp.details();
-    println!("Artist: {}", details.artist_name);
-    println!("Artist link: {}", details.artist_href);
-    println!("Source: {}", details.source_url);
+    println!("Artist: {}", details.artist.name);
+    println!("Artist link: {}", details.artist.href);
+    if let Some(source) = &details.source {
+        println!("Source: {}", source.url);
+    }
     Ok(())
 }
@@ -0,0 +1,182 @@
+//! The operations of the client behind a trait, so that code using them
+//! can be tested against a mock implementation.
+//!
+//! [`NekosBestApi`] is object-safe: its methods return boxed futures, so
+//! it can be used as `&dyn NekosBestApi` or `Arc<dyn NekosBestApi>`.
+
+#[cfg(not(feature = "blocking"))]
+use std::future::Future;
+#[cfg(not(feature = "blocking"))]
+use std::pin::Pin;
+
+use crate::client::Client;
+use crate::details::{GifDetails, ImageDetails};
+use crate::{Category, NekosBestError, NekosBestResponse, NekosBestResponseSingle, SearchQuery};
+
+/// What the methods of [`NekosBestApi`] return: a boxed future of the
+/// result, or with the `blocking` feature, the result itself.
+#[cfg(all(not(feature = "blocking"), not(target_arch = "wasm32")))]
+pub type ApiCall<'a, T> = Pin<Box<dyn Future<Output = Result<T, NekosBestError>> + Send + 'a>>;
+
+/// What the methods of [`NekosBestApi`] return: a boxed future of the
+/// result, or with the `blocking` feature, the result itself.
+///
+/// On wasm32, the futures of the browser's `fetch` are not `Send`.
+#[cfg(all(not(feature = "blocking"), target_arch = "wasm32"))]
+pub type ApiCall<'a, T> = Pin<Box<dyn Future<Output = Result<T, NekosBestError>> + 'a>>;
+
+/// What the methods of [`NekosBestApi`] return: a boxed future of the
+/// result, or with the `blocking` feature, the result itself.
+#[cfg(feature = "blocking")]
+pub type ApiCall<'a, T> = Result<T, NekosBestError>;
+
+/// The operations of the API, implemented by [`Client`].
+#[cfg(not(target_arch = "wasm32"))]
+pub trait NekosBestApi: Send + Sync {
+    /// Gets a single image, like [`crate::get_with_client`].
+    fn get(&self, category: Category) -> ApiCall<'_, NekosBestResponseSingle>;
+
+    /// Gets `amount` images, like [`crate::get_with_client_amount`].
+    fn get_amount(&self, category: Category, amount: u8) -> ApiCall<'_, NekosBestResponse>;
+
+    /// Searches for images, like [`crate::search_with_client`].
+    fn search(&self, query: SearchQuery) -> ApiCall<'_, NekosBestResponse>;
+
+    /// Gets the details of the image at `url`, like
+    /// [`crate::get_with_client_image_details`].
+    fn image_details<'a>(&'a self, url: &'a str) -> ApiCall<'a, ImageDetails>;
+
+    /// Gets the details of the gif at `url`, like
+    /// [`crate::get_with_client_gif_details`].
+    fn gif_details<'a>(&'a self, url: &'a str) -> ApiCall<'a, GifDetails>;
+}
+
+/// The operations of the API, implemented by [`Client`].
+///
+/// On wasm32, the client (with its shared futures, with the `coalesce`
+/// feature) is not `Send`, so implementations don't need to be either.
+#[cfg(target_arch = "wasm32")]
+pub trait NekosBestApi {
+    /// Gets a single image, like [`crate::get_with_client`].
+    fn get(&self, category: Category) -> ApiCall<'_, NekosBestResponseSingle>;
+
+    /// Gets `amount` images, like [`crate::get_with_client_amount`].
+    fn get_amount(&self, category: Category, amount: u8) -> ApiCall<'_, NekosBestResponse>;
+
+    /// Searches for images, like [`crate::search_with_client`].
+    fn search(&self, query: SearchQuery) -> ApiCall<'_, NekosBestResponse>;
+
+    /// Gets the details of the image at `url`, like
+    /// [`crate::get_with_client_image_details`].
+    fn image_details<'a>(&'a self, url: &'a str) -> ApiCall<'a, ImageDetails>;
+
+    /// Gets the details of the gif at `url`, like
+    /// [`crate::get_with_client_gif_details`].
+    fn gif_details<'a>(&'a self, url: &'a str) -> ApiCall<'a, GifDetails>;
+}
+
+#[cfg(not(feature = "blocking"))]
+impl NekosBestApi for Client {
+    fn get(&self, category: Category) -> ApiCall<'_, NekosBestResponseSingle> {
+        Box::pin(crate::get_with_client(self, category))
+    }
+
+    fn get_amount(&self, category: Category, amount: u8) -> ApiCall<'_, NekosBestResponse> {
+        Box::pin(crate::get_with_client_amount(self, category, amount))
+    }
+
+    fn search(&self, query: SearchQuery) -> ApiCall<'_, NekosBestResponse> {
+        Box::pin(crate::search_with_client(self, query))
+    }
+
+    fn image_details<'a>(&'a self, url: &'a str) -> ApiCall<'a, ImageDetails> {
+        Box::pin(crate::get_with_client_image_details(self, url))
+    }
+
+    fn gif_details<'a>(&'a self, url: &'a str) -> ApiCall<'a, GifDetails> {
+        Box::pin(crate::get_with_client_gif_details(self, url))
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl NekosBestApi for Client {
+    fn get(&self, category: Category) -> ApiCall<'_, NekosBestResponseSingle> {
+        crate::get_with_client(self, category)
+    }
+
+    fn get_amount(&self, category: Category, amount: u8) -> ApiCall<'_, NekosBestResponse> {
+        crate::get_with_client_amount(self, category, amount)
+    }
+
+    fn search(&self, query: SearchQuery) -> ApiCall<'_, NekosBestResponse> {
+        crate::search_with_client(self, query)
+    }
+
+    fn image_details<'a>(&'a self, url: &'a str) -> ApiCall<'a, ImageDetails> {
+        crate::get_with_client_image_details(self, url)
+    }
+
+    fn gif_details<'a>(&'a self, url: &'a str) -> ApiCall<'a, GifDetails> {
+        crate::get_with_client_gif_details(self, url)
+    }
+}
+
+#[cfg(all(test, not(feature = "blocking")))]
+mod test {
+    use super::*;
+
+    /// Answers every request for an image with the same gif.
+    struct Mock;
+
+    impl NekosBestApi for Mock {
+        fn get(&self, category: Category) -> ApiCall<'_, NekosBestResponseSingle> {
+            Box::pin(async move {
+                Ok(NekosBestResponseSingle::test_gif(
+                    format!("http://localhost/{}/a.gif", category.to_url_name()),
+                    "Senko",
+                ))
+            })
+        }
+
+        fn get_amount(&self, category: Category, amount: u8) -> ApiCall<'_, NekosBestResponse> {
+            Box::pin(async move {
+                let mut images = Vec::new();
+                for _ in 0..amount {
+                    images.push(self.get(category).await?);
+                }
+                Ok(NekosBestResponse(images))
+            })
+        }
+
+        fn search(&self, _query: SearchQuery) -> ApiCall<'_, NekosBestResponse> {
+            Box::pin(async { Ok(NekosBestResponse(Vec::new())) })
+        }
+
+        fn image_details<'a>(&'a self, _url: &'a str) -> ApiCall<'a, ImageDetails> {
+            Box::pin(async { Err(NekosBestError::NotFound) })
+        }
+
+        fn gif_details<'a>(&'a self, _url: &'a str) -> ApiCall<'a, GifDetails> {
+            Box::pin(async {
+                Ok(GifDetails {
+                    anime_name: "Senko".to_owned(),
+                })
+            })
+        }
+    }
+
+    async fn hug_command(api: &dyn NekosBestApi) -> Result<String, NekosBestError> {
+        Ok(api.get(Category::Hug).await?.url)
+    }
+
+    #[tokio::test]
+    async fn mock() {
+        assert_eq!(
+            hug_command(&Mock).await.unwrap(),
+            "http://localhost/hug/a.gif"
+        );
+        assert_eq!(Mock.get_amount(Category::Pat, 3).await.unwrap().len(), 3);
+
+        let _: &dyn NekosBestApi = &Client::new(Default::default());
+    }
+}
@@ -0,0 +1,410 @@
+//! Pluggable HTTP clients.
+//!
+//! [`BackendClient`] makes the same requests as the free functions of the
+//! crate, through any [`HttpBackend`], with the request construction and
+//! parsing from [`crate::sans_io`]. With the `reqwest` feature, the crate
+//! implements [`HttpBackend`] for `reqwest::Client`, and for
+//! `client::Client`, whose own requests go through the same
+//! [`HttpRequest`]s and [`HttpResponse`]s. With the `hyper` feature, it
+//! implements it for `hyper::Client`.
+//!
+//! Only `client::Client` and the free functions need reqwest: without the
+//! `reqwest` feature, the requests go through a [`BackendClient`] (or
+//! `ureq_client::UreqClient`).
+
+use std::future::Future;
+
+// the same `http::HeaderMap` for both
+#[cfg(all(
+    feature = "hyper",
+    not(feature = "reqwest"),
+    not(target_arch = "wasm32")
+))]
+use hyper::HeaderMap;
+#[cfg(feature = "reqwest")]
+use reqwest::header::HeaderMap;
+
+use crate::details::{GifDetails, ImageDetails};
+use crate::{
+    sans_io, Category, HeaderDeserializeUrlEncodedError, NekosBestError, NekosBestResponse,
+    NekosBestResponseSingle, SearchQuery, BASE_URL, USER_AGENT,
+};
+
+/// A `GET` request to send.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpRequest {
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+}
+
+impl HttpRequest {
+    pub fn get(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            headers: Vec::new(),
+        }
+    }
+}
+
+/// The response to a [`HttpRequest`], with the whole body read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    /// The value of the header `name`, compared case-insensitively.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub(crate) fn header_or_missing(
+        &self,
+        name: &str,
+    ) -> Result<&str, HeaderDeserializeUrlEncodedError> {
+        self.header(name)
+            .ok_or(HeaderDeserializeUrlEncodedError::MissingHeader)
+    }
+
+    pub(crate) fn error_for_status(self) -> Result<Self, NekosBestError> {
+        sans_io::check_status(self.status, self.header("retry-after"))?;
+        Ok(self)
+    }
+}
+
+/// An HTTP client that requests can be sent through.
+///
+/// Errors of the underlying client should be reported as
+/// [`NekosBestError::Backend`]. Non-success status codes are not
+/// errors at this level.
+///
+/// On wasm32, where the futures of the browser's `fetch` are not `Send`,
+/// neither the backends nor their futures need to be.
+#[cfg(not(target_arch = "wasm32"))]
+pub trait HttpBackend: Send + Sync {
+    fn send(
+        &self,
+        request: HttpRequest,
+    ) -> impl Future<Output = Result<HttpResponse, NekosBestError>> + Send;
+}
+
+/// An HTTP client that requests can be sent through.
+///
+/// Errors of the underlying client should be reported as
+/// [`NekosBestError::Backend`]. Non-success status codes are not
+/// errors at this level.
+#[cfg(target_arch = "wasm32")]
+pub trait HttpBackend {
+    fn send(
+        &self,
+        request: HttpRequest,
+    ) -> impl Future<Output = Result<HttpResponse, NekosBestError>>;
+}
+
+#[cfg(feature = "reqwest")]
+impl HttpBackend for reqwest::Client {
+    async fn send(&self, request: HttpRequest) -> Result<HttpResponse, NekosBestError> {
+        let mut req = self.get(request.url);
+        for (name, value) in request.headers {
+            req = req.header(name, value);
+        }
+
+        let resp = req.send().await?;
+        let status = resp.status().as_u16();
+        let headers = header_pairs(resp.headers());
+        let body = resp.bytes().await?.to_vec();
+
+        Ok(HttpResponse {
+            status,
+            headers,
+            body,
+        })
+    }
+}
+
+/// Sends the requests through the whole pipeline of the client, like the
+/// functions of the crate: its rate limiter, retries, middleware, circuit
+/// breaker and cassette. The cache isn't involved.
+#[cfg(all(feature = "reqwest", not(feature = "blocking")))]
+impl HttpBackend for crate::client::Client {
+    async fn send(&self, request: HttpRequest) -> Result<HttpResponse, NekosBestError> {
+        self.send_http(request).await
+    }
+}
+
+/// Sends the requests with hyper directly. Build the client with a TLS
+/// connector (like `hyper-rustls`) to send requests to nekos.best.
+#[cfg(all(feature = "hyper", not(target_arch = "wasm32")))]
+impl<C> HttpBackend for hyper::Client<C>
+where
+    C: hyper::client::connect::Connect + Clone + Send + Sync + 'static,
+{
+    async fn send(&self, request: HttpRequest) -> Result<HttpResponse, NekosBestError> {
+        let mut req = hyper::Request::get(request.url);
+        for (name, value) in request.headers {
+            req = req.header(name, value);
+        }
+        let req = req
+            .body(hyper::Body::empty())
+            .map_err(|e| NekosBestError::Backend(e.into()))?;
+
+        let resp = self
+            .request(req)
+            .await
+            .map_err(|e| NekosBestError::Backend(e.into()))?;
+        let status = resp.status().as_u16();
+        let headers = header_pairs(resp.headers());
+        let body = hyper::body::to_bytes(resp.into_body())
+            .await
+            .map_err(|e| NekosBestError::Backend(e.into()))?
+            .to_vec();
+
+        Ok(HttpResponse {
+            status,
+            headers,
+            body,
+        })
+    }
+}
+
+/// The headers of a response, skipping the values that aren't strings.
+#[cfg(any(
+    feature = "reqwest",
+    all(feature = "hyper", not(target_arch = "wasm32"))
+))]
+pub(crate) fn header_pairs(headers: &HeaderMap) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_owned())))
+        .collect()
+}
+
+/// Makes the requests of the crate through a [`HttpBackend`].
+///
+/// Unlike [`crate::client::Client`], it doesn't track the rate limit of
+/// the search endpoint; hitting it results in [`NekosBestError::RateLimited`].
+#[derive(Debug, Clone)]
+pub struct BackendClient<B> {
+    backend: B,
+    base_url: String,
+    user_agent: String,
+}
+
+impl<B: HttpBackend> BackendClient<B> {
+    pub fn new(backend: B) -> Self {
+        Self::with_base_url(backend, BASE_URL)
+    }
+
+    pub fn with_base_url(backend: B, base_url: impl Into<String>) -> Self {
+        Self {
+            backend,
+            base_url: base_url.into().trim_end_matches('/').to_owned(),
+            user_agent: USER_AGENT.to_owned(),
+        }
+    }
+
+    /// Sends the requests with `user_agent` as their `User-Agent`, instead
+    /// of [`USER_AGENT`].
+    pub fn with_user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = user_agent.into();
+        self
+    }
+
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    async fn fetch(&self, url: impl Into<String>) -> Result<HttpResponse, NekosBestError> {
+        let mut request = HttpRequest::get(url);
+        request
+            .headers
+            .push(("User-Agent".to_owned(), self.user_agent.clone()));
+
+        self.backend.send(request).await?.error_for_status()
+    }
+
+    async fn fetch_parse(&self, url: String) -> Result<NekosBestResponse, NekosBestError> {
+        let resp = self.fetch(url.as_str()).await?;
+        sans_io::parse_response(&resp.body)
+            .map_err(|e| e.with_response(&url, resp.status, &resp.body))
+    }
+
+    /// Gets a single image, like [`crate::get_with_client`].
+    pub async fn get(
+        &self,
+        category: impl Into<Category>,
+    ) -> Result<NekosBestResponseSingle, NekosBestError> {
+        let url = sans_io::category_url(&self.base_url, category.into());
+        let mut response = self.fetch_parse(url).await?;
+        response.0.pop().ok_or(NekosBestError::NotFound)
+    }
+
+    /// Gets `amount` images, like [`crate::get_with_client_amount`].
+    ///
+    /// # Errors
+    /// [`NekosBestError::InvalidAmount`] if `amount` is not in the
+    /// `1..=`[`crate::MAX_AMOUNT`] range, see [`sans_io::check_amount`].
+    pub async fn get_amount(
+        &self,
+        category: impl Into<Category>,
+        amount: impl Into<u8>,
+    ) -> Result<NekosBestResponse, NekosBestError> {
+        let amount = sans_io::check_amount(amount.into())?;
+        let url = sans_io::category_amount_url(&self.base_url, category.into(), amount);
+        self.fetch_parse(url).await
+    }
+
+    /// Searches for images, like [`crate::search_with_client`].
+    pub async fn search(&self, query: SearchQuery) -> Result<NekosBestResponse, NekosBestError> {
+        let url = sans_io::search_url(&self.base_url, &query);
+        self.fetch_parse(url).await
+    }
+
+    /// Gets the details of the image at `url`, like
+    /// [`crate::get_with_client_image_details`].
+    pub async fn image_details(
+        &self,
+        url: impl Into<String>,
+    ) -> Result<ImageDetails, NekosBestError> {
+        let resp = self.fetch(url).await?;
+        sans_io::parse_image_details(|name| resp.header_or_missing(name))
+    }
+
+    /// Gets the details of the gif at `url`, like
+    /// [`crate::get_with_client_gif_details`].
+    pub async fn gif_details(&self, url: impl Into<String>) -> Result<GifDetails, NekosBestError> {
+        let resp = self.fetch(url).await?;
+        sans_io::parse_gif_details(|name| resp.header_or_missing(name))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Answers every request with the same response, and records the urls
+    /// and headers.
+    struct Canned {
+        response: HttpResponse,
+        urls: Mutex<Vec<String>>,
+        headers: Mutex<Vec<(String, String)>>,
+    }
+
+    impl HttpBackend for Canned {
+        async fn send(&self, request: HttpRequest) -> Result<HttpResponse, NekosBestError> {
+            self.urls.lock().unwrap().push(request.url);
+            *self.headers.lock().unwrap() = request.headers;
+            Ok(self.response.clone())
+        }
+    }
+
+    fn canned(status: u16, headers: &[(&str, &str)], body: &str) -> BackendClient<Canned> {
+        BackendClient::with_base_url(
+            Canned {
+                response: HttpResponse {
+                    status,
+                    headers: headers
+                        .iter()
+                        .map(|(n, v)| (n.to_string(), v.to_string()))
+                        .collect(),
+                    body: body.as_bytes().to_vec(),
+                },
+                urls: Mutex::new(Vec::new()),
+                headers: Mutex::new(Vec::new()),
+            },
+            "http://localhost/",
+        )
+    }
+
+    #[tokio::test]
+    async fn requests() {
+        let client = canned(
+            200,
+            &[],
+            r#"{"results":[{"anime_name":"Senko","url":"http://localhost/pat/a.gif"}]}"#,
+        );
+        let resp = client.get(Category::Pat).await.unwrap();
+        assert_eq!(resp.details.as_gif().unwrap().anime_name, "Senko");
+        client.get_amount(Category::Pat, 3).await.unwrap();
+        assert!(matches!(
+            client.get_amount(Category::Pat, 0).await,
+            Err(NekosBestError::InvalidAmount { requested: 0, .. })
+        ));
+        assert_eq!(
+            *client.backend().urls.lock().unwrap(),
+            ["http://localhost/pat", "http://localhost/pat?amount=3"]
+        );
+        assert_eq!(
+            *client.backend().headers.lock().unwrap(),
+            [("User-Agent".to_owned(), USER_AGENT.to_owned())]
+        );
+
+        let client = canned(200, &[("Anime_Name", "Sewayaki+Kitsune")], "");
+        let details = client
+            .gif_details("http://localhost/pat/a.gif")
+            .await
+            .unwrap();
+        assert_eq!(details.anime_name, "Sewayaki Kitsune");
+    }
+
+    #[cfg(all(feature = "hyper", feature = "test-util"))]
+    #[tokio::test]
+    async fn hyper() {
+        let server = crate::test_util::MockServer::start().unwrap();
+        let client = BackendClient::with_base_url(hyper::Client::new(), server.base_url());
+
+        let image = client.get(Category::Neko).await.unwrap();
+        let details = client.image_details(image.url).await.unwrap();
+        assert_eq!(details.artist.name, crate::test_util::ARTIST_NAME);
+        assert!(matches!(
+            client
+                .image_details(format!("{}/neko/a.gif", server.base_url()))
+                .await,
+            Err(NekosBestError::Status(404))
+        ));
+    }
+
+    #[cfg(all(feature = "reqwest", feature = "test-util", not(feature = "blocking")))]
+    #[tokio::test]
+    async fn client() {
+        use crate::test_util::{Fault, MockServer};
+
+        let server = MockServer::start().unwrap();
+        let client = crate::client::Client::builder()
+            .base_url(server.base_url())
+            .retry_rate_limited(1)
+            .build()
+            .unwrap();
+        let client = BackendClient::with_base_url(client, server.base_url());
+
+        // retried by the client, like its own requests
+        server.fail_next(
+            1,
+            Fault::RateLimited {
+                retry_after: Some(0),
+            },
+        );
+        let image = client.get(Category::Neko).await.unwrap();
+        assert_eq!(server.requests(), 2);
+        let details = client.image_details(image.url).await.unwrap();
+        assert_eq!(details.artist.name, crate::test_util::ARTIST_NAME);
+    }
+
+    #[tokio::test]
+    async fn status() {
+        assert!(matches!(
+            canned(429, &[], "").get(Category::Pat).await,
+            Err(NekosBestError::RateLimited { retry_after: None })
+        ));
+        assert!(matches!(
+            canned(404, &[], "").get(Category::Pat).await,
+            Err(NekosBestError::Status(404))
+        ));
+    }
+}
@@ -0,0 +1,377 @@
+//! Caching responses in the client, see
+//! [`crate::client::ClientBuilder::cache`] and
+//! [`crate::client::ClientBuilder::disk_cache`].
+
+use std::collections::HashMap;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::rt::Instant;
+use crate::{Category, NekosBestResponse};
+
+/// What responses are cached by: the category, and the amount of images,
+/// [`None`] for requests without an amount.
+pub type CacheKey = (Category, Option<u8>);
+
+/// A cache of responses the client can use, see
+/// [`crate::client::ClientBuilder::cache_layer`].
+///
+/// Implemented by [`MemoryCache`], and by `moka::sync::Cache` with the
+/// `moka` feature. Implementations are responsible for expiring and
+/// evicting the entries.
+pub trait CacheLayer: Send + Sync {
+    /// The cached response for `key`, if there is a fresh one.
+    fn get(&self, key: &CacheKey) -> Option<NekosBestResponse>;
+
+    /// Caches `response` for `key`.
+    fn insert(&self, key: CacheKey, response: NekosBestResponse);
+}
+
+/// An in-memory cache of responses, evicting the least recently used
+/// entry when full.
+pub struct MemoryCache {
+    ttl: Duration,
+    max_entries: usize,
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    entries: HashMap<CacheKey, Entry>,
+    /// Incremented on every access, to find the least recently used entry.
+    clock: u64,
+}
+
+struct Entry {
+    response: NekosBestResponse,
+    inserted_at: Instant,
+    last_used: u64,
+}
+
+impl MemoryCache {
+    /// Creates a cache keeping up to `max_entries` responses, each for `ttl`.
+    pub fn new(ttl: Duration, max_entries: usize) -> Self {
+        Self {
+            ttl,
+            max_entries,
+            state: Mutex::default(),
+        }
+    }
+
+    /// The cached response for `key`, if there is one, and it is not
+    /// older than the ttl.
+    pub fn get(&self, key: &CacheKey) -> Option<NekosBestResponse> {
+        let mut state = self.state.lock().unwrap();
+        state.clock += 1;
+        let clock = state.clock;
+
+        match state.entries.get_mut(key) {
+            Some(entry) if entry.inserted_at.elapsed() < self.ttl => {
+                entry.last_used = clock;
+                Some(entry.response.clone())
+            }
+            Some(_) => {
+                state.entries.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Caches `response` for `key`.
+    pub fn insert(&self, key: CacheKey, response: NekosBestResponse) {
+        if self.max_entries == 0 {
+            return;
+        }
+
+        let mut state = self.state.lock().unwrap();
+        state.clock += 1;
+        let clock = state.clock;
+
+        if !state.entries.contains_key(&key) && state.entries.len() >= self.max_entries {
+            let ttl = self.ttl;
+            state
+                .entries
+                .retain(|_, entry| entry.inserted_at.elapsed() < ttl);
+            if state.entries.len() >= self.max_entries {
+                let lru = state
+                    .entries
+                    .iter()
+                    .min_by_key(|(_, entry)| entry.last_used)
+                    .map(|(key, _)| *key);
+                if let Some(lru) = lru {
+                    state.entries.remove(&lru);
+                }
+            }
+        }
+
+        state.entries.insert(
+            key,
+            Entry {
+                response,
+                inserted_at: Instant::now(),
+                last_used: clock,
+            },
+        );
+    }
+
+    /// How many responses are cached, including expired ones that were
+    /// not evicted yet.
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn clear(&self) {
+        self.state.lock().unwrap().entries.clear();
+    }
+}
+
+impl CacheLayer for MemoryCache {
+    fn get(&self, key: &CacheKey) -> Option<NekosBestResponse> {
+        MemoryCache::get(self, key)
+    }
+
+    fn insert(&self, key: CacheKey, response: NekosBestResponse) {
+        MemoryCache::insert(self, key, response);
+    }
+}
+
+/// Configure the capacity and expiration of the cache with its builder,
+/// e.g. `Cache::builder().max_capacity(100).time_to_live(ttl).build()`.
+#[cfg(feature = "moka")]
+impl CacheLayer for moka::sync::Cache<CacheKey, NekosBestResponse> {
+    fn get(&self, key: &CacheKey) -> Option<NekosBestResponse> {
+        moka::sync::Cache::get(self, key)
+    }
+
+    fn insert(&self, key: CacheKey, response: NekosBestResponse) {
+        moka::sync::Cache::insert(self, key, response);
+    }
+}
+
+/// A cache of responses on disk, as a directory of the JSON bodies
+/// returned by the API, one file per [`CacheKey`].
+///
+/// Unlike [`MemoryCache`], it survives restarts, making it useful for
+/// command line tools that are run repeatedly. The age of a response is
+/// the time since its file was last modified.
+///
+/// Not available on wasm32.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone)]
+pub struct DiskCache {
+    dir: PathBuf,
+    ttl: Duration,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl DiskCache {
+    /// Creates a cache keeping the responses in `dir` (created if
+    /// needed), each for `ttl`.
+    pub fn new(dir: impl Into<PathBuf>, ttl: Duration) -> Self {
+        Self {
+            dir: dir.into(),
+            ttl,
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The path of the file the response for `key` is kept in.
+    pub fn path(&self, key: &CacheKey) -> PathBuf {
+        let name = match key {
+            (category, Some(amount)) => format!("{}-{amount}.json", category.to_url_name()),
+            (category, None) => format!("{}.json", category.to_url_name()),
+        };
+        self.dir.join(name)
+    }
+
+    /// The cached response for `key`, if there is one, and it is not
+    /// older than the ttl.
+    ///
+    /// Files that can't be read or parsed are treated as missing.
+    pub fn get(&self, key: &CacheKey) -> Option<NekosBestResponse> {
+        self.read(key, false)
+    }
+
+    /// The cached response for `key`, if there is one, even if it is
+    /// older than the ttl. In that case, all its images are marked as
+    /// [`stale`](crate::NekosBestResponseSingle::stale).
+    pub fn get_stale(&self, key: &CacheKey) -> Option<NekosBestResponse> {
+        self.read(key, true)
+    }
+
+    fn read(&self, key: &CacheKey, allow_stale: bool) -> Option<NekosBestResponse> {
+        let path = self.path(key);
+        let age = std::fs::metadata(&path)
+            .and_then(|metadata| metadata.modified())
+            .ok()?
+            .elapsed()
+            .unwrap_or_default();
+        let stale = age >= self.ttl;
+        if stale && !allow_stale {
+            return None;
+        }
+
+        let mut response = crate::sans_io::parse_response(&std::fs::read(path).ok()?).ok()?;
+        for image in response.iter_mut() {
+            image.stale = stale;
+        }
+        Some(response)
+    }
+
+    /// Caches `body`, the body of the response for `key`.
+    ///
+    /// The file is written next to its final path, then renamed, so that
+    /// concurrent readers never see a partially written response.
+    ///
+    /// # Errors
+    /// Any I/O error creating the directory or writing the file.
+    pub fn insert(&self, key: &CacheKey, body: &[u8]) -> std::io::Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        let path = self.path(key);
+        let tmp = path.with_extension(format!("json.{}.tmp", std::process::id()));
+        std::fs::write(&tmp, body)?;
+        std::fs::rename(tmp, path)
+    }
+
+    /// Removes the cached response for `key`, if any.
+    ///
+    /// # Errors
+    /// Any I/O error removing the file, except it not existing.
+    pub fn remove(&self, key: &CacheKey) -> std::io::Result<()> {
+        match std::fs::remove_file(self.path(key)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn body(url: &str) -> String {
+        format!(r#"{{"results":[{{"anime_name":"a","url":"http://localhost/{url}"}}]}}"#)
+    }
+
+    fn response(url: &str) -> NekosBestResponse {
+        crate::sans_io::parse_response(body(url).as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn lru() {
+        let cache = MemoryCache::new(Duration::from_secs(60), 2);
+        cache.insert((Category::Hug, None), response("hug"));
+        cache.insert((Category::Pat, Some(2)), response("pat"));
+        assert_eq!(
+            cache.get(&(Category::Hug, None)).unwrap()[0].url,
+            "http://localhost/hug"
+        );
+
+        // pat is the least recently used
+        cache.insert((Category::Wave, None), response("wave"));
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get(&(Category::Pat, Some(2))).is_none());
+        assert!(cache.get(&(Category::Hug, None)).is_some());
+        assert!(cache.get(&(Category::Wave, None)).is_some());
+    }
+
+    #[test]
+    fn ttl() {
+        let cache = MemoryCache::new(Duration::ZERO, 10);
+        cache.insert((Category::Hug, None), response("hug"));
+        assert!(cache.get(&(Category::Hug, None)).is_none());
+        assert!(cache.is_empty());
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn temp_dir(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("nekosbest-{name}-test-{}", std::process::id()))
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn disk() {
+        let dir = temp_dir("cache");
+        let cache = DiskCache::new(&dir, Duration::from_secs(60));
+        let key = (Category::Hug, Some(2));
+        assert_eq!(cache.path(&key), dir.join("hug-2.json"));
+        assert!(cache.get(&key).is_none());
+
+        cache.insert(&key, body("hug").as_bytes()).unwrap();
+        assert_eq!(cache.get(&key).unwrap()[0].url, "http://localhost/hug");
+        assert!(cache.get(&(Category::Hug, None)).is_none());
+        assert!(!cache.get(&key).unwrap()[0].stale);
+        let expired = DiskCache::new(&dir, Duration::ZERO);
+        assert!(expired.get(&key).is_none());
+        assert!(expired.get_stale(&key).unwrap()[0].stale);
+
+        std::fs::write(cache.path(&key), "{").unwrap();
+        assert!(cache.get(&key).is_none());
+
+        cache.remove(&key).unwrap();
+        cache.remove(&key).unwrap();
+        assert!(!cache.path(&key).exists());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(all(
+        feature = "reqwest",
+        not(target_arch = "wasm32"),
+        not(feature = "blocking")
+    ))]
+    #[tokio::test]
+    async fn offline_fallback() {
+        let dir = temp_dir("offline");
+        let key = (Category::Hug, None);
+        let client = |fallback| {
+            crate::client::Client::builder()
+                .base_url("http://127.0.0.1:1")
+                .disk_cache(&dir, Duration::ZERO)
+                .offline_fallback(fallback)
+                .build()
+                .unwrap()
+        };
+        client(false)
+            .disk_cache()
+            .unwrap()
+            .insert(&key, body("hug").as_bytes())
+            .unwrap();
+
+        assert!(crate::get_with_client(&client(false), Category::Hug)
+            .await
+            .is_err());
+        let image = crate::get_with_client(&client(true), Category::Hug)
+            .await
+            .unwrap();
+        assert_eq!(image.url, "http://localhost/hug");
+        assert!(image.stale);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(feature = "moka")]
+    #[test]
+    fn moka() {
+        let cache: Box<dyn CacheLayer> = Box::new(
+            moka::sync::Cache::builder()
+                .max_capacity(10)
+                .time_to_live(Duration::from_secs(60))
+                .build(),
+        );
+        cache.insert((Category::Hug, None), response("hug"));
+        assert_eq!(
+            cache.get(&(Category::Hug, None)).unwrap()[0].url,
+            "http://localhost/hug"
+        );
+        assert!(cache.get(&(Category::Pat, None)).is_none());
+    }
+}
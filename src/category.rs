@@ -1,10 +1,26 @@
-use std::{convert::TryFrom, str::FromStr};
+use std::{
+    collections::HashMap,
+    convert::TryFrom,
+    str::FromStr,
+    sync::{OnceLock, RwLock},
+};
+
+use crate::endpoints::{EmbeddedEndpoint, ImageFormat};
 
 macro_rules! categories {
+    (@opt) => { None };
+    (@opt $value:literal) => { Some($value) };
     ($(
         $(#[$at:meta])* $(ref $(#[$ref_at:meta])*)?
-        $cat_name:ident => $url_name:literal,
+        $cat_name:ident => $url_name:literal $(| $alias:literal)* as $format:ident
+            $(($min:literal ..= $max:literal))?,
     )*) => {
+        /// A category (endpoint) of the API.
+        ///
+        /// Parsing with [`FromStr`] accepts aliases and reports close
+        /// matches on failure, see [`Category::from_alias`] and [`NoSuchVariant`].
+        /// serenity implements its `ArgumentConvert` for every [`FromStr`] type,
+        /// so a `Category` can be taken directly as a prefix-command argument.
         #[derive(
             Debug,
             Clone,
@@ -29,8 +45,12 @@ macro_rules! categories {
         impl FromStr for Category {
             type Err = NoSuchVariant;
 
+            /// Parses a category from its url name, or any of its aliases,
+            /// see [`Category::from_alias`].
             fn from_str(s: &str) -> Result<Self, Self::Err> {
-                Self::from_url_name(s).ok_or(NoSuchVariant)
+                Self::from_url_name(s)
+                    .or_else(|| Self::from_alias(s))
+                    .ok_or_else(|| NoSuchVariant::new(s))
             }
         }
 
@@ -44,6 +64,18 @@ macro_rules! categories {
                 }
             }
 
+            /// The format of the images served by this category, as
+            /// advertised by the `/endpoints` endpoint at the time of
+            /// the release of this crate.
+            pub const fn format(self) -> ImageFormat {
+                match self {
+                    $(
+                        $(#[$ref_at])*
+                        Category::$cat_name => ImageFormat::$format,
+                    )*
+                }
+            }
+
             pub fn from_url_name(name: &str) -> Option<Self> {
                 match name {
                     $(
@@ -53,6 +85,35 @@ macro_rules! categories {
                 }
             }
 
+            fn from_builtin_alias(normalized: &str) -> Option<Self> {
+                match normalized {
+                    $(
+                        $url_name $(| $alias)* => Some($(#[$ref_at])* {Category::$cat_name}),
+                    )*
+                    _ => None,
+                }
+            }
+
+            /// The url names and built-in aliases of all the categories.
+            const NAMES: &'static [(Category, &'static [&'static str])] = &[
+                $(
+                    $(#[$ref_at])*
+                    (Category::$cat_name, &[$url_name $(, $alias)*]),
+                )*
+            ];
+
+            pub(crate) const EMBEDDED_ENDPOINTS: &'static [EmbeddedEndpoint] = &[
+                $(
+                    $(#[$ref_at])*
+                    EmbeddedEndpoint {
+                        category: Category::$cat_name,
+                        format: ImageFormat::$format,
+                        min: categories!(@opt $($min)?),
+                        max: categories!(@opt $($max)?),
+                    },
+                )*
+            ];
+
             pub const ALL_VARIANTS: &[Category] = &[
                 $(
                     $(#[$ref_at])*
@@ -64,55 +125,335 @@ macro_rules! categories {
 }
 
 categories! {
-    Baka => "baka",
-    Bite => "bite",
-    Blush => "blush",
-    Bored => "bored",
-    Cry => "cry",
-    Cuddle => "cuddle",
-    Dance => "dance",
-    Facepalm => "facepalm",
-    Feed => "feed",
-    Handhold => "handhold",
-    Handshake => "handshake",
-    Happy => "happy",
-    Highfive => "highfive",
-    Hug => "hug",
-    Husbando => "husbando",
-    Kick => "kick",
-    Kiss => "kiss",
-    Kitsune => "kitsune",
-    Laugh => "laugh",
-    Lurk => "lurk",
-    Neko => "neko",
-    Nod => "nod",
-    Nom => "nom",
-    Nope => "nope",
-    Pat => "pat",
-    Peck => "peck",
-    Poke => "poke",
-    Pout => "pout",
-    Punch => "punch",
-    Shoot => "shoot",
-    Shrug => "shrug",
-    Slap => "slap",
-    Sleep => "sleep",
-    Smile => "smile",
-    Smug => "smug",
-    Stare => "stare",
-    Think => "think",
-    ThumbsUp => "thumbsup",
-    Tickle => "tickle",
-    Waifu => "waifu",
-    Wave => "wave",
-    Wink => "wink",
-    Yawn => "yawn",
-    Yeet => "yeet",
-}
-
-#[derive(thiserror::Error, Debug)]
-#[error("no such variant")]
-pub struct NoSuchVariant;
+    Baka => "baka" as Gif,
+    Bite => "bite" as Gif,
+    Blush => "blush" as Gif,
+    Bored => "bored" as Gif,
+    Cry => "cry" as Gif,
+    Cuddle => "cuddle" as Gif,
+    Dance => "dance" as Gif,
+    Facepalm => "facepalm" as Gif,
+    Feed => "feed" as Gif,
+    Handhold => "handhold" | "handholding" | "holdhands" as Gif,
+    Handshake => "handshake" as Gif,
+    Happy => "happy" as Gif,
+    Highfive => "highfive" | "hi5" | "high5" as Gif,
+    Hug => "hug" as Gif,
+    Husbando => "husbando" as Png ("0001" ..= "0100"),
+    Kick => "kick" as Gif,
+    Kiss => "kiss" as Gif,
+    Kitsune => "kitsune" | "foxgirl" as Png ("0001" ..= "0200"),
+    Laugh => "laugh" as Gif,
+    Lurk => "lurk" as Gif,
+    Neko => "neko" | "nekos" | "catgirl" as Png ("0001" ..= "0400"),
+    Nod => "nod" as Gif,
+    Nom => "nom" as Gif,
+    Nope => "nope" as Gif,
+    Pat => "pat" | "headpat" as Gif,
+    Peck => "peck" as Gif,
+    Poke => "poke" as Gif,
+    Pout => "pout" as Gif,
+    Punch => "punch" as Gif,
+    Shoot => "shoot" as Gif,
+    Shrug => "shrug" as Gif,
+    Slap => "slap" as Gif,
+    Sleep => "sleep" as Gif,
+    Smile => "smile" as Gif,
+    Smug => "smug" as Gif,
+    Stare => "stare" as Gif,
+    Think => "think" as Gif,
+    ThumbsUp => "thumbsup" | "thumbup" as Gif,
+    Tickle => "tickle" as Gif,
+    Waifu => "waifu" as Png ("0001" ..= "0300"),
+    Wave => "wave" as Gif,
+    Wink => "wink" as Gif,
+    Yawn => "yawn" as Gif,
+    Yeet => "yeet" as Gif,
+}
+
+/// The error returned when parsing an unknown category.
+///
+/// Its [`Display`](std::fmt::Display) implementation is suitable for
+/// showing to users directly, like "unknown category `pta`, did you mean
+/// `pat`?".
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NoSuchVariant {
+    name: String,
+    suggestions: Vec<Category>,
+}
+
+impl NoSuchVariant {
+    /// How many suggestions are kept at most.
+    const MAX_SUGGESTIONS: usize = 3;
+
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            suggestions: Category::close_matches(name)
+                .take(Self::MAX_SUGGESTIONS)
+                .collect(),
+        }
+    }
+
+    /// The name that failed to parse.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The closest categories to [`NoSuchVariant::name`], closest first.
+    /// Might be empty, if nothing was close enough.
+    pub fn suggestions(&self) -> &[Category] {
+        &self.suggestions
+    }
+}
+
+impl std::fmt::Display for NoSuchVariant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "unknown category `{}`", self.name)?;
+
+        if let Some((last, rest)) = self.suggestions.split_last() {
+            f.write_str(", did you mean ")?;
+            for suggestion in rest {
+                write!(f, "`{suggestion}`, ")?;
+            }
+            if !rest.is_empty() {
+                f.write_str("or ")?;
+            }
+            write!(f, "`{last}`?")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for NoSuchVariant {}
+
+/// Another name for [`NoSuchVariant`], the error returned by
+/// `str::parse::<Category>`.
+pub type UnknownCategoryError = NoSuchVariant;
+
+fn alias_registry() -> &'static RwLock<HashMap<String, Category>> {
+    static REGISTRY: OnceLock<RwLock<HashMap<String, Category>>> = OnceLock::new();
+    REGISTRY.get_or_init(Default::default)
+}
+
+/// Lowercases `name`, and strips whitespace, `-` and `_` from it,
+/// so that e.g. `"High-Five"` and `"high five"` resolve the same.
+fn normalize_alias(name: &str) -> String {
+    name.chars()
+        .filter(|c| !c.is_whitespace() && *c != '-' && *c != '_')
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// The optimal string alignment distance between `a` and `b`: the
+/// number of insertions, deletions, substitutions and transpositions
+/// of adjacent characters needed to turn one into the other.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a = a.chars().collect::<Vec<_>>();
+    let b = b.chars().collect::<Vec<_>>();
+
+    // d[i][j] is the distance between a[..i] and b[..j]
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in d[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            d[i][j] = (d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1)
+                .min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+
+    d[a.len()][b.len()]
+}
+
+/// A category requested by its url name, which might not be known to
+/// the crate yet.
+///
+/// The API adds categories faster than the crate gets released, so this
+/// allows requesting them in the meantime, with
+/// [`crate::get_custom_with_client`]. See also [`Category::custom`].
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CustomCategory(String);
+
+impl CustomCategory {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self(name.into())
+    }
+
+    /// The url name of the category.
+    pub fn name(&self) -> &str {
+        &self.0
+    }
+
+    /// The complete url of the endpoint of this category, like [`Category::to_url`].
+    pub fn to_url(&self, base_url: &str) -> String {
+        format!("{}/{}", base_url.trim_end_matches('/'), self.0)
+    }
+
+    /// The known category with this name, if any.
+    pub fn known(&self) -> Option<Category> {
+        Category::from_url_name(&self.0)
+    }
+}
+
+impl From<Category> for CustomCategory {
+    fn from(category: Category) -> Self {
+        Self::new(category.to_url_name())
+    }
+}
+
+impl std::fmt::Display for CustomCategory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl Category {
+    /// A category by its url name, e.g. for categories the API added
+    /// after the release of this crate, see [`CustomCategory`].
+    pub fn custom(name: impl Into<String>) -> CustomCategory {
+        CustomCategory::new(name)
+    }
+
+    /// All the categories, in alphabetical order. The same as
+    /// [`Category::ALL_VARIANTS`].
+    pub const ALL: &'static [Category] = Self::ALL_VARIANTS;
+
+    /// Iterates over all the categories, see [`Category::ALL`].
+    pub fn iter() -> impl ExactSizeIterator<Item = Category> {
+        Self::ALL.iter().copied()
+    }
+
+    /// Whether this category serves gifs, according to
+    /// [`Category::format`].
+    pub const fn is_gif(self) -> bool {
+        matches!(self.format(), ImageFormat::Gif)
+    }
+
+    /// Whether this category serves static images, according to
+    /// [`Category::format`].
+    pub const fn is_image(self) -> bool {
+        matches!(self.format(), ImageFormat::Png)
+    }
+
+    /// The path of the endpoint of this category, relative to the base
+    /// url of the API, e.g. `"/neko"`.
+    pub fn to_url_path(self) -> String {
+        format!("/{}", self.to_url_name())
+    }
+
+    /// The complete url of the endpoint of this category, for an API
+    /// at `base_url`, e.g. [`crate::BASE_URL`].
+    pub fn to_url(self, base_url: &str) -> String {
+        format!("{}{}", base_url.trim_end_matches('/'), self.to_url_path())
+    }
+
+    /// Resolves a category from a user-typed name, like `"catgirl"`
+    /// or `"High-Five"`.
+    ///
+    /// Matching ignores case, whitespace, `-` and `_`, and considers the
+    /// url names, the aliases built into the crate, and the aliases
+    /// registered with [`Category::register_alias`].
+    pub fn from_alias(name: &str) -> Option<Self> {
+        let normalized = normalize_alias(name);
+        Self::from_builtin_alias(&normalized)
+            .or_else(|| alias_registry().read().unwrap().get(&normalized).copied())
+    }
+
+    /// Resolves a category like [`Category::from_alias`], but also
+    /// tolerates minor typos, like `"hugg"` or `"kitsnue"`.
+    ///
+    /// Returns the closest name, if it is within an edit distance of
+    /// a third of the length of `name` (but at least 1).
+    pub fn parse_fuzzy(name: &str) -> Option<Self> {
+        Self::close_matches(name).next()
+    }
+
+    /// The categories close enough to `name` for [`Category::parse_fuzzy`],
+    /// closest first.
+    fn close_matches(name: &str) -> impl Iterator<Item = Category> {
+        let normalized = normalize_alias(name);
+        let max_distance = (normalized.chars().count() / 3).max(1);
+
+        Self::ranked_matches(&normalized)
+            .into_iter()
+            .take_while(move |(distance, _)| *distance <= max_distance)
+            .map(|(_, category)| category)
+    }
+
+    /// All the categories, along with the edit distance between
+    /// `normalized` and their closest name, closest first.
+    fn ranked_matches(normalized: &str) -> Vec<(usize, Category)> {
+        let registered = alias_registry().read().unwrap();
+        let names = Self::NAMES
+            .iter()
+            .flat_map(|(category, names)| names.iter().map(|name| (*name, *category)))
+            .chain(
+                registered
+                    .iter()
+                    .map(|(name, category)| (name.as_str(), *category)),
+            );
+
+        let mut best = HashMap::<Category, usize>::new();
+        for (name, category) in names {
+            let distance = edit_distance(normalized, name);
+            best.entry(category)
+                .and_modify(|d| *d = (*d).min(distance))
+                .or_insert(distance);
+        }
+
+        let mut ranked = best
+            .into_iter()
+            .map(|(category, distance)| (distance, category))
+            .collect::<Vec<_>>();
+        ranked.sort();
+        ranked
+    }
+
+    /// The categories with a name starting with `partial`, in
+    /// [`Category::ALL_VARIANTS`] order, for completing user input.
+    ///
+    /// Matching normalizes `partial` like [`Category::from_alias`], and
+    /// considers the same names.
+    #[cfg_attr(not(feature = "poise"), allow(dead_code))]
+    pub(crate) fn completions(partial: &str) -> Vec<Category> {
+        let normalized = normalize_alias(partial);
+        let registered = alias_registry().read().unwrap();
+
+        Self::NAMES
+            .iter()
+            .filter(|(category, names)| {
+                names.iter().any(|name| name.starts_with(&normalized))
+                    || registered
+                        .iter()
+                        .any(|(name, c)| c == category && name.starts_with(&normalized))
+            })
+            .map(|(category, _)| *category)
+            .collect()
+    }
+
+    /// Registers an extra alias for `category`, process-wide.
+    ///
+    /// Aliases are normalized the same way as in [`Category::from_alias`],
+    /// and cannot override the built-in names.
+    pub fn register_alias(alias: &str, category: Category) {
+        alias_registry()
+            .write()
+            .unwrap()
+            .insert(normalize_alias(alias), category);
+    }
+}
 
 impl TryFrom<String> for Category {
     type Error = <Self as FromStr>::Err;
@@ -122,9 +463,17 @@ impl TryFrom<String> for Category {
     }
 }
 
-impl Into<String> for Category {
-    fn into(self) -> String {
-        self.to_url_name().to_owned()
+impl TryFrom<&str> for Category {
+    type Error = <Self as FromStr>::Err;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Category> for String {
+    fn from(category: Category) -> Self {
+        category.to_url_name().to_owned()
     }
 }
 
@@ -133,3 +482,134 @@ impl std::fmt::Display for Category {
         self.to_url_name().fmt(f)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn urls() {
+        assert_eq!(Category::ThumbsUp.to_url_path(), "/thumbsup");
+        assert_eq!(
+            Category::Neko.to_url(crate::BASE_URL),
+            "https://nekos.best/api/v2/neko"
+        );
+        assert_eq!(
+            Category::Neko.to_url("http://localhost/"),
+            "http://localhost/neko"
+        );
+    }
+
+    #[test]
+    fn formats() {
+        assert!(Category::Neko.is_image());
+        assert!(!Category::Neko.is_gif());
+        assert!(Category::Pat.is_gif());
+        for category in Category::iter() {
+            assert_ne!(category.is_gif(), category.is_image());
+        }
+    }
+
+    #[test]
+    fn custom() {
+        let custom = Category::custom("newcategory");
+        assert_eq!(
+            custom.to_url("http://localhost"),
+            "http://localhost/newcategory"
+        );
+        assert_eq!(custom.known(), None);
+        assert_eq!(
+            CustomCategory::from(Category::Yawn).known(),
+            Some(Category::Yawn)
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        for category in Category::iter() {
+            assert_eq!(category.to_url_name().parse(), Ok(category));
+            assert_eq!(
+                Category::try_from(&category.to_url_path()[1..]),
+                Ok(category)
+            );
+        }
+    }
+
+    /// Registers an alias for the duration of a test, removing it from the
+    /// (process-wide) registry when dropped, so that it doesn't leak into
+    /// the other tests.
+    struct Registered(&'static str);
+
+    impl Registered {
+        fn new(alias: &'static str, category: Category) -> Self {
+            Category::register_alias(alias, category);
+            Self(alias)
+        }
+    }
+
+    impl Drop for Registered {
+        fn drop(&mut self) {
+            alias_registry()
+                .write()
+                .unwrap()
+                .remove(&normalize_alias(self.0));
+        }
+    }
+
+    #[test]
+    fn aliases() {
+        assert_eq!("neko".parse::<Category>().unwrap(), Category::Neko);
+        assert_eq!("catgirl".parse::<Category>().unwrap(), Category::Neko);
+        assert_eq!("High-Five".parse::<Category>().unwrap(), Category::Highfive);
+        assert_eq!("thumbs up".parse::<Category>().unwrap(), Category::ThumbsUp);
+        assert!("test-aliases-snuggle".parse::<Category>().is_err());
+
+        {
+            let _alias = Registered::new("Test-Aliases-Snuggle", Category::Cuddle);
+            assert_eq!(
+                "test aliases snuggle".parse::<Category>().unwrap(),
+                Category::Cuddle
+            );
+        }
+        assert!("test-aliases-snuggle".parse::<Category>().is_err());
+
+        // Only this test registers `neko`, so removing it afterwards is safe.
+        let _alias = Registered::new("neko", Category::Hug);
+        assert_eq!("neko".parse::<Category>().unwrap(), Category::Neko);
+    }
+
+    #[test]
+    fn fuzzy() {
+        assert_eq!(edit_distance("pta", "pat"), 1);
+        assert_eq!(edit_distance("hugg", "hug"), 1);
+        assert_eq!(edit_distance("", "hug"), 3);
+
+        assert_eq!(Category::parse_fuzzy("hugg"), Some(Category::Hug));
+        assert_eq!(Category::parse_fuzzy("Kitsnue"), Some(Category::Kitsune));
+        assert_eq!(Category::parse_fuzzy("catgrl"), Some(Category::Neko));
+        assert_eq!(Category::parse_fuzzy("wave"), Some(Category::Wave));
+        assert_eq!(Category::parse_fuzzy("xyzzy"), None);
+    }
+
+    #[test]
+    fn suggestions() {
+        let err = "pta".parse::<Category>().unwrap_err();
+        assert_eq!(err.name(), "pta");
+        assert_eq!(err.suggestions()[0], Category::Pat);
+        assert!(err
+            .to_string()
+            .starts_with("unknown category `pta`, did you mean `pat`"));
+
+        let err = "xyzzy".parse::<Category>().unwrap_err();
+        assert!(err.suggestions().is_empty());
+        assert_eq!(err.to_string(), "unknown category `xyzzy`");
+    }
+
+    #[test]
+    fn completions() {
+        assert_eq!(Category::completions("hug"), [Category::Hug]);
+        assert_eq!(Category::completions("Cat-G"), [Category::Neko]);
+        assert_eq!(Category::completions(""), Category::ALL_VARIANTS);
+        assert!(Category::completions("xyzzy").is_empty());
+    }
+}
@@ -0,0 +1,123 @@
+//! The circuit breaker of [`crate::client::Client`].
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::rt::Instant;
+use crate::NekosBestError;
+
+/// The state of the circuit breaker of a client, see
+/// [`crate::client::ClientBuilder::circuit_breaker`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Requests are sent as usual.
+    Closed,
+    /// Requests fail right away with [`NekosBestError::CircuitOpen`].
+    Open,
+    /// The cooldown is over, and a single request is let through to
+    /// check whether the API is back.
+    HalfOpen,
+}
+
+enum State {
+    Closed { failures: u32 },
+    Open { until: Instant },
+    HalfOpen { trial_started_at: Instant },
+}
+
+pub(crate) struct CircuitBreaker {
+    threshold: u32,
+    cooldown: Duration,
+    state: Mutex<State>,
+}
+
+impl CircuitBreaker {
+    pub(crate) fn new(threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            threshold: threshold.max(1),
+            cooldown,
+            state: Mutex::new(State::Closed { failures: 0 }),
+        }
+    }
+
+    pub(crate) fn state(&self) -> CircuitState {
+        match *self.state.lock().unwrap() {
+            State::Closed { .. } => CircuitState::Closed,
+            State::Open { until } if until > Instant::now() => CircuitState::Open,
+            State::Open { .. } | State::HalfOpen { .. } => CircuitState::HalfOpen,
+        }
+    }
+
+    /// Checks whether a request may be sent now.
+    ///
+    /// # Errors
+    /// [`NekosBestError::CircuitOpen`] if the circuit is open, or another
+    /// request is already checking whether the API is back.
+    pub(crate) fn check(&self, now: Instant) -> Result<(), NekosBestError> {
+        let mut state = self.state.lock().unwrap();
+        let retry_after = match *state {
+            State::Closed { .. } => return Ok(()),
+            State::Open { until } if until > now => until - now,
+            // a trial request that never reported back, e.g. because its
+            // future was dropped, doesn't keep the circuit half-open forever
+            State::HalfOpen { trial_started_at } if now < trial_started_at + self.cooldown => {
+                trial_started_at + self.cooldown - now
+            }
+            State::Open { .. } | State::HalfOpen { .. } => {
+                *state = State::HalfOpen {
+                    trial_started_at: now,
+                };
+                return Ok(());
+            }
+        };
+
+        Err(NekosBestError::CircuitOpen { retry_after })
+    }
+
+    /// Records the outcome of a request let through by [`CircuitBreaker::check`].
+    pub(crate) fn record(&self, now: Instant, success: bool) {
+        let mut state = self.state.lock().unwrap();
+        *state = match (&*state, success) {
+            (_, true) => State::Closed { failures: 0 },
+            (State::Closed { failures }, false) if failures + 1 < self.threshold => State::Closed {
+                failures: failures + 1,
+            },
+            (_, false) => State::Open {
+                until: now + self.cooldown,
+            },
+        };
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn opens_and_recovers() {
+        let breaker = CircuitBreaker::new(2, Duration::from_secs(10));
+        let start = Instant::now();
+
+        breaker.check(start).unwrap();
+        breaker.record(start, false);
+        breaker.check(start).unwrap();
+        breaker.record(start, false);
+        assert!(matches!(
+            breaker.check(start + Duration::from_secs(4)),
+            Err(NekosBestError::CircuitOpen { retry_after }) if retry_after == Duration::from_secs(6)
+        ));
+
+        // half-open, a single trial request goes through
+        let later = start + Duration::from_secs(10);
+        breaker.check(later).unwrap();
+        assert!(breaker.check(later).is_err());
+        breaker.record(later, false);
+        assert!(breaker.check(later + Duration::from_secs(1)).is_err());
+
+        let later = later + Duration::from_secs(10);
+        breaker.check(later).unwrap();
+        breaker.record(later, true);
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        breaker.check(later).unwrap();
+    }
+}
@@ -1,7 +1,25 @@
+#[cfg(feature = "blocking")]
+use nb_blocking_util::blocking;
 use std::ops::Add;
-use std::sync::Arc;
-use tokio::sync::Mutex;
-use tokio::time::Instant;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+use rand::Rng;
+
+use crate::backend::{HttpRequest, HttpResponse};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::cache::DiskCache;
+use crate::cache::{CacheLayer, MemoryCache};
+use crate::circuit_breaker::CircuitBreaker;
+use crate::details::{GifDetails, ImageDetails};
+use crate::endpoints::EndpointsMap;
+use crate::middleware::Middleware;
+use crate::ratelimit::RateLimiter;
+use crate::rt::Instant;
+use crate::{
+    Category, NekosBestError, NekosBestResponse, NekosBestResponseSingle, SearchQuery, BASE_URL,
+    USER_AGENT,
+};
 
 #[cfg(not(feature = "blocking"))]
 pub(crate) type ReqwestClient = reqwest::Client;
@@ -9,9 +27,9 @@ pub(crate) type ReqwestClient = reqwest::Client;
 pub(crate) type ReqwestClient = reqwest::blocking::Client;
 
 #[cfg(not(feature = "blocking"))]
-pub(crate) type ReqBuilder = reqwest::RequestBuilder;
+pub(crate) type ReqwestRequestBuilder = reqwest::RequestBuilder;
 #[cfg(feature = "blocking")]
-pub(crate) type ReqBuilder = reqwest::blocking::RequestBuilder;
+pub(crate) type ReqwestRequestBuilder = reqwest::blocking::RequestBuilder;
 
 #[cfg(not(feature = "blocking"))]
 pub(crate) type ReqwestResponse = reqwest::Response;
@@ -35,12 +53,204 @@ pub struct ClientConfig {
     pub search_ratelimit_behavior: SearchRatelimitBehavior,
 }
 
+/// The client used by the functions of the crate that don't take one,
+/// like [`crate::get`], set with [`set_default_client`].
+#[cfg(not(target_arch = "wasm32"))]
+static DEFAULT_CLIENT: RwLock<Option<Client>> = RwLock::new(None);
+
+// The wasm32 client is not `Sync` with the `coalesce` feature, and there is
+// a single thread anyway.
+#[cfg(target_arch = "wasm32")]
+thread_local! {
+    static DEFAULT_CLIENT: std::cell::RefCell<Option<Client>> =
+        const { std::cell::RefCell::new(None) };
+}
+
+/// The client used by the functions of the crate that don't take one, like
+/// [`crate::get`] (or a clone of it, sharing its connection pool and
+/// state). Built with [`Client::new`] on first use, unless set with
+/// [`set_default_client`] before.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn default_client() -> Client {
+    if let Some(client) = &*DEFAULT_CLIENT.read().unwrap() {
+        return client.clone();
+    }
+
+    DEFAULT_CLIENT
+        .write()
+        .unwrap()
+        .get_or_insert_with(|| Client::new(ClientConfig::default()))
+        .clone()
+}
+
+/// The client used by the functions of the crate that don't take one, like
+/// [`crate::get`] (or a clone of it, sharing its connection pool and
+/// state). Built with [`Client::new`] on first use, unless set with
+/// [`set_default_client`] before.
+#[cfg(target_arch = "wasm32")]
+pub fn default_client() -> Client {
+    DEFAULT_CLIENT.with(|client| {
+        client
+            .borrow_mut()
+            .get_or_insert_with(|| Client::new(ClientConfig::default()))
+            .clone()
+    })
+}
+
+/// Replaces the client used by the functions of the crate that don't take
+/// one, e.g. to set a proxy, a user agent or a cache for all of them.
+pub fn set_default_client(client: Client) {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        *DEFAULT_CLIENT.write().unwrap() = Some(client);
+    }
+    #[cfg(target_arch = "wasm32")]
+    DEFAULT_CLIENT.with(|default| *default.borrow_mut() = Some(client));
+}
+
+/// Makes the requests of the crate through an existing reqwest client:
+///
+/// ```no_run
+/// # async fn f() -> Result<(), nekosbest::NekosBestError> {
+/// use nekosbest::{client::NekosBestClientExt, Category};
+///
+/// let client = reqwest::Client::new();
+/// let image = client.nekos_best().get(Category::Neko).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub trait NekosBestClientExt {
+    /// A [`Client`] sending its requests through this client, with the
+    /// default settings.
+    ///
+    /// Each call returns a new [`Client`], so the state kept by clients
+    /// (like the rate limit of the search endpoint) isn't shared between
+    /// calls; keep the returned client around, or use
+    /// [`ClientBuilder::reqwest_client`], for that.
+    fn nekos_best(&self) -> Client;
+}
+
+impl NekosBestClientExt for ReqwestClient {
+    fn nekos_best(&self) -> Client {
+        Client::new_with_reqwest_client(self.clone(), ClientConfig::default())
+    }
+}
+
+#[cfg(all(feature = "reqwest-middleware", not(feature = "blocking")))]
+impl NekosBestClientExt for reqwest_middleware::ClientWithMiddleware {
+    fn nekos_best(&self) -> Client {
+        Client {
+            reqwest_middleware: Some(self.clone()),
+            ..Client::new(ClientConfig::default())
+        }
+    }
+}
+
+/// How requests failing with transient errors are retried, see
+/// [`ClientBuilder::retry_policy`].
+///
+/// Server errors (5xx), timeouts and connection errors are retried, with
+/// an exponential backoff: the `n`-th retry waits `base_delay * 2^(n-1)`,
+/// up to `max_delay`, randomly shortened by up to `jitter` of that.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct RetryPolicy {
+    max_retries: u32,
+    base_delay: Duration,
+    max_delay: Duration,
+    jitter: f64,
+}
+
+impl Default for RetryPolicy {
+    /// 3 retries, starting at 500ms, up to 30s, with a jitter of 0.5.
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            jitter: 0.5,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// The delay before the first retry.
+    pub fn base_delay(mut self, base_delay: Duration) -> Self {
+        self.base_delay = base_delay;
+        self
+    }
+
+    /// The longest delay between two retries, before the jitter.
+    pub fn max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// The fraction of the delay, from 0 to 1, that is randomly cut off,
+    /// so that many clients failing at once don't retry all together.
+    pub fn jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    /// The delay before the `retry`-th retry (from 1), or [`None`] if
+    /// there are no retries left.
+    fn delay(&self, retry: u32, rng: &mut impl Rng) -> Option<Duration> {
+        if retry > self.max_retries {
+            return None;
+        }
+
+        let backoff = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(retry - 1))
+            .min(self.max_delay);
+        Some(backoff.mul_f64(1.0 - rng.gen_range(0.0..=self.jitter)))
+    }
+}
+
+#[derive(Clone)]
 pub struct Client {
     pub(crate) client: ReqwestClient,
     client_config: ClientConfig,
+    base_url: String,
+    default_amount: u8,
+    clamp_amount: bool,
     search_ratelimit_data: Arc<Mutex<Option<SearchRatelimitData>>>,
+    endpoints: Arc<RwLock<Option<Arc<EndpointsMap>>>>,
+    unknown_endpoints_callback: Option<UnknownEndpointsCallback>,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    rate_limited_retries: u32,
+    retry_policy: Option<RetryPolicy>,
+    circuit_breaker: Option<Arc<CircuitBreaker>>,
+    #[cfg(all(feature = "coalesce", not(feature = "blocking")))]
+    inflight: Option<Arc<crate::coalesce::Inflight>>,
+    cache: Option<Arc<dyn CacheLayer>>,
+    #[cfg(not(target_arch = "wasm32"))]
+    disk_cache: Option<DiskCache>,
+    #[cfg(not(target_arch = "wasm32"))]
+    offline_fallback: bool,
+    #[cfg(all(feature = "test-util", not(target_arch = "wasm32")))]
+    cassette: Option<Arc<crate::vcr::Cassette>>,
+    #[cfg(not(target_arch = "wasm32"))]
+    request_timeout: Option<Duration>,
+    #[cfg(feature = "otel")]
+    propagate_trace_context: bool,
+    middleware: Vec<Arc<dyn Middleware>>,
+    #[cfg(all(feature = "reqwest-middleware", not(feature = "blocking")))]
+    reqwest_middleware: Option<reqwest_middleware::ClientWithMiddleware>,
+    user_agent: Arc<str>,
 }
 
+type UnknownEndpointsCallback = Arc<dyn Fn(&[String]) + Send + Sync>;
+
 impl Client {
     pub fn new(client_config: ClientConfig) -> Self {
         Self::new_with_reqwest_client(ReqwestClient::new(), client_config)
@@ -50,50 +260,1197 @@ impl Client {
         Self {
             client,
             client_config,
+            base_url: BASE_URL.to_owned(),
+            default_amount: ClientBuilder::DEFAULT_AMOUNT,
+            clamp_amount: false,
             search_ratelimit_data: Arc::new(Mutex::new(None)),
+            endpoints: Arc::new(RwLock::new(None)),
+            unknown_endpoints_callback: None,
+            rate_limiter: None,
+            rate_limited_retries: 0,
+            retry_policy: None,
+            circuit_breaker: None,
+            #[cfg(all(feature = "coalesce", not(feature = "blocking")))]
+            inflight: None,
+            cache: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            disk_cache: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            offline_fallback: false,
+            #[cfg(all(feature = "test-util", not(target_arch = "wasm32")))]
+            cassette: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            request_timeout: None,
+            #[cfg(feature = "otel")]
+            propagate_trace_context: false,
+            middleware: Vec::new(),
+            #[cfg(all(feature = "reqwest-middleware", not(feature = "blocking")))]
+            reqwest_middleware: None,
+            user_agent: USER_AGENT.into(),
         }
     }
 
-    pub(crate) async fn handle_search_ratelimit(&self) -> Result<(), crate::NekosBestError> {
-        let lock = self.search_ratelimit_data.lock().await;
-        if let Some(search_ratelimit_data) = &*lock {
-            if search_ratelimit_data.remaining == 0 {
-                match self.client_config.search_ratelimit_behavior {
-                    SearchRatelimitBehavior::Sleep => {
-                        tokio::time::sleep_until(search_ratelimit_data.resets_at).await;
-                    }
-                    SearchRatelimitBehavior::Error => {
-                        return Err(crate::NekosBestError::RateLimited);
+    pub fn builder() -> ClientBuilder {
+        ClientBuilder::new()
+    }
+
+    /// The base url of the API the requests go to, [`BASE_URL`] by default.
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// The `User-Agent` the requests are sent with, [`USER_AGENT`] by default.
+    pub fn user_agent(&self) -> &str {
+        &self.user_agent
+    }
+
+    /// A clone of the client (sharing its state, like its cache and rate
+    /// limiter) where every request times out after `timeout`, overriding
+    /// [`ClientBuilder::timeout`], e.g. for commands with tight latency
+    /// bounds:
+    ///
+    /// ```no_run
+    /// # async fn f(client: &nekosbest::client::Client) -> Result<(), nekosbest::NekosBestError> {
+    /// # use std::time::Duration;
+    /// let image = client
+    ///     .with_timeout(Duration::from_secs(2))
+    ///     .get(nekosbest::Category::Pat)
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// With retries, each attempt gets the whole `timeout`.
+    ///
+    /// Not available on wasm32, where the browser handles timeouts.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_timeout(&self, timeout: Duration) -> Self {
+        Self {
+            request_timeout: Some(timeout),
+            ..self.clone()
+        }
+    }
+
+    /// A clone of the client (sharing its state) retrying its requests
+    /// according to `policy`, instead of [`ClientBuilder::retry_policy`].
+    pub fn with_retry_policy(&self, policy: RetryPolicy) -> Self {
+        Self {
+            retry_policy: Some(policy),
+            ..self.clone()
+        }
+    }
+
+    /// The retry policy of the client, if any, see
+    /// [`ClientBuilder::retry_policy`].
+    pub fn retry_policy(&self) -> Option<&RetryPolicy> {
+        self.retry_policy.as_ref()
+    }
+
+    /// Gets a single image, see [`crate::get_with_client`].
+    #[cfg_attr(feature = "blocking", blocking)]
+    pub async fn get(
+        &self,
+        category: impl Into<Category>,
+    ) -> Result<NekosBestResponseSingle, NekosBestError> {
+        crate::get_with_client(self, category).await
+    }
+
+    /// Gets `amount` images, see [`crate::get_with_client_amount`].
+    #[cfg_attr(feature = "blocking", blocking)]
+    pub async fn get_amount(
+        &self,
+        category: impl Into<Category>,
+        amount: impl Into<u8>,
+    ) -> Result<NekosBestResponse, NekosBestError> {
+        crate::get_with_client_amount(self, category, amount).await
+    }
+
+    /// Gets exactly `amount` images, see [`crate::get_with_client_exact_amount`].
+    #[cfg_attr(feature = "blocking", blocking)]
+    pub async fn get_exact_amount(
+        &self,
+        category: impl Into<Category>,
+        amount: usize,
+    ) -> Result<NekosBestResponse, NekosBestError> {
+        crate::get_with_client_exact_amount(self, category, amount).await
+    }
+
+    /// Gets `amount` distinct images, see [`crate::get_with_client_unique_amount`].
+    #[cfg_attr(feature = "blocking", blocking)]
+    pub async fn get_unique_amount(
+        &self,
+        category: impl Into<Category>,
+        amount: usize,
+        max_requests: usize,
+    ) -> Result<NekosBestResponse, NekosBestError> {
+        crate::get_with_client_unique_amount(self, category, amount, max_requests).await
+    }
+
+    /// Gets the default amount of images, see [`ClientBuilder::default_amount`].
+    #[cfg_attr(feature = "blocking", blocking)]
+    pub async fn get_many(
+        &self,
+        category: impl Into<Category>,
+    ) -> Result<NekosBestResponse, NekosBestError> {
+        crate::get_with_client_amount(self, category, self.default_amount).await
+    }
+
+    /// Searches for images, see [`crate::search_with_client`].
+    #[cfg_attr(feature = "blocking", blocking)]
+    pub async fn search(&self, query: SearchQuery) -> Result<NekosBestResponse, NekosBestError> {
+        crate::search_with_client(self, query).await
+    }
+
+    /// Gets the details of the image at `url`, see [`crate::get_with_client_image_details`].
+    #[cfg_attr(feature = "blocking", blocking)]
+    pub async fn image_details(
+        &self,
+        url: impl AsRef<str>,
+    ) -> Result<ImageDetails, NekosBestError> {
+        crate::get_with_client_image_details(self, url).await
+    }
+
+    /// Gets the details of the gif at `url`, see [`crate::get_with_client_gif_details`].
+    #[cfg_attr(feature = "blocking", blocking)]
+    pub async fn gif_details(&self, url: impl AsRef<str>) -> Result<GifDetails, NekosBestError> {
+        crate::get_with_client_gif_details(self, url).await
+    }
+
+    /// Sets a callback invoked by [`Client::refresh_endpoints`] with
+    /// the (sorted) names of the endpoints that don't correspond to any
+    /// [`Category`], if there are any.
+    ///
+    /// Useful for getting notified in deployed bots when the API adds new
+    /// categories, which can already be requested with their name in the
+    /// meantime.
+    pub fn with_unknown_endpoints_callback(
+        mut self,
+        callback: impl Fn(&[String]) + Send + Sync + 'static,
+    ) -> Self {
+        self.unknown_endpoints_callback = Some(Arc::new(callback));
+        self
+    }
+
+    /// Fetches the metadata of the endpoints from `/endpoints`, and keeps
+    /// it in the client, to validate the requests made through it.
+    #[cfg_attr(feature = "blocking", blocking)]
+    pub async fn refresh_endpoints(&self) -> Result<(), NekosBestError> {
+        let endpoints = crate::endpoints::get_endpoints_with_client(self).await?;
+
+        if let Some(callback) = &self.unknown_endpoints_callback {
+            let mut unknown = endpoints
+                .keys()
+                .filter(|name| Category::from_url_name(name).is_none())
+                .cloned()
+                .collect::<Vec<_>>();
+            if !unknown.is_empty() {
+                unknown.sort();
+                callback(&unknown);
+            }
+        }
+
+        *self.endpoints.write().unwrap() = Some(Arc::new(endpoints));
+        Ok(())
+    }
+
+    /// The state of the circuit breaker, if there is one, see
+    /// [`ClientBuilder::circuit_breaker`].
+    pub fn circuit_state(&self) -> Option<crate::CircuitState> {
+        self.circuit_breaker.as_ref().map(|breaker| breaker.state())
+    }
+
+    /// Sends a request to the API, going through the circuit breaker and
+    /// waiting for the rate limiter first, if there are any, and retrying
+    /// as configured with [`ClientBuilder::retry_rate_limited`] and
+    /// [`ClientBuilder::retry_policy`].
+    ///
+    /// # Errors
+    /// [`NekosBestError::CircuitOpen`] if the circuit breaker is open,
+    /// [`NekosBestError::RateLimited`] if the server responds with `429 Too
+    /// Many Requests`, and [`NekosBestError::ReqwestError`] if the request
+    /// couldn't be sent.
+    #[cfg_attr(feature = "blocking", blocking)]
+    pub(crate) async fn send(
+        &self,
+        req: ReqwestRequestBuilder,
+    ) -> Result<ReqwestResponse, NekosBestError> {
+        let req = req.header(reqwest::header::USER_AGENT, &*self.user_agent);
+        #[cfg(not(target_arch = "wasm32"))]
+        let req = match self.request_timeout {
+            Some(timeout) => req.timeout(timeout),
+            None => req,
+        };
+
+        #[cfg(all(feature = "test-util", not(target_arch = "wasm32")))]
+        if let Some(cassette) = &self.cassette {
+            return cassette.send(self, req).await;
+        }
+
+        let result = self.send_live(req).await;
+        if let Err(e) = &result {
+            #[cfg(feature = "metrics-facade")]
+            crate::telemetry::record_error(e);
+            for middleware in &self.middleware {
+                middleware.on_error(e);
+            }
+        }
+        result
+    }
+
+    /// Sends `request` like [`Client::send`], reading the whole body: the
+    /// [`HttpBackend`](crate::backend::HttpBackend) side of the client, which
+    /// the requests for images, details and metadata go through, to be
+    /// parsed with [`crate::sans_io`].
+    ///
+    /// Like for any backend, statuses that aren't successes aren't errors
+    /// here (but rate limits still are, see [`Client::send`]).
+    #[cfg_attr(feature = "blocking", blocking)]
+    pub(crate) async fn send_http(
+        &self,
+        request: HttpRequest,
+    ) -> Result<HttpResponse, NekosBestError> {
+        let mut req = self.client.get(request.url);
+        for (name, value) in request.headers {
+            req = req.header(name, value);
+        }
+
+        let resp = self.send(req).await?;
+        let status = resp.status().as_u16();
+        let headers = crate::backend::header_pairs(resp.headers());
+        let body = resp.bytes().await?.to_vec();
+        #[cfg(feature = "metrics-facade")]
+        crate::telemetry::record_bytes(body.len());
+
+        Ok(HttpResponse {
+            status,
+            headers,
+            body,
+        })
+    }
+
+    /// Sends a request like [`Client::send`], but to the API even when
+    /// replaying a cassette.
+    #[cfg_attr(feature = "blocking", blocking)]
+    pub(crate) async fn send_live(
+        &self,
+        req: ReqwestRequestBuilder,
+    ) -> Result<ReqwestResponse, NekosBestError> {
+        let breaker = match &self.circuit_breaker {
+            Some(breaker) => breaker,
+            None => return self.send_with_retries(req).await,
+        };
+
+        breaker.check(Instant::now())?;
+        let result = self.send_with_retries(req).await;
+        let failed = match &result {
+            Ok(resp) => resp.status().is_server_error(),
+            Err(NekosBestError::RateLimited { .. }) => true,
+            Err(NekosBestError::ReqwestError(e)) => is_transient(e),
+            Err(_) => false,
+        };
+        breaker.record(Instant::now(), !failed);
+
+        result
+    }
+
+    #[cfg_attr(feature = "blocking", blocking)]
+    async fn send_with_retries(
+        &self,
+        mut req: ReqwestRequestBuilder,
+    ) -> Result<ReqwestResponse, NekosBestError> {
+        let (mut rate_limited, mut transient) = (0, 0);
+        loop {
+            if let Some(rate_limiter) = &self.rate_limiter {
+                rate_limiter.acquire().await;
+            }
+
+            // the middleware run again on the retries, from the request
+            // they were given
+            let retry = req.try_clone();
+            for middleware in &self.middleware {
+                req = middleware.on_request(req);
+            }
+            #[cfg(feature = "otel")]
+            let span = crate::otel::client_span(&req, rate_limited + transient);
+            #[cfg(feature = "otel")]
+            if self.propagate_trace_context {
+                req = req.headers(crate::otel::trace_context_headers(&span));
+            }
+            #[cfg(any(feature = "tracing", feature = "metrics-facade"))]
+            let started = Instant::now();
+            let result = self.execute(req).await?;
+            #[cfg(feature = "otel")]
+            crate::otel::record_response(&span, &result);
+            if let Ok(resp) = &result {
+                for middleware in &self.middleware {
+                    middleware.on_response(resp);
+                }
+            }
+            #[cfg(feature = "tracing")]
+            trace_response(&result, started.elapsed(), rate_limited + transient);
+            #[cfg(feature = "metrics-facade")]
+            crate::telemetry::record_request(
+                match &result {
+                    Ok(resp) => Some(resp.status().as_u16()),
+                    Err(e) => e.status().map(|status| status.as_u16()),
+                },
+                started.elapsed(),
+            );
+
+            let wait = match &result {
+                Ok(resp) if resp.status() == reqwest::StatusCode::TOO_MANY_REQUESTS => {
+                    let retry_after = resp
+                        .headers()
+                        .get(reqwest::header::RETRY_AFTER)
+                        .and_then(|v| v.to_str().ok())
+                        .and_then(crate::sans_io::parse_retry_after);
+                    if rate_limited >= self.rate_limited_retries || retry.is_none() {
+                        return Err(NekosBestError::RateLimited { retry_after });
                     }
+                    rate_limited += 1;
+                    Some(retry_after.unwrap_or(Self::DEFAULT_RETRY_AFTER))
+                }
+                // a server error, or a transient network error
+                Ok(resp) if !resp.status().is_server_error() => None,
+                Err(e) if !is_transient(e) => None,
+                _ => self.retry_policy.as_ref().and_then(|policy| {
+                    transient += 1;
+                    policy.delay(transient, &mut rand::thread_rng())
+                }),
+            };
+
+            match (wait, retry) {
+                (Some(wait), Some(retry)) => {
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!(?wait, "retrying request to nekos.best");
+                    crate::rt::sleep_until(Instant::now() + wait).await;
+                    req = retry;
                 }
+                _ => return Ok(result?),
+            }
+        }
+    }
+
+    /// Sends `req` once, through the
+    /// [`ClientBuilder::reqwest_middleware`] client if there is one.
+    ///
+    /// # Errors
+    /// [`NekosBestError::Middleware`] if a middleware of that client failed,
+    /// which isn't retried. The errors of reqwest are in the inner result.
+    #[cfg_attr(feature = "blocking", blocking)]
+    async fn execute(
+        &self,
+        req: ReqwestRequestBuilder,
+    ) -> Result<Result<ReqwestResponse, reqwest::Error>, NekosBestError> {
+        #[cfg(all(feature = "reqwest-middleware", not(feature = "blocking")))]
+        if let Some(client) = &self.reqwest_middleware {
+            let req = match req.build() {
+                Ok(req) => req,
+                Err(e) => return Ok(Err(e)),
+            };
+            return match client.execute(req).await {
+                Ok(resp) => Ok(Ok(resp)),
+                Err(reqwest_middleware::Error::Reqwest(e)) => Ok(Err(e)),
+                Err(reqwest_middleware::Error::Middleware(e)) => {
+                    Err(NekosBestError::Middleware(e.into()))
+                }
+            };
+        }
+
+        Ok(req.send().await)
+    }
+
+    /// How long to wait before retrying a rate limited request, when the
+    /// server doesn't say.
+    const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(1);
+
+    /// The in-flight requests, if coalescing them is enabled.
+    #[cfg(all(feature = "coalesce", not(feature = "blocking")))]
+    pub(crate) fn inflight(&self) -> Option<&crate::coalesce::Inflight> {
+        self.inflight.as_deref()
+    }
+
+    /// The cache of responses, if enabled with [`ClientBuilder::cache`]
+    /// or [`ClientBuilder::cache_layer`].
+    pub fn cache(&self) -> Option<&dyn CacheLayer> {
+        self.cache.as_deref()
+    }
+
+    /// Whether the client has a cache of responses, in memory or on disk.
+    #[cfg(feature = "metrics-facade")]
+    pub(crate) fn has_cache(&self) -> bool {
+        #[cfg(not(target_arch = "wasm32"))]
+        if self.disk_cache.is_some() {
+            return true;
+        }
+        self.cache.is_some()
+    }
+
+    /// The cache of responses on disk, if enabled with
+    /// [`ClientBuilder::disk_cache`].
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn disk_cache(&self) -> Option<&DiskCache> {
+        self.disk_cache.as_ref()
+    }
+
+    /// The response for `key` in the disk cache, however old, if `error`
+    /// means the API is unreachable and [`ClientBuilder::offline_fallback`]
+    /// is enabled.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn offline_response(
+        &self,
+        key: &crate::cache::CacheKey,
+        error: &NekosBestError,
+    ) -> Option<crate::NekosBestResponse> {
+        if !self.offline_fallback || !is_unreachable(error) {
+            return None;
+        }
+
+        self.disk_cache.as_ref()?.get_stale(key)
+    }
+
+    /// The endpoint metadata loaded by [`Client::refresh_endpoints`], if any.
+    pub(crate) fn endpoints(&self) -> Option<Arc<EndpointsMap>> {
+        self.endpoints.read().unwrap().clone()
+    }
+
+    /// Checks that `amount` is in the `1..=`[`crate::MAX_AMOUNT`] range the
+    /// API accepts, or clamps it to it, see [`ClientBuilder::clamp_amount`].
+    pub(crate) fn amount_in_range(&self, amount: u8) -> Result<u8, NekosBestError> {
+        match crate::sans_io::check_amount(amount) {
+            Err(_) if self.clamp_amount => Ok(amount.clamp(1, crate::MAX_AMOUNT)),
+            result => result,
+        }
+    }
+
+    /// Validates `amount` like [`Client::amount_in_range`], and against the
+    /// endpoint metadata, if it is loaded, returning the amount to request.
+    ///
+    /// `/endpoints` doesn't advertise a limit on the amount, so the only
+    /// other bound that can be checked is the number of files in the
+    /// category.
+    pub(crate) fn validate_amount(
+        &self,
+        category: Category,
+        amount: u8,
+    ) -> Result<u8, NekosBestError> {
+        let amount = self.amount_in_range(amount)?;
+        let Some(endpoints) = self.endpoints() else {
+            return Ok(amount);
+        };
+        let Some(files) =
+            crate::endpoints::endpoint(&endpoints, category).and_then(|desc| desc.file_count())
+        else {
+            return Ok(amount);
+        };
+
+        if u64::from(amount) > files {
+            return Err(NekosBestError::InvalidAmount {
+                requested: amount.into(),
+                min: 1,
+                max: files,
+            });
+        }
+
+        Ok(amount)
+    }
+
+    #[cfg_attr(feature = "blocking", blocking)]
+    pub(crate) async fn handle_search_ratelimit(&self) -> Result<(), crate::NekosBestError> {
+        // copy the deadline out, so that the lock is not held across the sleep
+        let resets_at = match &*self.search_ratelimit_data.lock().unwrap() {
+            Some(data) if data.remaining == 0 => data.resets_at,
+            _ => return Ok(()),
+        };
+
+        match self.client_config.search_ratelimit_behavior {
+            SearchRatelimitBehavior::Sleep => {
+                crate::rt::sleep_until(resets_at).await;
+            }
+            SearchRatelimitBehavior::Error => {
+                return Err(crate::NekosBestError::RateLimited {
+                    retry_after: Some(resets_at.saturating_duration_since(Instant::now())),
+                });
             }
         }
 
         Ok(())
     }
 
-    pub(crate) async fn update_search_ratelimit_data(&self, headers: &reqwest::header::HeaderMap) {
-        let Some(remaining) = headers.get("X-Rate-Limit-Remaining")
-            else { return; };
-        let Some(reset) = headers.get("X-Rate-Limit-Reset")
-            else { return; };
-        let Ok(remaining) = remaining.to_str() else { return; };
-        let Ok(remaining) = remaining.parse::<u32>() else { return; };
-        let Ok(reset) = reset.to_str() else { return; };
+    pub(crate) fn update_search_ratelimit_data(&self, resp: &HttpResponse) {
+        let Some(remaining) = resp.header("X-Rate-Limit-Remaining") else {
+            return;
+        };
+        let Some(reset) = resp.header("X-Rate-Limit-Reset") else {
+            return;
+        };
 
-        let Ok(reset) = reset.parse::<chrono::DateTime::<chrono::Utc>>()
-            else { return; };
+        let Some(info) = crate::sans_io::parse_ratelimit(remaining, reset) else {
+            return;
+        };
 
-        let diff = reset - chrono::Utc::now();
+        let diff = info.resets_at - chrono::Utc::now();
 
         if diff < chrono::Duration::zero() {
-            *self.search_ratelimit_data.lock().await = None;
+            *self.search_ratelimit_data.lock().unwrap() = None;
             return;
         }
 
-        *self.search_ratelimit_data.lock().await = Some(SearchRatelimitData {
-            remaining,
-            resets_at: Instant::from_std(std::time::Instant::now().add(diff.to_std().unwrap())),
+        *self.search_ratelimit_data.lock().unwrap() = Some(SearchRatelimitData {
+            remaining: info.remaining,
+            resets_at: Instant::now().add(diff.to_std().unwrap()),
         });
     }
 }
+
+/// Emits an event for the response of a request (or its failure), sent
+/// after `retries` retries.
+#[cfg(feature = "tracing")]
+fn trace_response(
+    result: &Result<ReqwestResponse, reqwest::Error>,
+    latency: Duration,
+    retries: u32,
+) {
+    match result {
+        Ok(resp) => tracing::debug!(
+            url = %resp.url(),
+            status = resp.status().as_u16(),
+            ?latency,
+            retries,
+            "response from nekos.best"
+        ),
+        Err(e) => tracing::debug!(
+            url = e.url().map(|url| url.as_str()),
+            status = e.status().map(|status| status.as_u16()),
+            ?latency,
+            retries,
+            error = %e,
+            "request to nekos.best failed"
+        ),
+    }
+}
+
+/// Whether sending a request failed for a reason that may go away by itself.
+pub(crate) fn is_transient(e: &reqwest::Error) -> bool {
+    #[cfg(not(target_arch = "wasm32"))]
+    if e.is_connect() {
+        return true;
+    }
+
+    e.is_timeout() || e.is_request()
+}
+
+/// Whether a request failed because the API couldn't be reached at all.
+#[cfg(not(target_arch = "wasm32"))]
+fn is_unreachable(e: &NekosBestError) -> bool {
+    match e {
+        NekosBestError::ReqwestError(e) => e.status().is_none() && is_transient(e),
+        NekosBestError::CircuitOpen { .. } => true,
+        #[cfg(all(feature = "coalesce", not(feature = "blocking")))]
+        NekosBestError::Coalesced(e) => is_unreachable(e),
+        _ => false,
+    }
+}
+
+/// A builder for [`Client`], see [`Client::builder`].
+#[derive(Default)]
+pub struct ClientBuilder {
+    client_config: ClientConfig,
+    reqwest_client: Option<ReqwestClient>,
+    base_url: Option<String>,
+    #[cfg(not(target_arch = "wasm32"))]
+    timeout: Option<Duration>,
+    #[cfg(not(target_arch = "wasm32"))]
+    connect_timeout: Option<Duration>,
+    #[cfg(not(target_arch = "wasm32"))]
+    proxies: Vec<reqwest::Proxy>,
+    #[cfg(not(target_arch = "wasm32"))]
+    no_proxy: bool,
+    default_amount: Option<u8>,
+    clamp_amount: bool,
+    rate_limit: Option<(f64, u32)>,
+    rate_limited_retries: u32,
+    retry_policy: Option<RetryPolicy>,
+    circuit_breaker: Option<(u32, Duration)>,
+    #[cfg(all(feature = "coalesce", not(feature = "blocking")))]
+    coalesce_requests: bool,
+    cache: Option<Arc<dyn CacheLayer>>,
+    #[cfg(not(target_arch = "wasm32"))]
+    disk_cache: Option<DiskCache>,
+    #[cfg(not(target_arch = "wasm32"))]
+    offline_fallback: bool,
+    #[cfg(all(feature = "test-util", not(target_arch = "wasm32")))]
+    cassette: Option<crate::vcr::Cassette>,
+    #[cfg(feature = "otel")]
+    propagate_trace_context: bool,
+    middleware: Vec<Arc<dyn Middleware>>,
+    #[cfg(all(feature = "reqwest-middleware", not(feature = "blocking")))]
+    reqwest_middleware: Option<reqwest_middleware::ClientWithMiddleware>,
+    user_agent: Option<String>,
+}
+
+impl ClientBuilder {
+    /// The default for [`ClientBuilder::default_amount`].
+    pub const DEFAULT_AMOUNT: u8 = 20;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn config(mut self, client_config: ClientConfig) -> Self {
+        self.client_config = client_config;
+        self
+    }
+
+    /// Uses the given reqwest client, instead of building one.
+    ///
+    /// The timeouts and proxies set on the builder are ignored in that
+    /// case, and should be set on the reqwest client instead.
+    pub fn reqwest_client(mut self, client: ReqwestClient) -> Self {
+        self.reqwest_client = Some(client);
+        self
+    }
+
+    /// Sends the requests through `client` and its middleware stack (like
+    /// retries or tracing), instead of a plain reqwest client.
+    ///
+    /// Like with [`ClientBuilder::reqwest_client`], the timeouts and
+    /// proxies set on the builder are ignored in that case.
+    ///
+    /// Only available with the `reqwest-middleware` feature, and not with
+    /// `blocking`.
+    #[cfg(all(feature = "reqwest-middleware", not(feature = "blocking")))]
+    pub fn reqwest_middleware(mut self, client: reqwest_middleware::ClientWithMiddleware) -> Self {
+        self.reqwest_middleware = Some(client);
+        self
+    }
+
+    /// Sends the requests to the API at `base_url` instead of [`BASE_URL`].
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = Some(base_url.into());
+        self
+    }
+
+    /// The `User-Agent` the requests are sent with, instead of
+    /// [`USER_AGENT`]. It should identify the application, e.g.
+    /// `"my-bot/1.0 (https://example.com)"`.
+    ///
+    /// It is set on every request, overriding the one of the reqwest
+    /// client, even with [`ClientBuilder::reqwest_client`].
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = Some(user_agent.into());
+        self
+    }
+
+    /// The timeout for whole requests, from connecting until the body
+    /// has been read.
+    ///
+    /// Not available on wasm32, where the browser handles timeouts.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// The timeout for connecting to the server.
+    ///
+    /// Not available on wasm32, where the browser handles timeouts.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Sends the requests through `proxy`, e.g.
+    /// `reqwest::Proxy::all("http://proxy:8080")?`, with its own no-proxy
+    /// list set with [`reqwest::Proxy::no_proxy`]. Can be called several
+    /// times, the first proxy matching a request being used.
+    ///
+    /// SOCKS proxies need the `socks` feature. Without any proxy set, the
+    /// system proxies (`HTTP_PROXY`, `HTTPS_PROXY`, ...) are used.
+    ///
+    /// Not available on wasm32, and ignored with
+    /// [`ClientBuilder::reqwest_client`].
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn proxy(mut self, proxy: reqwest::Proxy) -> Self {
+        self.proxies.push(proxy);
+        self
+    }
+
+    /// Ignores the system proxies, and those set with
+    /// [`ClientBuilder::proxy`].
+    ///
+    /// Not available on wasm32, and ignored with
+    /// [`ClientBuilder::reqwest_client`].
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn no_proxy(mut self) -> Self {
+        self.no_proxy = true;
+        self
+    }
+
+    /// How many images [`Client::get_many`] requests. Defaults to
+    /// [`ClientBuilder::DEFAULT_AMOUNT`].
+    ///
+    /// Checked by [`ClientBuilder::build`], like the amounts of the
+    /// requests, see [`ClientBuilder::clamp_amount`].
+    pub fn default_amount(mut self, amount: u8) -> Self {
+        self.default_amount = Some(amount);
+        self
+    }
+
+    /// Clamps the amounts of images requested to the `1..=`[`crate::MAX_AMOUNT`]
+    /// range, like the server does, instead of failing with
+    /// [`NekosBestError::InvalidAmount`]. Defaults to `false`.
+    pub fn clamp_amount(mut self, clamp: bool) -> Self {
+        self.clamp_amount = clamp;
+        self
+    }
+
+    /// Throttles the requests made through the client (and its clones) to
+    /// `requests_per_second`, allowing bursts of up to `burst` requests.
+    ///
+    /// Requests over the limit wait for their turn, in order.
+    /// `requests_per_second` must be finite and positive, and `burst` at
+    /// least 1, or [`ClientBuilder::build`] fails.
+    pub fn rate_limit(mut self, requests_per_second: f64, burst: u32) -> Self {
+        self.rate_limit = Some((requests_per_second, burst));
+        self
+    }
+
+    /// Retries requests that were rate limited by the server (`429 Too Many
+    /// Requests`) up to `max_retries` times, after waiting as long as its
+    /// `Retry-After` header says. Defaults to 0, where they fail with
+    /// [`NekosBestError::RateLimited`] right away.
+    pub fn retry_rate_limited(mut self, max_retries: u32) -> Self {
+        self.rate_limited_retries = max_retries;
+        self
+    }
+
+    /// Retries the requests failing with transient errors according to
+    /// `policy`. By default, they aren't retried.
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = Some(policy);
+        self
+    }
+
+    /// Adds a circuit breaker to the client (shared by its clones): after
+    /// `threshold` consecutive requests failing with server errors, rate
+    /// limiting or network errors (after their retries), the requests fail
+    /// right away with [`NekosBestError::CircuitOpen`] for `cooldown`. A
+    /// single request is then let through, and closes the circuit again if
+    /// it succeeds.
+    pub fn circuit_breaker(mut self, threshold: u32, cooldown: Duration) -> Self {
+        self.circuit_breaker = Some((threshold, cooldown));
+        self
+    }
+
+    /// Whether concurrent identical requests for images (same category and
+    /// amount) made through the client (or its clones) share a single
+    /// request, each caller getting a clone of the response. Defaults to
+    /// `false`.
+    ///
+    /// Only available with the `coalesce` feature, and not with `blocking`.
+    #[cfg(all(feature = "coalesce", not(feature = "blocking")))]
+    pub fn coalesce_requests(mut self, coalesce: bool) -> Self {
+        self.coalesce_requests = coalesce;
+        self
+    }
+
+    /// Caches the responses for images (by category and amount) in memory
+    /// for `ttl`, keeping up to `max_entries` of them and evicting the
+    /// least recently used ones. By default, nothing is cached.
+    ///
+    /// While cached, the same images are returned for the same requests,
+    /// so this is meant for commands used often enough that the API
+    /// traffic matters more than the variety.
+    pub fn cache(self, ttl: Duration, max_entries: usize) -> Self {
+        self.cache_layer(MemoryCache::new(ttl, max_entries))
+    }
+
+    /// Caches the responses for images in `cache`, like
+    /// [`ClientBuilder::cache`] but with any [`CacheLayer`], e.g. a
+    /// `moka::sync::Cache` with the `moka` feature.
+    pub fn cache_layer(mut self, cache: impl CacheLayer + 'static) -> Self {
+        self.cache = Some(Arc::new(cache));
+        self
+    }
+
+    /// Caches the responses for images on disk, see [`DiskCache`]. If
+    /// [`ClientBuilder::cache`] is also set, responses read from disk are
+    /// cached in memory as well.
+    ///
+    /// Errors writing to the cache are ignored, the requests succeed anyway.
+    ///
+    /// Not available on wasm32.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn disk_cache(mut self, dir: impl Into<std::path::PathBuf>, ttl: Duration) -> Self {
+        self.disk_cache = Some(DiskCache::new(dir, ttl));
+        self
+    }
+
+    /// Whether requests for images that fail because the API can't be
+    /// reached (connection errors, timeouts, or an open circuit breaker)
+    /// fall back to the responses in the [`ClientBuilder::disk_cache`],
+    /// however old. Images older than the ttl of the cache are marked as
+    /// [`stale`](crate::NekosBestResponseSingle::stale). Defaults to
+    /// `false`.
+    ///
+    /// Not available on wasm32.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn offline_fallback(mut self, fallback: bool) -> Self {
+        self.offline_fallback = fallback;
+        self
+    }
+
+    /// Records the responses of the API to `cassette`, or replays them
+    /// from it instead of sending the requests, see [`crate::vcr`].
+    ///
+    /// Only available with the `test-util` feature, and not on wasm32.
+    #[cfg(all(feature = "test-util", not(target_arch = "wasm32")))]
+    pub fn cassette(mut self, cassette: crate::vcr::Cassette) -> Self {
+        self.cassette = Some(cassette);
+        self
+    }
+
+    /// Registers `middleware`, called around every request sent, see
+    /// [`Middleware`].
+    pub fn middleware(mut self, middleware: impl Middleware + 'static) -> Self {
+        self.middleware.push(Arc::new(middleware));
+        self
+    }
+
+    /// Whether to send the context of the span of every request to the
+    /// API, in the W3C trace-context headers (`traceparent` and
+    /// `tracestate`), when the span is recorded by `tracing-opentelemetry`.
+    /// Defaults to `false`.
+    ///
+    /// Only available with the `otel` feature.
+    #[cfg(feature = "otel")]
+    pub fn propagate_trace_context(mut self, propagate: bool) -> Self {
+        self.propagate_trace_context = propagate;
+        self
+    }
+
+    /// Builds the client.
+    ///
+    /// # Errors
+    /// [`NekosBestError::ReqwestError`] if the reqwest client can't be built,
+    /// [`NekosBestError::InvalidRateLimit`] if the
+    /// [`ClientBuilder::rate_limit`] is invalid, and
+    /// [`NekosBestError::InvalidAmount`] if the
+    /// [`ClientBuilder::default_amount`] isn't in the `1..=`[`crate::MAX_AMOUNT`]
+    /// range (and isn't clamped).
+    pub fn build(self) -> Result<Client, NekosBestError> {
+        if let Some((requests_per_second, burst)) = self.rate_limit {
+            if !(requests_per_second.is_finite() && requests_per_second > 0.0) || burst == 0 {
+                return Err(NekosBestError::InvalidRateLimit {
+                    requests_per_second,
+                    burst,
+                });
+            }
+        }
+
+        let reqwest_client = match self.reqwest_client {
+            Some(client) => client,
+            #[cfg(not(target_arch = "wasm32"))]
+            None => {
+                let mut builder = ReqwestClient::builder();
+                if let Some(timeout) = self.timeout {
+                    builder = builder.timeout(timeout);
+                }
+                if let Some(timeout) = self.connect_timeout {
+                    builder = builder.connect_timeout(timeout);
+                }
+                if self.no_proxy {
+                    builder = builder.no_proxy();
+                } else {
+                    for proxy in self.proxies {
+                        builder = builder.proxy(proxy);
+                    }
+                }
+                builder.build()?
+            }
+            #[cfg(target_arch = "wasm32")]
+            None => ReqwestClient::new(),
+        };
+
+        let mut client = Client::new_with_reqwest_client(reqwest_client, self.client_config);
+        if let Some(base_url) = self.base_url {
+            client.base_url = base_url.trim_end_matches('/').to_owned();
+        }
+        if let Some(user_agent) = self.user_agent {
+            client.user_agent = user_agent.into();
+        }
+        client.clamp_amount = self.clamp_amount;
+        if let Some(amount) = self.default_amount {
+            client.default_amount = client.amount_in_range(amount)?;
+        }
+        client.rate_limited_retries = self.rate_limited_retries;
+        client.retry_policy = self.retry_policy;
+        #[cfg(all(feature = "coalesce", not(feature = "blocking")))]
+        if self.coalesce_requests {
+            client.inflight = Some(Arc::default());
+        }
+        if let Some((threshold, cooldown)) = self.circuit_breaker {
+            client.circuit_breaker = Some(Arc::new(CircuitBreaker::new(threshold, cooldown)));
+        }
+        client.cache = self.cache;
+        client.middleware = self.middleware;
+        #[cfg(all(feature = "reqwest-middleware", not(feature = "blocking")))]
+        {
+            client.reqwest_middleware = self.reqwest_middleware;
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            client.disk_cache = self.disk_cache;
+            client.offline_fallback = self.offline_fallback;
+        }
+        #[cfg(all(feature = "test-util", not(target_arch = "wasm32")))]
+        {
+            client.cassette = self.cassette.map(Arc::new);
+        }
+        #[cfg(feature = "otel")]
+        {
+            client.propagate_trace_context = self.propagate_trace_context;
+        }
+        if let Some((requests_per_second, burst)) = self.rate_limit {
+            client.rate_limiter = Some(Arc::new(RateLimiter::new(requests_per_second, burst)));
+        }
+        Ok(client)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn retry_delays() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let policy = RetryPolicy::new()
+            .max_retries(5)
+            .base_delay(Duration::from_secs(1))
+            .max_delay(Duration::from_secs(5));
+
+        let no_jitter = policy.jitter(0.0);
+        let delays = (1..=6)
+            .map(|retry| no_jitter.delay(retry, &mut rng).map(|d| d.as_secs()))
+            .collect::<Vec<_>>();
+        assert_eq!(delays, [Some(1), Some(2), Some(4), Some(5), Some(5), None]);
+
+        for _ in 0..100 {
+            let delay = policy.delay(3, &mut rng).unwrap();
+            assert!((Duration::from_secs(2)..=Duration::from_secs(4)).contains(&delay));
+        }
+    }
+
+    #[cfg(not(feature = "blocking"))]
+    #[tokio::test]
+    async fn amount_range() {
+        let client = Client::builder()
+            .base_url("http://127.0.0.1:1")
+            .build()
+            .unwrap();
+        for amount in [0, crate::MAX_AMOUNT + 1] {
+            assert!(matches!(
+                crate::get_with_client_amount(&client, Category::Hug, amount).await,
+                Err(NekosBestError::InvalidAmount { requested, min: 1, max: 20 })
+                    if requested == u64::from(amount)
+            ));
+        }
+
+        let client = Client::builder().clamp_amount(true).build().unwrap();
+        assert_eq!(client.validate_amount(Category::Hug, 0).unwrap(), 1);
+        assert_eq!(client.validate_amount(Category::Hug, 100).unwrap(), 20);
+        assert_eq!(client.validate_amount(Category::Hug, 5).unwrap(), 5);
+    }
+
+    #[cfg(not(feature = "blocking"))]
+    #[tokio::test]
+    async fn request_timeout() {
+        // Accepts connections (through the backlog), but never answers.
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = Client::builder()
+            .base_url(format!("http://{}", listener.local_addr().unwrap()))
+            .build()
+            .unwrap();
+
+        let result = client
+            .with_timeout(Duration::from_millis(100))
+            .get(Category::Pat)
+            .await;
+        assert!(matches!(result, Err(NekosBestError::ReqwestError(e)) if e.is_timeout()));
+    }
+
+    #[cfg(all(feature = "test-util", not(feature = "blocking")))]
+    #[tokio::test]
+    async fn circuit_breaker_rate_limited() {
+        let server = crate::test_util::MockServer::start().unwrap();
+        server.fail_next(
+            2,
+            crate::test_util::Fault::RateLimited {
+                retry_after: Some(0),
+            },
+        );
+        let client = Client::builder()
+            .base_url(server.base_url())
+            .circuit_breaker(2, Duration::from_secs(30))
+            .build()
+            .unwrap();
+
+        for _ in 0..2 {
+            assert!(matches!(
+                client.get(Category::Pat).await,
+                Err(NekosBestError::RateLimited { .. })
+            ));
+        }
+        assert_eq!(client.circuit_state(), Some(crate::CircuitState::Open));
+        assert!(matches!(
+            client.get(Category::Pat).await,
+            Err(NekosBestError::CircuitOpen { .. })
+        ));
+        assert_eq!(server.requests(), 2);
+    }
+
+    #[test]
+    fn ext() {
+        assert_eq!(ReqwestClient::new().nekos_best().base_url(), BASE_URL);
+    }
+
+    #[cfg(all(
+        feature = "reqwest-middleware",
+        feature = "test-util",
+        not(feature = "blocking")
+    ))]
+    #[tokio::test]
+    async fn reqwest_middleware() {
+        use reqwest_middleware::{Middleware, Next};
+        use std::sync::atomic::{AtomicU32, Ordering};
+        use task_local_extensions::Extensions;
+
+        /// Counts the requests, rejecting them once `limit` is reached.
+        struct Limit(AtomicU32, u32);
+
+        #[async_trait::async_trait]
+        impl Middleware for Limit {
+            async fn handle(
+                &self,
+                req: reqwest::Request,
+                extensions: &mut Extensions,
+                next: Next<'_>,
+            ) -> reqwest_middleware::Result<reqwest::Response> {
+                if self.0.fetch_add(1, Ordering::SeqCst) >= self.1 {
+                    let e = std::io::Error::other("limit reached");
+                    return Err(reqwest_middleware::Error::middleware(e));
+                }
+                next.run(req, extensions).await
+            }
+        }
+
+        let server = crate::test_util::MockServer::start().unwrap();
+        let client = Client::builder()
+            .base_url(server.base_url())
+            .reqwest_middleware(
+                reqwest_middleware::ClientBuilder::new(ReqwestClient::new())
+                    .with(Limit(AtomicU32::new(0), 1))
+                    .build(),
+            )
+            .build()
+            .unwrap();
+        crate::get_with_client(&client, Category::Pat)
+            .await
+            .unwrap();
+        assert!(matches!(
+            crate::get_with_client(&client, Category::Pat).await,
+            Err(NekosBestError::Middleware(_))
+        ));
+        assert_eq!(server.requests(), 1);
+    }
+
+    #[test]
+    fn default() {
+        assert_eq!(default_client().base_url(), BASE_URL);
+        set_default_client(Client::builder().user_agent("test").build().unwrap());
+        assert_eq!(default_client().user_agent(), "test");
+        set_default_client(Client::new(ClientConfig::default()));
+    }
+
+    #[test]
+    fn builder() {
+        let client = Client::builder()
+            .base_url("http://localhost:8080/api/v2/")
+            .timeout(Duration::from_secs(5))
+            .default_amount(5)
+            .build()
+            .unwrap();
+        assert_eq!(client.base_url(), "http://localhost:8080/api/v2");
+        assert_eq!(client.default_amount, 5);
+        assert!(client.rate_limiter.is_none());
+        assert_eq!(client.circuit_state(), None);
+        assert_eq!(
+            Client::builder()
+                .circuit_breaker(5, Duration::from_secs(30))
+                .build()
+                .unwrap()
+                .circuit_state(),
+            Some(crate::CircuitState::Closed)
+        );
+        assert!(Client::builder()
+            .rate_limit(2.0, 5)
+            .build()
+            .unwrap()
+            .rate_limiter
+            .is_some());
+        for (requests_per_second, burst) in [
+            (0.0, 1),
+            (-1.0, 1),
+            (f64::NAN, 1),
+            (f64::INFINITY, 1),
+            (2.0, 0),
+        ] {
+            assert!(matches!(
+                Client::builder()
+                    .rate_limit(requests_per_second, burst)
+                    .build(),
+                Err(NekosBestError::InvalidRateLimit { .. })
+            ));
+        }
+        for amount in [0, crate::MAX_AMOUNT + 1] {
+            assert!(matches!(
+                Client::builder().default_amount(amount).build(),
+                Err(NekosBestError::InvalidAmount { .. })
+            ));
+        }
+        let clamped = Client::builder()
+            .default_amount(0)
+            .clamp_amount(true)
+            .build()
+            .unwrap();
+        assert_eq!(clamped.default_amount, 1);
+        assert!(Client::builder()
+            .proxy(reqwest::Proxy::all("http://localhost:3128").unwrap())
+            .build()
+            .is_ok());
+        assert!(Client::builder()
+            .cache(Duration::from_secs(60), 100)
+            .build()
+            .unwrap()
+            .cache()
+            .is_some());
+
+        let client = Client::new(ClientConfig::default());
+        assert_eq!(client.base_url(), BASE_URL);
+        assert_eq!(client.default_amount, ClientBuilder::DEFAULT_AMOUNT);
+        assert_eq!(client.user_agent(), USER_AGENT);
+        assert!(USER_AGENT.starts_with("nb-rs/"));
+        assert_eq!(
+            Client::builder()
+                .user_agent("my-bot/1.0")
+                .build()
+                .unwrap()
+                .user_agent(),
+            "my-bot/1.0"
+        );
+    }
+}
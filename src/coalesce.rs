@@ -0,0 +1,101 @@
+//! Sharing identical in-flight requests, see
+//! [`crate::client::ClientBuilder::coalesce_requests`].
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use futures::future::{FutureExt, Shared};
+
+use crate::client::Client;
+use crate::{Category, NekosBestError, NekosBestResponse};
+
+type RequestResult = Result<NekosBestResponse, Arc<NekosBestError>>;
+// reqwest's futures aren't `Send` on wasm32
+#[cfg(not(target_arch = "wasm32"))]
+type SharedRequest = Shared<futures::future::BoxFuture<'static, RequestResult>>;
+#[cfg(target_arch = "wasm32")]
+type SharedRequest = Shared<futures::future::LocalBoxFuture<'static, RequestResult>>;
+type Requests = HashMap<(Category, Option<u8>), SharedRequest>;
+
+/// The in-flight requests, keyed by category and amount, [`None`] being
+/// a request without an amount.
+#[derive(Default)]
+pub(crate) struct Inflight {
+    requests: Arc<Mutex<Requests>>,
+}
+
+impl Inflight {
+    /// Gets `amount` images from `category`, joining an identical request
+    /// if there is one in flight.
+    ///
+    /// # Errors
+    /// Same as [`crate::get_with_client_amount`], except that the errors
+    /// are wrapped in [`NekosBestError::Coalesced`], whether the request
+    /// was shared by several callers or not.
+    pub(crate) async fn get(
+        &self,
+        client: &Client,
+        category: Category,
+        amount: Option<u8>,
+    ) -> Result<NekosBestResponse, NekosBestError> {
+        let key = (category, amount);
+        let request = self
+            .requests
+            .lock()
+            .unwrap()
+            .entry(key)
+            .or_insert_with(|| {
+                let client = client.clone();
+                let requests = Arc::clone(&self.requests);
+                let request = async move {
+                    let result = crate::implementation::fetch(&client, category, amount).await;
+                    requests.lock().unwrap().remove(&key);
+                    result.map_err(Arc::new)
+                };
+                #[cfg(not(target_arch = "wasm32"))]
+                let request = request.boxed();
+                #[cfg(target_arch = "wasm32")]
+                let request = request.boxed_local();
+                request.shared()
+            })
+            .clone();
+
+        request.await.map_err(NekosBestError::Coalesced)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn shared_failure() {
+        // nothing listens there, so the request fails right away
+        let client = Client::builder()
+            .base_url("http://127.0.0.1:1")
+            .coalesce_requests(true)
+            .build()
+            .unwrap();
+
+        let (a, b) = tokio::join!(
+            client.get_amount(Category::Neko, 2),
+            client.get_amount(Category::Neko, 2)
+        );
+        let c = client.get_amount(Category::Neko, 2).await;
+        for result in [a, b, c] {
+            match result {
+                Err(NekosBestError::Coalesced(e)) => {
+                    assert!(matches!(*e, NekosBestError::ReqwestError(_)))
+                }
+                other => panic!("unexpected result {other:?}"),
+            }
+        }
+        assert!(client
+            .inflight()
+            .unwrap()
+            .requests
+            .lock()
+            .unwrap()
+            .is_empty());
+    }
+}
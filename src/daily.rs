@@ -0,0 +1,141 @@
+//! Picking images from the file range of an endpoint: randomly,
+//! deterministically, or an image of the day.
+
+#[cfg(feature = "blocking")]
+use nb_blocking_util::blocking;
+
+use crate::client::{default_client, Client};
+use crate::details::Details;
+use crate::endpoints::{endpoint_desc, ImageFormat};
+use crate::{
+    get_with_client_gif_details, get_with_client_image_details, sans_io, Category, NekosBestError,
+    NekosBestResponseSingle, Selection,
+};
+
+/// A stable mix of `category` and `date`.
+///
+/// Doesn't use [`std::hash::DefaultHasher`], since its output may change
+/// between Rust versions, and every instance of a bot should agree on it.
+fn daily_seed(category: Category, date: chrono::NaiveDate) -> u64 {
+    // FNV-1a over the category name and the date
+    category
+        .to_url_name()
+        .bytes()
+        .chain(date.to_string().bytes())
+        .fold(0xcbf29ce484222325, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+        })
+}
+
+/// Gets an image from `category`, picked according to `selection` from
+/// the file range the endpoint advertises in `/endpoints`, with a
+/// supplied client.
+///
+/// [`Selection::Index`] picks the file at that index in the range.
+/// Uses the metadata loaded by [`Client::refresh_endpoints`] if there is
+/// any, otherwise fetches it, falling back on the snapshot in
+/// [`EMBEDDED_ENDPOINTS`](crate::endpoints::EMBEDDED_ENDPOINTS) when
+/// offline.
+///
+/// # Errors
+/// [`NekosBestError::NoFileRange`] if the endpoint doesn't advertise a
+/// numeric file range, [`NekosBestError::NotFound`] if the index of
+/// [`Selection::Index`] is out of the range, otherwise refer to
+/// [`NekosBestError`].
+#[cfg_attr(feature = "blocking", blocking)]
+pub async fn get_from_range_with_client(
+    client: &Client,
+    category: impl Into<Category>,
+    selection: Selection,
+) -> Result<NekosBestResponseSingle, NekosBestError> {
+    let category = category.into();
+    let desc = endpoint_desc(client, category).await?;
+    let (min, max, _) = desc.file_range().ok_or(NekosBestError::NoFileRange)?;
+
+    let n = selection
+        .pick(max - min + 1)
+        .ok_or(NekosBestError::NotFound)?;
+    let file_name = desc.file_name(n).ok_or(NekosBestError::NoFileRange)?;
+    let url = format!(
+        "{}/{file_name}",
+        sans_io::category_url(client.base_url(), category)
+    );
+
+    let details = match desc.image_format().unwrap_or(category.format()) {
+        ImageFormat::Gif => Details::Gif(get_with_client_gif_details(client, &url).await?),
+        ImageFormat::Png => Details::Image(get_with_client_image_details(client, &url).await?),
+    };
+
+    Ok(NekosBestResponseSingle::new(url, details))
+}
+
+/// Gets an image from `category`, picked according to `selection`, with
+/// the default client.
+///
+/// # Errors
+/// Same as [`get_from_range_with_client`].
+#[cfg_attr(feature = "blocking", blocking)]
+pub async fn get_from_range(
+    category: impl Into<Category>,
+    selection: Selection,
+) -> Result<NekosBestResponseSingle, NekosBestError> {
+    get_from_range_with_client(&default_client(), category, selection).await
+}
+
+/// Gets the image of the day for `category`, with a supplied client.
+///
+/// The image is picked from the file range the endpoint advertises in
+/// `/endpoints`, based only on `category` and `date`, so all the shards /
+/// instances of a bot get the same image on the same day.
+///
+/// # Errors
+/// Same as [`get_from_range_with_client`].
+#[cfg_attr(feature = "blocking", blocking)]
+pub async fn get_daily_with_client(
+    client: &Client,
+    category: impl Into<Category>,
+    date: chrono::NaiveDate,
+) -> Result<NekosBestResponseSingle, NekosBestError> {
+    let category = category.into();
+    let seed = daily_seed(category, date);
+    get_from_range_with_client(client, category, Selection::Seeded(seed)).await
+}
+
+/// Gets the image of the day for `category`, with the default client.
+///
+/// # Errors
+/// Same as [`get_daily_with_client`].
+#[cfg_attr(feature = "blocking", blocking)]
+pub async fn get_daily(
+    category: impl Into<Category>,
+    date: chrono::NaiveDate,
+) -> Result<NekosBestResponseSingle, NekosBestError> {
+    get_daily_with_client(&default_client(), category, date).await
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn stable_seed() {
+        let date = chrono::NaiveDate::from_ymd_opt(2023, 8, 19).unwrap();
+        let next = date.succ_opt().unwrap();
+
+        assert_eq!(
+            daily_seed(Category::Neko, date),
+            daily_seed(Category::Neko, date)
+        );
+        assert_ne!(
+            daily_seed(Category::Neko, date),
+            daily_seed(Category::Neko, next)
+        );
+        assert_ne!(
+            daily_seed(Category::Neko, date),
+            daily_seed(Category::Pat, date)
+        );
+        // guards against accidental changes of the algorithm, which
+        // would change every image of the day
+        assert_eq!(daily_seed(Category::Neko, date), 0xe4d5f4400cebec2f);
+    }
+}
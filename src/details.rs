@@ -1,16 +1,46 @@
-use serde::{Deserialize, Deserializer};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use url::Url;
 
 /// In the case of [`Category::Neko`], the API
 /// also returns the source url, the name and a
 /// link to the artist that made it.
-#[derive(Deserialize, Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+///
+/// On the wire, the fields are flat (`artist_name`, `artist_href` and
+/// `source_url`).
+#[derive(Serialize, Deserialize, Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct ImageDetails {
-    #[serde(deserialize_with = "deserialize_url")]
-    pub artist_href: Url,
-    pub artist_name: String,
-    #[serde(deserialize_with = "deserialize_url")]
-    pub source_url: Url,
+    #[serde(flatten)]
+    pub artist: ArtistInfo,
+    #[serde(flatten)]
+    pub source: Option<SourceInfo>,
+}
+
+/// The artist that made an image.
+#[derive(Serialize, Deserialize, Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ArtistInfo {
+    #[serde(rename = "artist_name")]
+    pub name: String,
+    #[serde(
+        rename = "artist_href",
+        serialize_with = "serialize_url",
+        deserialize_with = "deserialize_url"
+    )]
+    pub href: Url,
+}
+
+/// Where an image was originally posted.
+#[derive(Serialize, Deserialize, Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SourceInfo {
+    #[serde(
+        rename = "source_url",
+        serialize_with = "serialize_url",
+        deserialize_with = "deserialize_url"
+    )]
+    pub url: Url,
+}
+
+fn serialize_url<S: Serializer>(url: &Url, ser: S) -> Result<S::Ok, S::Error> {
+    ser.serialize_str(url.as_str())
 }
 
 fn deserialize_url<'de, D: Deserializer<'de>>(de: D) -> Result<Url, D::Error> {
@@ -20,12 +50,12 @@ fn deserialize_url<'de, D: Deserializer<'de>>(de: D) -> Result<Url, D::Error> {
 
 /// In the case of gif endpoints, the API also
 /// returns the anime name.
-#[derive(Deserialize, Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Serialize, Deserialize, Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct GifDetails {
     pub anime_name: String,
 }
 
-#[derive(Deserialize, Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Serialize, Deserialize, Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
 #[serde(untagged)]
 #[non_exhaustive]
 pub enum Details {
@@ -49,6 +79,7 @@ impl Details {
         }
     }
 
+    #[allow(clippy::result_large_err)]
     pub fn try_into_image(self) -> Result<ImageDetails, Self> {
         if let Self::Image(v) = self {
             Ok(v)
@@ -72,6 +103,7 @@ impl Details {
         }
     }
 
+    #[allow(clippy::result_large_err)]
     pub fn try_into_gif(self) -> Result<GifDetails, Self> {
         if let Self::Gif(v) = self {
             Ok(v)
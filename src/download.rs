@@ -1,10 +1,10 @@
-use crate::client::{Client, ClientConfig};
+use crate::client::{default_client, Client, ReqwestResponse};
 use crate::{NekosBestError, NekosBestResponseSingle};
 use image::AnimationDecoder;
 #[cfg(feature = "blocking")]
 use nb_blocking_util::blocking;
 use reqwest::IntoUrl;
-use tokio::io::AsyncWriteExt;
+use std::sync::{Arc, Mutex};
 
 #[derive(Clone)]
 pub enum DownloadResult {
@@ -22,6 +22,67 @@ impl DownloadResult {
     }
 }
 
+/// A format to convert downloaded images to, see [`DownloadResult::encode`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ConvertFormat {
+    Png,
+    /// JPEG, with the given quality, up to 100. Transparency is dropped.
+    Jpeg(u8),
+    /// Lossless WebP.
+    WebP,
+}
+
+impl ConvertFormat {
+    /// The file extension for this format, without the leading dot.
+    pub const fn extension(self) -> &'static str {
+        match self {
+            Self::Png => "png",
+            Self::Jpeg(_) => "jpg",
+            Self::WebP => "webp",
+        }
+    }
+
+    pub const fn mime_type(self) -> &'static str {
+        match self {
+            Self::Png => "image/png",
+            Self::Jpeg(_) => "image/jpeg",
+            Self::WebP => "image/webp",
+        }
+    }
+}
+
+impl DownloadResult {
+    /// Encodes the image in the given format.
+    ///
+    /// For gifs, only the first frame is kept.
+    ///
+    /// # Errors
+    /// [`NekosBestError::EmptyImage`] for a gif without frames.
+    pub fn encode(&self, format: ConvertFormat) -> Result<Vec<u8>, NekosBestError> {
+        let image = match self {
+            DownloadResult::Image(img) => img.clone(),
+            DownloadResult::Gif(gif) => match gif.frames.first() {
+                Some(frame) => image::DynamicImage::ImageRgba8(frame.buffer().clone()),
+                None => return Err(NekosBestError::EmptyImage),
+            },
+        };
+
+        let (image, output_format) = match format {
+            ConvertFormat::Png => (image, image::ImageOutputFormat::Png),
+            ConvertFormat::Jpeg(quality) => (
+                image::DynamicImage::ImageRgb8(image.into_rgb8()),
+                image::ImageOutputFormat::Jpeg(quality),
+            ),
+            ConvertFormat::WebP => (image, image::ImageOutputFormat::WebP),
+        };
+
+        let mut buf = std::io::Cursor::new(Vec::new());
+        image.write_to(&mut buf, output_format)?;
+        Ok(buf.into_inner())
+    }
+}
+
 #[derive(Clone)]
 pub struct GifDownloadResult {
     frames: Vec<image::Frame>,
@@ -63,12 +124,53 @@ impl GifDownloadResult {
     }
 }
 
+impl NekosBestResponseSingle {
+    /// Downloads the raw bytes of the image, without decoding them.
+    ///
+    /// # Errors
+    /// Same as [`download_bytes_from_url_with_client`].
+    #[cfg_attr(feature = "blocking", blocking)]
+    pub async fn download(&self, client: &Client) -> Result<bytes::Bytes, NekosBestError> {
+        download_bytes_from_url_with_client(client, &self.url).await
+    }
+}
+
+/// Downloads the raw bytes of the image at the given url, without
+/// decoding them.
+///
+/// # Errors
+/// Any error from the request, including non-success statuses.
+#[cfg_attr(feature = "blocking", blocking)]
+pub async fn download_bytes_from_url(url: impl IntoUrl) -> Result<bytes::Bytes, NekosBestError> {
+    download_bytes_from_url_with_client(&default_client(), url).await
+}
+
+/// Downloads the raw bytes of the image at the given url using the given
+/// client, without decoding them.
+///
+/// # Errors
+/// Same as [`download_bytes_from_url`].
+#[cfg_attr(feature = "blocking", blocking)]
+pub async fn download_bytes_from_url_with_client(
+    client: &Client,
+    url: impl IntoUrl,
+) -> Result<bytes::Bytes, NekosBestError> {
+    let resp = client
+        .send(client.client.get(url))
+        .await?
+        .error_for_status()?;
+    let bytes = resp.bytes().await?;
+    #[cfg(feature = "metrics-facade")]
+    crate::telemetry::record_bytes(bytes.len());
+    Ok(bytes)
+}
+
 /// Downloads the image from the given response.
 #[cfg_attr(feature = "blocking", blocking)]
 pub async fn download(
     response: &NekosBestResponseSingle,
 ) -> Result<DownloadResult, NekosBestError> {
-    download_with_client(&Client::new(ClientConfig::default()), response).await
+    download_with_client(&default_client(), response).await
 }
 
 /// Downloads the image from the given response using the given client.
@@ -83,7 +185,7 @@ pub async fn download_with_client(
 /// Downloads the image from the given url.
 #[cfg_attr(feature = "blocking", blocking)]
 pub async fn download_from_url(url: impl IntoUrl) -> Result<DownloadResult, NekosBestError> {
-    download_from_url_with_client(&Client::new(ClientConfig::default()), url).await
+    download_from_url_with_client(&default_client(), url).await
 }
 
 /// Downloads the image from the given url using the given client.
@@ -92,24 +194,170 @@ pub async fn download_from_url_with_client(
     client: &Client,
     url: impl IntoUrl,
 ) -> Result<DownloadResult, NekosBestError> {
-    let resp = client.client.get(url).send().await?.error_for_status()?;
+    let downloaded =
+        download_from_url_with_options(client, url, &DownloadOptions::default()).await?;
+    Ok(downloaded.image)
+}
+
+/// The `Accept` header value preferring modern image formats, as sent
+/// with [`DownloadOptions::modern_formats`].
+pub const MODERN_FORMATS_ACCEPT: &str =
+    "image/avif,image/webp,image/png,image/gif;q=0.9,image/*;q=0.8";
+
+type ProgressCallback = Arc<Mutex<dyn FnMut(u64, Option<u64>) + Send>>;
+
+/// Options for the `*_with_options` download functions.
+#[derive(Clone, Default)]
+pub struct DownloadOptions {
+    accept: Option<String>,
+    progress: Option<ProgressCallback>,
+    resume: bool,
+}
+
+impl std::fmt::Debug for DownloadOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DownloadOptions")
+            .field("accept", &self.accept)
+            .field("progress", &self.progress.is_some())
+            .field("resume", &self.resume)
+            .finish()
+    }
+}
+
+impl DownloadOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sends the given `Accept` header with the download request.
+    pub fn accept(mut self, accept: impl Into<String>) -> Self {
+        self.accept = Some(accept.into());
+        self
+    }
+
+    /// Asks for modern formats (AVIF, WebP) where the CDN supports
+    /// them, see [`MODERN_FORMATS_ACCEPT`].
+    ///
+    /// Note that AVIF images can be downloaded to files, but not decoded.
+    pub fn modern_formats(self) -> Self {
+        self.accept(MODERN_FORMATS_ACCEPT)
+    }
+
+    /// Calls `f` after every chunk of the image is received, with the
+    /// number of bytes downloaded so far and the total size, if the server
+    /// sent a `Content-Length`.
+    ///
+    /// Clones of these options share the callback.
+    pub fn progress(mut self, f: impl FnMut(u64, Option<u64>) + Send + 'static) -> Self {
+        self.progress = Some(Arc::new(Mutex::new(f)));
+        self
+    }
+
+    /// Whether downloads to files resume from a partial file left by an
+    /// earlier download, with a `Range` request. Falls back to a full
+    /// download when the server doesn't support ranges. Defaults to `false`.
+    pub fn resume(mut self, resume: bool) -> Self {
+        self.resume = resume;
+        self
+    }
+
+    fn report_progress(&self, downloaded: u64, total: Option<u64>) {
+        if let Some(progress) = &self.progress {
+            (progress.lock().unwrap())(downloaded, total);
+        }
+    }
+}
+
+/// A downloaded image, along with the content type the server sent it as.
+#[derive(Clone)]
+pub struct DownloadedImage {
+    pub image: DownloadResult,
+    pub content_type: String,
+    /// The hash of the image as it was downloaded, before decoding.
+    #[cfg(feature = "hash")]
+    pub hash: crate::hash::ContentHash,
+}
+
+#[cfg_attr(feature = "blocking", blocking)]
+async fn send_download_request(
+    client: &Client,
+    url: impl IntoUrl,
+    options: &DownloadOptions,
+    range_start: Option<u64>,
+) -> Result<(ReqwestResponse, String), NekosBestError> {
+    let mut req = client.client.get(url);
+    if let Some(accept) = &options.accept {
+        req = req.header(reqwest::header::ACCEPT, accept);
+    }
+    if let Some(start) = range_start {
+        req = req.header(reqwest::header::RANGE, format!("bytes={start}-"));
+    }
+
+    let resp = client.send(req).await?.error_for_status()?;
     let content_type = resp
         .headers()
         .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
         .ok_or(NekosBestError::MissingContentType)?
-        .to_str()
-        .unwrap()
         .to_owned();
-    let bytes = resp.bytes().await?;
-    match content_type.as_str() {
-        "image/png" => Ok(DownloadResult::Image(image::load_from_memory(&bytes)?)),
+
+    Ok((resp, content_type))
+}
+
+/// Downloads the image from the given url using the given client and
+/// options, also returning the negotiated content type.
+#[cfg_attr(feature = "blocking", blocking)]
+pub async fn download_from_url_with_options(
+    client: &Client,
+    url: impl IntoUrl,
+    options: &DownloadOptions,
+) -> Result<DownloadedImage, NekosBestError> {
+    let (resp, content_type) = send_download_request(client, url, options, None).await?;
+    let bytes = read_body(resp, options).await?;
+    #[cfg(feature = "hash")]
+    let hash = crate::hash::ContentHash::of(&bytes);
+    let image = match content_type.as_str() {
         "image/gif" => {
             let decoder = image::codecs::gif::GifDecoder::new(std::io::Cursor::new(bytes))?;
             let frames = decoder.into_frames().collect_frames()?;
-            Ok(DownloadResult::Gif(GifDownloadResult { frames }))
+            DownloadResult::Gif(GifDownloadResult { frames })
         }
-        _ => Err(NekosBestError::MissingContentType),
-    }
+        ty if ty.starts_with("image/") => DownloadResult::Image(image::load_from_memory(&bytes)?),
+        _ => return Err(NekosBestError::MissingContentType),
+    };
+
+    Ok(DownloadedImage {
+        image,
+        content_type,
+        #[cfg(feature = "hash")]
+        hash,
+    })
+}
+
+/// Downloads the image from the given response, converted to `format`.
+///
+/// # Errors
+/// Same as [`download`] and [`DownloadResult::encode`].
+#[cfg_attr(feature = "blocking", blocking)]
+pub async fn download_as(
+    response: &NekosBestResponseSingle,
+    format: ConvertFormat,
+) -> Result<Vec<u8>, NekosBestError> {
+    download_as_with_client(&default_client(), response, format).await
+}
+
+/// Downloads the image from the given response using the given client,
+/// converted to `format`.
+///
+/// # Errors
+/// Same as [`download_with_client`] and [`DownloadResult::encode`].
+#[cfg_attr(feature = "blocking", blocking)]
+pub async fn download_as_with_client(
+    client: &Client,
+    response: &NekosBestResponseSingle,
+    format: ConvertFormat,
+) -> Result<Vec<u8>, NekosBestError> {
+    download_with_client(client, response).await?.encode(format)
 }
 
 #[cfg_attr(feature = "blocking", blocking)]
@@ -117,11 +365,7 @@ pub async fn download_to_file(
     response: &NekosBestResponseSingle,
     file: impl AsRef<std::path::Path>,
 ) -> Result<(), NekosBestError> {
-    download_to_file_with_client(
-        &Client::new(ClientConfig::default()),
-        response,
-        file,
-    ).await
+    download_to_file_with_client(&default_client(), response, file).await
 }
 
 #[cfg_attr(feature = "blocking", blocking)]
@@ -142,11 +386,7 @@ pub async fn download_from_url_to_file(
     url: impl IntoUrl,
     file: impl AsRef<std::path::Path>,
 ) -> Result<(), NekosBestError> {
-    download_from_url_to_file_with_client(
-        &Client::new(ClientConfig::default()),
-        url,
-        file,
-    ).await
+    download_from_url_to_file_with_client(&default_client(), url, file).await
 }
 
 #[cfg_attr(feature = "blocking", blocking)]
@@ -154,16 +394,397 @@ pub async fn download_from_url_to_file_with_client(
     client: &Client,
     url: impl IntoUrl,
     file: impl AsRef<std::path::Path>,
+) -> Result<(), NekosBestError> {
+    download_from_url_to_file_with_options(client, url, file, &DownloadOptions::default()).await?;
+    Ok(())
+}
+
+/// Downloads the image from the given url to `file` using the given
+/// client and options, returning the negotiated content type.
+///
+/// With [`DownloadOptions::resume`], an existing `file` is taken to be
+/// the start of the image, and only the rest is requested.
+#[cfg_attr(feature = "blocking", blocking)]
+pub async fn download_from_url_to_file_with_options(
+    client: &Client,
+    url: impl IntoUrl,
+    file: impl AsRef<std::path::Path>,
+    options: &DownloadOptions,
+) -> Result<String, NekosBestError> {
+    let url = url.into_url()?;
+    let file = file.as_ref();
+
+    let existing = match std::fs::metadata(file) {
+        Ok(metadata) if options.resume => metadata.len(),
+        _ => 0,
+    };
+    if existing > 0 {
+        match send_download_request(client, url.clone(), options, Some(existing)).await {
+            Ok((resp, content_type)) if resumes_at(&resp, existing) => {
+                let f = crate::rt::File::append(file).await?;
+                write_response(resp, f, options, existing).await?;
+                return Ok(content_type);
+            }
+            // the server ignored the range, and sent the whole image
+            Ok((resp, content_type)) if resp.status() == reqwest::StatusCode::OK => {
+                let f = crate::rt::File::create(file).await?;
+                write_response(resp, f, options, 0).await?;
+                return Ok(content_type);
+            }
+            // a range the server doesn't like, most likely because the file
+            // is complete or bigger than the image, start over
+            Ok(_) => {}
+            Err(NekosBestError::ReqwestError(e))
+                if e.status() == Some(reqwest::StatusCode::RANGE_NOT_SATISFIABLE) => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    let (resp, content_type) = send_download_request(client, url, options, None).await?;
+    let f = crate::rt::File::create(file).await?;
+    write_response(resp, f, options, 0).await?;
+    Ok(content_type)
+}
+
+/// Whether `resp` is a partial response starting at `start`.
+fn resumes_at(resp: &ReqwestResponse, start: u64) -> bool {
+    resp.status() == reqwest::StatusCode::PARTIAL_CONTENT
+        && resp
+            .headers()
+            .get(reqwest::header::CONTENT_RANGE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|range| range.strip_prefix("bytes ")?.split_once('-'))
+            .is_some_and(|(first, _)| first.parse() == Ok(start))
+}
+
+/// Downloads the image from the given response as a stream of chunks,
+/// see [`download_stream_from_url_with_client`].
+#[cfg(not(feature = "blocking"))]
+pub async fn download_stream(
+    response: &NekosBestResponseSingle,
+) -> Result<impl futures::Stream<Item = Result<bytes::Bytes, NekosBestError>>, NekosBestError> {
+    download_stream_with_client(&default_client(), response).await
+}
+
+/// Downloads the image from the given response as a stream of chunks,
+/// using the given client, see [`download_stream_from_url_with_client`].
+#[cfg(not(feature = "blocking"))]
+pub async fn download_stream_with_client(
+    client: &Client,
+    response: &NekosBestResponseSingle,
+) -> Result<impl futures::Stream<Item = Result<bytes::Bytes, NekosBestError>>, NekosBestError> {
+    download_stream_from_url_with_client(client, response.url.clone()).await
+}
+
+/// Downloads the image at the given url as a stream of chunks, see
+/// [`download_stream_from_url_with_client`].
+#[cfg(not(feature = "blocking"))]
+pub async fn download_stream_from_url(
+    url: impl IntoUrl,
+) -> Result<impl futures::Stream<Item = Result<bytes::Bytes, NekosBestError>>, NekosBestError> {
+    download_stream_from_url_with_client(&default_client(), url).await
+}
+
+/// Downloads the image at the given url using the given client, as a
+/// stream of chunks of the body, as they arrive.
+///
+/// Unlike the other download functions, the image is never held in
+/// memory as a whole, so it can be piped to a file or an upload.
+/// Not available with the `blocking` feature, where the response can be
+/// read from directly instead.
+///
+/// # Errors
+/// Any error from sending the request, including non-success statuses.
+/// Errors while receiving the body are yielded by the stream.
+#[cfg(not(feature = "blocking"))]
+pub async fn download_stream_from_url_with_client(
+    client: &Client,
+    url: impl IntoUrl,
+) -> Result<impl futures::Stream<Item = Result<bytes::Bytes, NekosBestError>>, NekosBestError> {
+    use futures::TryStreamExt;
+
+    let resp = client
+        .send(client.client.get(url))
+        .await?
+        .error_for_status()?;
+    Ok(resp.bytes_stream().map_err(NekosBestError::from))
+}
+
+#[cfg_attr(feature = "blocking", blocking)]
+async fn write_response(
+    resp: ReqwestResponse,
+    mut f: crate::rt::File,
+    options: &DownloadOptions,
+    offset: u64,
 ) -> Result<(), NekosBestError> {
     use futures::StreamExt;
 
-    let resp = client.client.get(url).send().await?.error_for_status()?;
+    let total = resp.content_length().map(|len| offset + len);
     let mut stream = resp.bytes_stream();
-    let mut f = tokio::fs::File::create(file).await?;
 
+    let mut downloaded = offset;
     while let Some(item) = stream.next().await {
-        f.write_all(&item?).await?;
+        let chunk = item?;
+        f.write_all(&chunk).await?;
+        downloaded += chunk.len() as u64;
+        options.report_progress(downloaded, total);
     }
 
     Ok(())
 }
+
+#[cfg_attr(feature = "blocking", blocking)]
+async fn read_body(
+    resp: ReqwestResponse,
+    options: &DownloadOptions,
+) -> Result<Vec<u8>, NekosBestError> {
+    use futures::StreamExt;
+
+    let total = resp.content_length();
+    let mut stream = resp.bytes_stream();
+    let mut body = Vec::with_capacity(total.unwrap_or(0) as usize);
+
+    while let Some(item) = stream.next().await {
+        body.extend_from_slice(&item?);
+        options.report_progress(body.len() as u64, total);
+    }
+
+    Ok(body)
+}
+
+/// Downloads the image from the given response to `path`, see
+/// [`download_from_url_to_path_with_client`].
+#[cfg_attr(feature = "blocking", blocking)]
+pub async fn download_to_path(
+    response: &NekosBestResponseSingle,
+    path: impl AsRef<std::path::Path>,
+) -> Result<std::path::PathBuf, NekosBestError> {
+    download_to_path_with_client(&default_client(), response, path).await
+}
+
+/// Downloads the image from the given response to `path` using the given
+/// client, see [`download_from_url_to_path_with_client`].
+#[cfg_attr(feature = "blocking", blocking)]
+pub async fn download_to_path_with_client(
+    client: &Client,
+    response: &NekosBestResponseSingle,
+    path: impl AsRef<std::path::Path>,
+) -> Result<std::path::PathBuf, NekosBestError> {
+    download_from_url_to_path_with_client(client, &response.url, path).await
+}
+
+/// Downloads the image from the given url to `path`, see
+/// [`download_from_url_to_path_with_client`].
+#[cfg_attr(feature = "blocking", blocking)]
+pub async fn download_from_url_to_path(
+    url: impl IntoUrl,
+    path: impl AsRef<std::path::Path>,
+) -> Result<std::path::PathBuf, NekosBestError> {
+    download_from_url_to_path_with_client(&default_client(), url, path).await
+}
+
+/// Downloads the image from the given url to `path` using the given
+/// client, returning the path of the file that was written.
+///
+/// If `path` is an existing directory, the file is created in it, named
+/// after the url, see [`file_name`]. Otherwise, `path` is the file.
+#[cfg_attr(feature = "blocking", blocking)]
+pub async fn download_from_url_to_path_with_client(
+    client: &Client,
+    url: impl IntoUrl,
+    path: impl AsRef<std::path::Path>,
+) -> Result<std::path::PathBuf, NekosBestError> {
+    download_from_url_to_path_with_options(client, url, path, &DownloadOptions::default()).await
+}
+
+/// Like [`download_from_url_to_path_with_client`], with the given options.
+#[cfg_attr(feature = "blocking", blocking)]
+pub async fn download_from_url_to_path_with_options(
+    client: &Client,
+    url: impl IntoUrl,
+    path: impl AsRef<std::path::Path>,
+    options: &DownloadOptions,
+) -> Result<std::path::PathBuf, NekosBestError> {
+    let url = url.into_url()?;
+    let path = path.as_ref();
+
+    // the path is known before the request, unless it depends on the content type
+    let known = if !path.is_dir() {
+        Some(path.to_owned())
+    } else {
+        let name = file_name(url.as_str(), "");
+        (name != crate::sans_io::file_id(url.as_str())).then(|| path.join(name))
+    };
+    if let Some(path) = known {
+        download_from_url_to_file_with_options(client, url, &path, options).await?;
+        return Ok(path);
+    }
+
+    let (resp, content_type) = send_download_request(client, url.as_str(), options, None).await?;
+    let path = path.join(file_name(url.as_str(), &content_type));
+    let f = crate::rt::File::create(&path).await?;
+    write_response(resp, f, options, 0).await?;
+    Ok(path)
+}
+
+/// The name to save the image at `url` as, when it was sent with the
+/// given content type.
+///
+/// This is the last segment of the url, with the extension for the
+/// content type appended if the url has none.
+pub fn file_name(url: &str, content_type: &str) -> String {
+    let id = crate::sans_io::file_id(url);
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    let name = path.rsplit('/').next().unwrap_or(path);
+    if name != id {
+        return name.to_owned();
+    }
+
+    match extension_for(content_type) {
+        Some(extension) => format!("{id}.{extension}"),
+        None => id.to_owned(),
+    }
+}
+
+/// The usual file extension for an image content type, without the
+/// leading dot.
+pub fn extension_for(content_type: &str) -> Option<&'static str> {
+    let mime = content_type.split(';').next()?.trim();
+    Some(match mime {
+        "image/png" => "png",
+        "image/gif" => "gif",
+        "image/jpeg" => "jpg",
+        "image/webp" => "webp",
+        "image/avif" => "avif",
+        _ => return None,
+    })
+}
+
+/// Metadata of a gif, read without decoding its frames.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct GifInfo {
+    pub width: u16,
+    pub height: u16,
+    pub frame_count: usize,
+    /// How long one loop of the animation lasts.
+    pub duration: std::time::Duration,
+}
+
+impl GifInfo {
+    /// Reads the metadata of the gif in `bytes`.
+    ///
+    /// Only the headers of the frames are parsed, the image data
+    /// itself is skipped.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, NekosBestError> {
+        let mut options = gif::DecodeOptions::new();
+        options.skip_frame_decoding(true);
+        let mut decoder = options.read_info(bytes)?;
+
+        let mut frame_count = 0;
+        let mut delay = 0u64;
+        while let Some(frame) = decoder.read_next_frame()? {
+            frame_count += 1;
+            // in units of 10ms
+            delay += u64::from(frame.delay);
+        }
+
+        Ok(Self {
+            width: decoder.width(),
+            height: decoder.height(),
+            frame_count,
+            duration: std::time::Duration::from_millis(delay * 10),
+        })
+    }
+}
+
+/// Fetches the gif at the given url and reads its metadata, using the given client.
+///
+/// Useful to check the size or length of a gif before sending it on.
+#[cfg_attr(feature = "blocking", blocking)]
+pub async fn probe_gif_from_url_with_client(
+    client: &Client,
+    url: impl IntoUrl,
+) -> Result<GifInfo, NekosBestError> {
+    let bytes = download_bytes_from_url_with_client(client, url).await?;
+    GifInfo::from_bytes(&bytes)
+}
+
+/// Fetches the gif from the given response and reads its metadata.
+#[cfg_attr(feature = "blocking", blocking)]
+pub async fn probe_gif(response: &NekosBestResponseSingle) -> Result<GifInfo, NekosBestError> {
+    probe_gif_with_client(&default_client(), response).await
+}
+
+/// Fetches the gif from the given response and reads its metadata, using the given client.
+#[cfg_attr(feature = "blocking", blocking)]
+pub async fn probe_gif_with_client(
+    client: &Client,
+    response: &NekosBestResponseSingle,
+) -> Result<GifInfo, NekosBestError> {
+    probe_gif_from_url_with_client(client, &response.url).await
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn file_names() {
+        assert_eq!(
+            file_name("https://nekos.best/api/v2/neko/abc.png", "image/webp"),
+            "abc.png"
+        );
+        assert_eq!(
+            file_name(
+                "https://example.com/files/abc?x=1",
+                "image/jpeg; charset=binary"
+            ),
+            "abc.jpg"
+        );
+        assert_eq!(file_name("https://example.com/abc", "text/html"), "abc");
+        assert_eq!(extension_for("image/avif"), Some("avif"));
+    }
+
+    #[test]
+    fn gif_info() {
+        let mut bytes = Vec::new();
+        {
+            let mut encoder =
+                gif::Encoder::new(&mut bytes, 4, 2, &[0, 0, 0, 255, 255, 255]).unwrap();
+            for delay in [5, 10, 15] {
+                let mut frame =
+                    gif::Frame::from_indexed_pixels(4, 2, [0, 1, 0, 1, 1, 0, 1, 0], None);
+                frame.delay = delay;
+                encoder.write_frame(&frame).unwrap();
+            }
+        }
+
+        let info = GifInfo::from_bytes(&bytes).unwrap();
+        assert_eq!((info.width, info.height), (4, 2));
+        assert_eq!(info.frame_count, 3);
+        assert_eq!(info.duration, std::time::Duration::from_millis(300));
+        assert!(GifInfo::from_bytes(b"not a gif").is_err());
+    }
+
+    #[test]
+    fn encode() {
+        let image = DownloadResult::Image(image::DynamicImage::ImageRgba8(
+            image::RgbaImage::from_pixel(3, 3, image::Rgba([255, 0, 0, 128])),
+        ));
+
+        for (format, expected) in [
+            (ConvertFormat::Png, image::ImageFormat::Png),
+            (ConvertFormat::Jpeg(80), image::ImageFormat::Jpeg),
+            (ConvertFormat::WebP, image::ImageFormat::WebP),
+        ] {
+            let bytes = image.encode(format).unwrap();
+            assert_eq!(image::guess_format(&bytes).unwrap(), expected);
+        }
+
+        let empty = DownloadResult::Gif(GifDownloadResult { frames: Vec::new() });
+        assert!(matches!(
+            empty.encode(ConvertFormat::Png),
+            Err(NekosBestError::EmptyImage)
+        ));
+    }
+}
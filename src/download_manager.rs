@@ -0,0 +1,187 @@
+//! Downloading many images at once, with bounded concurrency and retries.
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use futures::{Stream, StreamExt};
+
+use crate::client::Client;
+use crate::download::{download_from_url_to_path_with_options, DownloadOptions};
+use crate::rt::Instant;
+use crate::NekosBestError;
+
+/// An image to download, and where to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DownloadJob {
+    pub url: String,
+    /// The file to download to, or a directory to create it in, see
+    /// [`download_from_url_to_path_with_options`].
+    pub path: PathBuf,
+}
+
+/// The outcome of a [`DownloadJob`].
+#[derive(Debug)]
+pub struct DownloadReport {
+    pub job: DownloadJob,
+    /// The path of the downloaded file, or the last error.
+    pub result: Result<PathBuf, NekosBestError>,
+    /// How many times the download was attempted.
+    pub attempts: u32,
+}
+
+/// Downloads a queue of images, a few at a time.
+///
+/// ```no_run
+/// # use nekosbest::{client::{Client, ClientConfig}, download_manager::DownloadManager};
+/// # async fn f(urls: Vec<String>) {
+/// let mut manager = DownloadManager::new(Client::new(ClientConfig::default())).concurrency(8);
+/// for url in urls {
+///     manager.push(url, "archive/");
+/// }
+/// for report in manager.run().await {
+///     if let Err(e) = report.result {
+///         eprintln!("{}: {e}", report.job.url);
+///     }
+/// }
+/// # }
+/// ```
+pub struct DownloadManager {
+    client: Client,
+    jobs: Vec<DownloadJob>,
+    concurrency: usize,
+    max_retries: u32,
+    retry_delay: Duration,
+    options: DownloadOptions,
+}
+
+impl DownloadManager {
+    pub fn new(client: Client) -> Self {
+        Self {
+            client,
+            jobs: Vec::new(),
+            concurrency: 4,
+            max_retries: 3,
+            retry_delay: Duration::from_secs(1),
+            options: DownloadOptions::default(),
+        }
+    }
+
+    /// How many downloads run at the same time. Defaults to 4.
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// How many times a download is retried after a retryable error
+    /// (network errors, server errors and rate limits). Defaults to 3.
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// The delay before the first retry of a download, doubled for every
+    /// retry after that. Defaults to 1 second.
+    pub fn retry_delay(mut self, retry_delay: Duration) -> Self {
+        self.retry_delay = retry_delay;
+        self
+    }
+
+    /// The options every download is made with.
+    pub fn options(mut self, options: DownloadOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Queues the image at `url` to be downloaded to `path`.
+    pub fn push(&mut self, url: impl Into<String>, path: impl Into<PathBuf>) -> &mut Self {
+        self.jobs.push(DownloadJob {
+            url: url.into(),
+            path: path.into(),
+        });
+        self
+    }
+
+    /// The queued jobs.
+    pub fn jobs(&self) -> &[DownloadJob] {
+        &self.jobs
+    }
+
+    /// Downloads all the queued images, returning the reports in the
+    /// order the jobs were queued in.
+    pub async fn run(self) -> Vec<DownloadReport> {
+        let concurrency = self.concurrency;
+        self.downloads().buffered(concurrency).collect().await
+    }
+
+    /// Downloads all the queued images, yielding the reports as the
+    /// downloads finish.
+    pub fn stream(self) -> impl Stream<Item = DownloadReport> {
+        let concurrency = self.concurrency;
+        self.downloads().buffer_unordered(concurrency)
+    }
+
+    fn downloads(self) -> impl Stream<Item = impl std::future::Future<Output = DownloadReport>> {
+        let Self {
+            client,
+            jobs,
+            max_retries,
+            retry_delay,
+            options,
+            ..
+        } = self;
+
+        futures::stream::iter(jobs).map(move |job| {
+            let client = client.clone();
+            let options = options.clone();
+            async move {
+                let mut delay = retry_delay;
+                let mut attempts = 0;
+                loop {
+                    attempts += 1;
+                    let result = download_from_url_to_path_with_options(
+                        &client, &job.url, &job.path, &options,
+                    )
+                    .await;
+                    match result {
+                        Err(e) if attempts <= max_retries && e.is_retryable() => {
+                            crate::rt::sleep_until(Instant::now() + delay).await;
+                            delay *= 2;
+                        }
+                        result => {
+                            return DownloadReport {
+                                job,
+                                result,
+                                attempts,
+                            }
+                        }
+                    }
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::client::ClientConfig;
+
+    #[tokio::test]
+    async fn invalid_url() {
+        let mut manager = DownloadManager::new(Client::new(ClientConfig::default()));
+        manager
+            .push("not a url", "out.png")
+            .push("also not a url", "out.gif");
+        assert_eq!(manager.jobs().len(), 2);
+
+        let reports = manager.run().await;
+        assert_eq!(reports[1].job.path, PathBuf::from("out.gif"));
+        for report in reports {
+            assert_eq!(report.attempts, 1);
+            assert!(matches!(
+                report.result,
+                Err(NekosBestError::ReqwestError(_))
+            ));
+        }
+    }
+}
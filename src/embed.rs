@@ -0,0 +1,172 @@
+//! Markup for showing images on web pages, with the proper attribution.
+
+use crate::details::Details;
+use crate::{Category, NekosBestResponseSingle};
+
+/// Escapes `s` so it can be used as HTML text or as the value of a
+/// quoted attribute.
+pub fn escape_html(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// A `<figure>` showing the image in `response`, with the attribution
+/// as its caption.
+///
+/// For images, the caption links to the artist and the source (if
+/// known), and for
+/// gifs it names the anime they're from:
+///
+/// ```html
+/// <figure><img src="https://nekos.best/api/v2/neko/..." alt="art by someone"><figcaption>art by <a href="...">someone</a> (<a href="...">source</a>)</figcaption></figure>
+/// ```
+pub fn html_snippet(response: &NekosBestResponseSingle) -> String {
+    let src = escape_html(&response.url);
+    let (alt, caption) = match &response.details {
+        Details::Image(image) => {
+            let artist = escape_html(&image.artist.name);
+            let mut caption = format!(
+                r#"art by <a href="{}">{artist}</a>"#,
+                escape_html(image.artist.href.as_str()),
+            );
+            if let Some(source) = &image.source {
+                caption.push_str(&format!(
+                    r#" (<a href="{}">source</a>)"#,
+                    escape_html(source.url.as_str())
+                ));
+            }
+            (format!("art by {artist}"), caption)
+        }
+        Details::Gif(gif) => {
+            let anime = escape_html(&gif.anime_name);
+            (format!("from {anime}"), format!("from {anime}"))
+        }
+    };
+
+    format!(r#"<figure><img src="{src}" alt="{alt}"><figcaption>{caption}</figcaption></figure>"#)
+}
+
+/// The values of the OpenGraph and Twitter card meta tags for a page
+/// built around an image.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpenGraph {
+    /// The title, derived from the category, e.g. `"Neko"`.
+    pub title: String,
+    /// The attribution, e.g. `"Art by someone"`.
+    pub description: String,
+    pub image: String,
+    pub image_type: &'static str,
+}
+
+impl OpenGraph {
+    pub fn new(category: Category, response: &NekosBestResponseSingle) -> Self {
+        let name = category.to_url_name();
+        let mut title = name[..1].to_uppercase();
+        title.push_str(&name[1..]);
+
+        let description = match &response.details {
+            Details::Image(image) => format!("Art by {}", image.artist.name),
+            Details::Gif(gif) => format!("From {}", gif.anime_name),
+        };
+
+        Self {
+            title,
+            description,
+            image: response.url.clone(),
+            image_type: category.format().mime_type(),
+        }
+    }
+
+    /// The `(property, content)` pairs of the meta tags, unescaped.
+    pub fn properties(&self) -> [(&'static str, &str); 8] {
+        [
+            ("og:title", &self.title),
+            ("og:description", &self.description),
+            ("og:image", &self.image),
+            ("og:image:type", self.image_type),
+            ("twitter:card", "summary_large_image"),
+            ("twitter:title", &self.title),
+            ("twitter:description", &self.description),
+            ("twitter:image", &self.image),
+        ]
+    }
+
+    /// The `<meta>` tags, escaped and separated by newlines.
+    pub fn meta_tags(&self) -> String {
+        self.properties()
+            .iter()
+            .map(|(property, content)| {
+                let attr = if property.starts_with("og:") {
+                    "property"
+                } else {
+                    "name"
+                };
+                format!(
+                    r#"<meta {attr}="{property}" content="{}">"#,
+                    escape_html(content)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn escape() {
+        assert_eq!(
+            escape_html(r#"<a href="x">Tom & 'Jerry'</a>"#),
+            "&lt;a href=&quot;x&quot;&gt;Tom &amp; &#39;Jerry&#39;&lt;/a&gt;"
+        );
+    }
+
+    #[test]
+    fn snippets() {
+        let image = NekosBestResponseSingle::test_image(
+            "https://nekos.best/api/v2/neko/a.png",
+            "<script>",
+            "https://example.com/artist?a=1&b=2",
+        );
+        assert_eq!(
+            html_snippet(&image),
+            r#"<figure><img src="https://nekos.best/api/v2/neko/a.png" alt="art by &lt;script&gt;"><figcaption>art by <a href="https://example.com/artist?a=1&amp;b=2">&lt;script&gt;</a> (<a href="https://example.com/source">source</a>)</figcaption></figure>"#
+        );
+
+        let gif =
+            NekosBestResponseSingle::test_gif("https://nekos.best/api/v2/pat/a.gif", "Senko & co");
+        assert_eq!(
+            html_snippet(&gif),
+            r#"<figure><img src="https://nekos.best/api/v2/pat/a.gif" alt="from Senko &amp; co"><figcaption>from Senko &amp; co</figcaption></figure>"#
+        );
+    }
+
+    #[test]
+    fn open_graph() {
+        let gif = NekosBestResponseSingle::test_gif(
+            "https://nekos.best/api/v2/thumbsup/a.gif",
+            "Senko & co",
+        );
+        let og = OpenGraph::new(Category::ThumbsUp, &gif);
+        assert_eq!(og.title, "Thumbsup");
+        assert_eq!(og.description, "From Senko & co");
+        assert_eq!(og.image_type, "image/gif");
+
+        let tags = og.meta_tags();
+        assert!(tags.starts_with(r#"<meta property="og:title" content="Thumbsup">"#));
+        assert!(tags.contains(r#"<meta name="twitter:description" content="From Senko &amp; co">"#));
+        assert_eq!(tags.lines().count(), 8);
+    }
+}
@@ -0,0 +1,265 @@
+//! The metadata of the API endpoints, as served by `/endpoints`.
+
+use std::collections::HashMap;
+
+#[cfg(feature = "blocking")]
+use nb_blocking_util::blocking;
+
+#[cfg(feature = "reqwest")]
+use crate::client::{default_client, Client};
+use crate::Category;
+#[cfg(feature = "reqwest")]
+use crate::{sans_io, NekosBestError};
+
+/// The format of the images served by an endpoint.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ImageFormat {
+    Png,
+    Gif,
+}
+
+impl ImageFormat {
+    /// The file extension for this format, without the leading `.`.
+    pub const fn extension(self) -> &'static str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Gif => "gif",
+        }
+    }
+
+    /// The mime type for this format.
+    pub const fn mime_type(self) -> &'static str {
+        match self {
+            ImageFormat::Png => "image/png",
+            ImageFormat::Gif => "image/gif",
+        }
+    }
+
+    /// Parses a format from its file extension.
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension {
+            "png" => Some(ImageFormat::Png),
+            "gif" => Some(ImageFormat::Gif),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for ImageFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.extension().fmt(f)
+    }
+}
+
+/// The description of an endpoint, as bundled with the crate.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct EmbeddedEndpoint {
+    pub category: Category,
+    pub format: ImageFormat,
+    /// The name of the first file, when the files are numbered.
+    pub min: Option<&'static str>,
+    /// The name of the last file at the time of the snapshot, when the
+    /// files are numbered.
+    pub max: Option<&'static str>,
+}
+
+impl EmbeddedEndpoint {
+    /// The url name of the endpoint.
+    pub const fn name(&self) -> &'static str {
+        self.category.to_url_name()
+    }
+
+    /// The snapshot as an [`EndpointDesc`], like `/endpoints` returns it.
+    pub fn desc(&self) -> EndpointDesc {
+        EndpointDesc {
+            format: self.format.extension().to_owned(),
+            min: self.min.map(str::to_owned),
+            max: self.max.map(str::to_owned),
+        }
+    }
+}
+
+/// A snapshot of `/endpoints` at the time of the release of this crate,
+/// for every [`Category`], in the order of [`Category::ALL_VARIANTS`].
+///
+/// The file ranges (`min` / `max`) are those of this release: the API
+/// only adds files, so every file in them exists, but newer files are
+/// only known at runtime, through
+/// [`crate::client::Client::refresh_endpoints`].
+pub const EMBEDDED_ENDPOINTS: &[EmbeddedEndpoint] = Category::EMBEDDED_ENDPOINTS;
+
+/// The description of an endpoint, as returned by `/endpoints`.
+#[derive(serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct EndpointDesc {
+    /// The file extension of the images, e.g. `"png"`.
+    pub format: String,
+    /// The name of the first file, when the files are numbered.
+    #[serde(default)]
+    pub min: Option<String>,
+    /// The name of the last file, when the files are numbered.
+    #[serde(default)]
+    pub max: Option<String>,
+}
+
+impl EndpointDesc {
+    /// The range of file names, if the endpoint advertises a numeric one,
+    /// along with the width the names are zero-padded to.
+    pub fn file_range(&self) -> Option<(u64, u64, usize)> {
+        let (min, max) = (self.min.as_deref()?, self.max.as_deref()?);
+        let range = (min.parse().ok()?, max.parse().ok()?, max.len());
+        (range.0 <= range.1).then_some(range)
+    }
+
+    /// The format of the images, if it is one known to this crate.
+    pub fn image_format(&self) -> Option<ImageFormat> {
+        ImageFormat::from_extension(&self.format)
+    }
+
+    /// The name of the `n`-th file in [`EndpointDesc::file_range`].
+    pub fn file_name(&self, n: u64) -> Option<String> {
+        let (min, max, width) = self.file_range()?;
+        let id = min.checked_add(n).filter(|id| *id <= max)?;
+        Some(format!("{id:0width$}.{}", self.format))
+    }
+
+    /// How many files the endpoint has, if it advertises a numeric
+    /// [`EndpointDesc::file_range`].
+    pub fn file_count(&self) -> Option<u64> {
+        let (min, max, _) = self.file_range()?;
+        Some(max - min + 1)
+    }
+}
+
+/// The metadata of all the endpoints, keyed by their url name.
+pub type EndpointsMap = HashMap<String, EndpointDesc>;
+
+/// The metadata of the endpoint of `category` in `endpoints`.
+pub fn endpoint(endpoints: &EndpointsMap, category: Category) -> Option<&EndpointDesc> {
+    endpoints.get(category.to_url_name())
+}
+
+/// The snapshot of the endpoint of `category` in [`EMBEDDED_ENDPOINTS`].
+#[cfg_attr(not(feature = "reqwest"), allow(dead_code))]
+fn embedded_endpoint(category: Category) -> Option<&'static EmbeddedEndpoint> {
+    EMBEDDED_ENDPOINTS
+        .iter()
+        .find(|embedded| embedded.category == category)
+}
+
+/// The metadata of the endpoint of `category`: the metadata loaded by
+/// [`Client::refresh_endpoints`] if there is any, otherwise fetched,
+/// falling back on the snapshot in [`EMBEDDED_ENDPOINTS`] if that fails.
+#[cfg(feature = "reqwest")]
+#[cfg_attr(feature = "blocking", blocking)]
+pub(crate) async fn endpoint_desc(
+    client: &Client,
+    category: Category,
+) -> Result<EndpointDesc, NekosBestError> {
+    if let Some(endpoints) = client.endpoints() {
+        return endpoint(&endpoints, category)
+            .cloned()
+            .ok_or(NekosBestError::NotFound);
+    }
+
+    let fetched = get_endpoints_with_client(client).await;
+    match fetched {
+        Ok(endpoints) => endpoint(&endpoints, category)
+            .cloned()
+            .ok_or(NekosBestError::NotFound),
+        Err(e) => embedded_endpoint(category)
+            .map(EmbeddedEndpoint::desc)
+            .filter(|desc| desc.file_range().is_some())
+            .ok_or(e),
+    }
+}
+
+/// Gets the metadata of all the endpoints from `/endpoints`, with a supplied client.
+///
+/// See also [`Client::refresh_endpoints`], to keep it in the client.
+#[cfg(feature = "reqwest")]
+#[cfg_attr(feature = "blocking", blocking)]
+pub async fn get_endpoints_with_client(client: &Client) -> Result<EndpointsMap, NekosBestError> {
+    let url = sans_io::endpoints_url(client.base_url());
+    let resp = crate::implementation::http_get(client, &url).await?;
+
+    sans_io::parse_body(&resp.body).map_err(|e| e.with_response(&url, resp.status, &resp.body))
+}
+
+/// Gets the metadata of all the endpoints from `/endpoints`, with the default client.
+#[cfg(feature = "reqwest")]
+#[cfg_attr(feature = "blocking", blocking)]
+pub async fn get_endpoints() -> Result<EndpointsMap, NekosBestError> {
+    get_endpoints_with_client(&default_client()).await
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn formats() {
+        assert_eq!(Category::Neko.format(), ImageFormat::Png);
+        assert_eq!(Category::Pat.format(), ImageFormat::Gif);
+        for format in [ImageFormat::Png, ImageFormat::Gif] {
+            assert_eq!(
+                ImageFormat::from_extension(format.extension()),
+                Some(format)
+            );
+        }
+    }
+
+    #[test]
+    fn embedded() {
+        assert_eq!(EMBEDDED_ENDPOINTS.len(), Category::ALL_VARIANTS.len());
+        for (endpoint, category) in EMBEDDED_ENDPOINTS.iter().zip(Category::ALL_VARIANTS) {
+            assert_eq!(endpoint.category, *category);
+            assert_eq!(endpoint.format, category.format());
+            let desc = endpoint.desc();
+            assert_eq!(desc.image_format(), Some(endpoint.format));
+            assert_eq!(desc.file_range().is_some(), endpoint.min.is_some());
+        }
+
+        let neko = embedded_endpoint(Category::Neko).unwrap().desc();
+        assert_eq!(neko.file_name(0).unwrap(), "0001.png");
+        assert!(neko.file_count().unwrap() > 1);
+    }
+
+    #[cfg(all(feature = "reqwest", not(feature = "blocking")))]
+    #[tokio::test]
+    async fn offline() {
+        // nothing listens on the discard port
+        let client = Client::builder()
+            .base_url("http://127.0.0.1:9")
+            .build()
+            .unwrap();
+
+        let desc = endpoint_desc(&client, Category::Neko).await.unwrap();
+        assert_eq!(desc, embedded_endpoint(Category::Neko).unwrap().desc());
+        assert!(matches!(
+            endpoint_desc(&client, Category::Pat).await,
+            Err(NekosBestError::ReqwestError(_))
+        ));
+    }
+
+    #[test]
+    fn file_names() {
+        let desc = EndpointDesc {
+            format: "png".to_owned(),
+            min: Some("0001".to_owned()),
+            max: Some("0491".to_owned()),
+        };
+        assert_eq!(desc.file_range(), Some((1, 491, 4)));
+        assert_eq!(desc.file_count(), Some(491));
+        assert_eq!(desc.file_name(0).unwrap(), "0001.png");
+        assert_eq!(desc.file_name(490).unwrap(), "0491.png");
+        assert_eq!(desc.file_name(491), None);
+
+        let uuids = EndpointDesc {
+            min: Some("1efcda2d-d0d3-4e96-9b40-86852374b4bc".to_owned()),
+            ..desc
+        };
+        assert_eq!(uuids.file_range(), None);
+        assert_eq!(uuids.file_count(), None);
+    }
+}
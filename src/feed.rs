@@ -0,0 +1,221 @@
+//! RSS and Atom feeds of images, for publishing on static sites.
+
+use std::collections::VecDeque;
+
+use chrono::{DateTime, Utc};
+#[cfg(feature = "blocking")]
+use nb_blocking_util::blocking;
+
+use crate::client::Client;
+use crate::embed::{escape_html, html_snippet};
+use crate::{get_with_client, Category, NekosBestError, NekosBestResponseSingle};
+
+/// An image in a [`Feed`].
+#[derive(Debug, Clone)]
+pub struct FeedItem {
+    pub category: Category,
+    pub response: NekosBestResponseSingle,
+    pub fetched_at: DateTime<Utc>,
+}
+
+/// A feed of images from some categories, newest first.
+///
+/// Call [`Feed::refresh_with_client`] periodically to add an image from
+/// each category, then render the feed with [`Feed::to_rss`] or
+/// [`Feed::to_atom`]. The body of every item is the
+/// [`html_snippet`] of the image, with its attribution.
+#[derive(Debug, Clone)]
+pub struct Feed {
+    title: String,
+    link: String,
+    categories: Vec<Category>,
+    max_items: usize,
+    items: VecDeque<FeedItem>,
+}
+
+impl Feed {
+    /// The default for [`Feed::max_items`].
+    pub const DEFAULT_MAX_ITEMS: usize = 50;
+
+    /// Creates an empty feed, with `link` being the url of the page the
+    /// feed is published for.
+    pub fn new(
+        title: impl Into<String>,
+        link: impl Into<String>,
+        categories: impl IntoIterator<Item = impl Into<Category>>,
+    ) -> Self {
+        Self {
+            title: title.into(),
+            link: link.into(),
+            categories: categories.into_iter().map(Into::into).collect(),
+            max_items: Self::DEFAULT_MAX_ITEMS,
+            items: VecDeque::new(),
+        }
+    }
+
+    /// Sets how many items the feed keeps, dropping the oldest ones beyond that.
+    pub fn max_items(mut self, max_items: usize) -> Self {
+        self.max_items = max_items;
+        self.items.truncate(max_items);
+        self
+    }
+
+    /// The items in the feed, newest first.
+    pub fn items(&self) -> impl Iterator<Item = &FeedItem> {
+        self.items.iter()
+    }
+
+    /// Adds an item at the top of the feed.
+    pub fn push(&mut self, item: FeedItem) {
+        self.items.push_front(item);
+        self.items.truncate(self.max_items);
+    }
+
+    /// Fetches a new image from each of the categories of the feed.
+    ///
+    /// # Errors
+    /// Same as [`get_with_client`]. The images fetched before the error
+    /// are still added.
+    #[cfg_attr(feature = "blocking", blocking)]
+    pub async fn refresh_with_client(&mut self, client: &Client) -> Result<(), NekosBestError> {
+        for category in self.categories.clone() {
+            let response = get_with_client(client, category).await?;
+            self.push(FeedItem {
+                category,
+                response,
+                fetched_at: Utc::now(),
+            });
+        }
+
+        Ok(())
+    }
+
+    fn updated(&self) -> DateTime<Utc> {
+        self.items
+            .iter()
+            .map(|item| item.fetched_at)
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// Renders the feed as RSS 2.0.
+    pub fn to_rss(&self) -> String {
+        let mut rss = format!(
+            concat!(
+                r#"<?xml version="1.0" encoding="UTF-8"?>"#,
+                "\n",
+                r#"<rss version="2.0"><channel>"#,
+                "<title>{}</title><link>{}</link><description>{}</description>",
+                "<lastBuildDate>{}</lastBuildDate>",
+            ),
+            escape_html(&self.title),
+            escape_html(&self.link),
+            escape_html(&self.title),
+            self.updated().to_rfc2822(),
+        );
+
+        for item in &self.items {
+            rss.push_str(&format!(
+                concat!(
+                    "<item><title>{}</title><link>{url}</link>",
+                    r#"<guid isPermaLink="true">{url}</guid>"#,
+                    "<pubDate>{}</pubDate><description>{}</description></item>",
+                ),
+                item.category,
+                item.fetched_at.to_rfc2822(),
+                escape_html(&html_snippet(&item.response)),
+                url = escape_html(&item.response.url),
+            ));
+        }
+
+        rss.push_str("</channel></rss>");
+        rss
+    }
+
+    /// Renders the feed as Atom.
+    pub fn to_atom(&self) -> String {
+        let link = escape_html(&self.link);
+        let mut atom = format!(
+            concat!(
+                r#"<?xml version="1.0" encoding="UTF-8"?>"#,
+                "\n",
+                r#"<feed xmlns="http://www.w3.org/2005/Atom">"#,
+                r#"<title>{}</title><link href="{link}"/><id>{link}</id>"#,
+                "<updated>{}</updated>",
+            ),
+            escape_html(&self.title),
+            self.updated().to_rfc3339(),
+            link = link,
+        );
+
+        for item in &self.items {
+            atom.push_str(&format!(
+                concat!(
+                    r#"<entry><title>{}</title><link href="{url}"/><id>{url}</id>"#,
+                    "<updated>{}</updated>",
+                    r#"<content type="html">{}</content></entry>"#,
+                ),
+                item.category,
+                item.fetched_at.to_rfc3339(),
+                escape_html(&html_snippet(&item.response)),
+                url = escape_html(&item.response.url),
+            ));
+        }
+
+        atom.push_str("</feed>");
+        atom
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn item(n: u32) -> FeedItem {
+        FeedItem {
+            category: Category::Pat,
+            response: NekosBestResponseSingle::test_gif(
+                format!("https://nekos.best/api/v2/pat/{n}.gif"),
+                "Senko & co",
+            ),
+            fetched_at: DateTime::from_timestamp(1_700_000_000 + i64::from(n), 0).unwrap(),
+        }
+    }
+
+    #[test]
+    fn max_items() {
+        let mut feed = Feed::new("pats", "https://example.com", [Category::Pat]).max_items(2);
+        (0..3).for_each(|n| feed.push(item(n)));
+        let urls = feed
+            .items()
+            .map(|i| i.response.url.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            urls,
+            [
+                "https://nekos.best/api/v2/pat/2.gif",
+                "https://nekos.best/api/v2/pat/1.gif"
+            ]
+        );
+    }
+
+    #[test]
+    fn render() {
+        let mut feed = Feed::new("Tom & Jerry", "https://example.com", [Category::Pat]);
+        feed.push(item(0));
+
+        let rss = feed.to_rss();
+        assert!(rss.contains("<title>Tom &amp; Jerry</title>"));
+        assert!(rss.contains("<pubDate>Tue, 14 Nov 2023 22:13:20 +0000</pubDate>"));
+        assert!(rss.contains("<description>&lt;figure&gt;&lt;img src=&quot;https://nekos.best/api/v2/pat/0.gif&quot;"));
+        assert!(rss.contains("from Senko &amp;amp; co"));
+        assert!(rss.ends_with("</item></channel></rss>"));
+
+        let atom = feed.to_atom();
+        assert!(atom.contains("<updated>2023-11-14T22:13:20+00:00</updated>"));
+        assert!(atom.contains(
+            r#"<entry><title>pat</title><link href="https://nekos.best/api/v2/pat/0.gif"/>"#
+        ));
+        assert!(atom.ends_with("</entry></feed>"));
+    }
+}
@@ -0,0 +1,130 @@
+//! Paging through the images of a response.
+
+use crate::{NekosBestResponse, NekosBestResponseSingle};
+
+/// A cursor over a list of images, for "previous / next" style
+/// pagination, independent of where it is displayed.
+///
+/// More images can be appended at any point (e.g. as they are fetched),
+/// without moving the cursor.
+#[derive(Debug, Clone, Default)]
+pub struct Gallery {
+    images: Vec<NekosBestResponseSingle>,
+    index: usize,
+}
+
+impl Gallery {
+    /// Creates a gallery over the images of `response`, positioned on the first one.
+    pub fn new(response: NekosBestResponse) -> Self {
+        Self {
+            images: response.0,
+            index: 0,
+        }
+    }
+
+    /// The current image, or [`None`] if the gallery is empty.
+    pub fn current(&self) -> Option<&NekosBestResponseSingle> {
+        self.images.get(self.index)
+    }
+
+    /// Moves to the next image and returns it, or returns [`None`] without
+    /// moving if already on the last one.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Option<&NekosBestResponseSingle> {
+        self.seek(self.index + 1)
+    }
+
+    /// Moves to the previous image and returns it, or returns [`None`]
+    /// without moving if already on the first one.
+    pub fn prev(&mut self) -> Option<&NekosBestResponseSingle> {
+        self.seek(self.index.checked_sub(1)?)
+    }
+
+    /// Moves to the image at `index` and returns it, or returns [`None`]
+    /// without moving if it is out of bounds.
+    pub fn seek(&mut self, index: usize) -> Option<&NekosBestResponseSingle> {
+        if index >= self.images.len() {
+            return None;
+        }
+        self.index = index;
+        self.current()
+    }
+
+    /// The index of the current image.
+    pub fn position(&self) -> usize {
+        self.index
+    }
+
+    pub fn len(&self) -> usize {
+        self.images.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.images.is_empty()
+    }
+
+    pub fn has_next(&self) -> bool {
+        self.index + 1 < self.images.len()
+    }
+
+    pub fn has_prev(&self) -> bool {
+        self.index > 0
+    }
+
+    /// Appends an image at the end of the gallery.
+    pub fn push(&mut self, image: NekosBestResponseSingle) {
+        self.images.push(image);
+    }
+
+    /// All the images in the gallery.
+    pub fn images(&self) -> &[NekosBestResponseSingle] {
+        &self.images
+    }
+}
+
+impl From<NekosBestResponse> for Gallery {
+    fn from(response: NekosBestResponse) -> Self {
+        Self::new(response)
+    }
+}
+
+impl Extend<NekosBestResponseSingle> for Gallery {
+    fn extend<T: IntoIterator<Item = NekosBestResponseSingle>>(&mut self, iter: T) {
+        self.images.extend(iter);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn image(n: usize) -> NekosBestResponseSingle {
+        NekosBestResponseSingle::test_gif(format!("https://nekos.best/api/v2/pat/{n}.gif"), "Senko")
+    }
+
+    #[test]
+    fn paging() {
+        let mut gallery = Gallery::new(NekosBestResponse(vec![image(0), image(1)]));
+        assert_eq!(gallery.current().unwrap().url, image(0).url);
+        assert!(gallery.prev().is_none());
+        assert!(!gallery.has_prev() && gallery.has_next());
+
+        assert_eq!(gallery.next().unwrap().url, image(1).url);
+        assert!(gallery.next().is_none());
+        assert_eq!(gallery.position(), 1);
+
+        gallery.push(image(2));
+        assert!(gallery.has_next());
+        assert_eq!(gallery.next().unwrap().url, image(2).url);
+        assert_eq!(gallery.prev().unwrap().url, image(1).url);
+    }
+
+    #[test]
+    fn empty() {
+        let mut gallery = Gallery::default();
+        assert!(gallery.current().is_none());
+        assert!(gallery.next().is_none());
+        assert!(gallery.seek(0).is_none());
+        assert!(!gallery.has_next() && !gallery.has_prev());
+    }
+}
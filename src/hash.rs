@@ -0,0 +1,72 @@
+//! Content hashes of images, behind the `hash` feature, to deduplicate
+//! and verify downloaded files.
+
+use std::fmt::Write;
+
+use sha2::Digest;
+
+/// The hashes of the contents of a file.
+///
+/// The BLAKE3 hash is only computed with the `blake3` feature.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ContentHash {
+    pub sha256: [u8; 32],
+    #[cfg(feature = "blake3")]
+    pub blake3: [u8; 32],
+}
+
+impl ContentHash {
+    /// Hashes `bytes`.
+    pub fn of(bytes: &[u8]) -> Self {
+        Self {
+            sha256: sha2::Sha256::digest(bytes).into(),
+            #[cfg(feature = "blake3")]
+            blake3: blake3::hash(bytes).into(),
+        }
+    }
+
+    /// Hashes the contents of the file at `path`.
+    pub fn of_file(path: impl AsRef<std::path::Path>) -> std::io::Result<Self> {
+        Ok(Self::of(&std::fs::read(path)?))
+    }
+
+    /// The SHA-256 hash, as lowercase hex.
+    pub fn sha256_hex(&self) -> String {
+        to_hex(&self.sha256)
+    }
+
+    /// The BLAKE3 hash, as lowercase hex.
+    #[cfg(feature = "blake3")]
+    pub fn blake3_hex(&self) -> String {
+        to_hex(&self.blake3)
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .fold(String::with_capacity(bytes.len() * 2), |mut hex, b| {
+            let _ = write!(hex, "{b:02x}");
+            hex
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn hashes() {
+        let hash = ContentHash::of(b"abc");
+        assert_eq!(
+            hash.sha256_hex(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        #[cfg(feature = "blake3")]
+        assert_eq!(
+            hash.blake3_hex(),
+            "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85"
+        );
+        assert_ne!(hash, ContentHash::of(b"abd"));
+    }
+}
@@ -0,0 +1,166 @@
+//! Parsing the urls of the images served by nekos.best, see
+//! [`NekosBestUrl`].
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::endpoints::ImageFormat;
+use crate::Category;
+
+/// The url of an image, like
+/// `https://nekos.best/api/v2/neko/1efcda2d-d0d3-4e96-9b40-86852374b4bc.png`,
+/// split into its parts.
+///
+/// Only the last two segments of the path are looked at, so urls of
+/// other hosts serving the API (mirrors, or
+/// [`crate::client::ClientBuilder::base_url`]) parse as well.
+///
+/// ```
+/// use nekosbest::{Category, NekosBestUrl};
+///
+/// let url: NekosBestUrl = "https://nekos.best/api/v2/pat/0001.gif".parse().unwrap();
+/// assert_eq!(url.category, Category::Pat);
+/// assert_eq!(url.file_id, "0001");
+/// assert_eq!(
+///     url.with_file_id("0002").to_string(),
+///     "https://nekos.best/api/v2/pat/0002.gif"
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct NekosBestUrl {
+    pub category: Category,
+    /// The name of the file, without the extension, see
+    /// [`crate::sans_io::file_id`].
+    pub file_id: String,
+    pub extension: ImageFormat,
+}
+
+/// Why a url isn't the url of an image of the API.
+#[derive(Debug, thiserror::Error)]
+pub enum NekosBestUrlError {
+    #[error("invalid url")]
+    Url(#[from] url::ParseError),
+    #[error("url has no category and file name")]
+    MissingSegments,
+    #[error("unknown category {0:?}")]
+    UnknownCategory(String),
+    #[error("unknown file extension {0:?}")]
+    UnknownExtension(String),
+}
+
+impl NekosBestUrl {
+    /// The url of the file `file_id` of `category`, in the format of the
+    /// category.
+    pub fn new(category: impl Into<Category>, file_id: impl Into<String>) -> Self {
+        let category = category.into();
+        Self {
+            category,
+            file_id: file_id.into(),
+            extension: category.format(),
+        }
+    }
+
+    /// Parses an url.
+    ///
+    /// # Errors
+    /// See [`NekosBestUrlError`].
+    pub fn parse(url: &str) -> Result<Self, NekosBestUrlError> {
+        let url = url::Url::parse(url)?;
+        let mut segments = url
+            .path_segments()
+            .ok_or(NekosBestUrlError::MissingSegments)?
+            .rev();
+        let (Some(file), Some(category)) = (segments.next(), segments.next()) else {
+            return Err(NekosBestUrlError::MissingSegments);
+        };
+        let Some((file_id, extension)) = file.rsplit_once('.') else {
+            return Err(NekosBestUrlError::UnknownExtension(String::new()));
+        };
+        if file_id.is_empty() {
+            return Err(NekosBestUrlError::MissingSegments);
+        }
+
+        Ok(Self {
+            category: Category::from_url_name(category)
+                .ok_or_else(|| NekosBestUrlError::UnknownCategory(category.to_owned()))?,
+            file_id: file_id.to_owned(),
+            extension: ImageFormat::from_extension(extension)
+                .ok_or_else(|| NekosBestUrlError::UnknownExtension(extension.to_owned()))?,
+        })
+    }
+
+    /// The file name, with the extension.
+    pub fn file_name(&self) -> String {
+        format!("{}.{}", self.file_id, self.extension)
+    }
+
+    /// The url of this file, under `base_url` (like [`crate::BASE_URL`]).
+    pub fn to_url(&self, base_url: &str) -> String {
+        format!("{}/{}", self.category.to_url(base_url), self.file_name())
+    }
+
+    /// Another file of the same category.
+    pub fn with_file_id(&self, file_id: impl Into<String>) -> Self {
+        Self {
+            file_id: file_id.into(),
+            ..self.clone()
+        }
+    }
+}
+
+/// Formats the url under [`crate::BASE_URL`].
+impl fmt::Display for NekosBestUrl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_url(crate::BASE_URL))
+    }
+}
+
+impl FromStr for NekosBestUrl {
+    type Err = NekosBestUrlError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let url = NekosBestUrl::parse(
+            "https://nekos.best/api/v2/neko/1efcda2d-d0d3-4e96-9b40-86852374b4bc.png?x=1",
+        )
+        .unwrap();
+        assert_eq!(
+            url,
+            NekosBestUrl::new(Category::Neko, "1efcda2d-d0d3-4e96-9b40-86852374b4bc")
+        );
+        assert_eq!(
+            url.to_url("http://localhost/api"),
+            "http://localhost/api/neko/1efcda2d-d0d3-4e96-9b40-86852374b4bc.png"
+        );
+
+        assert!(matches!(
+            NekosBestUrl::parse("https://nekos.best/api/v2/notacategory/a.png"),
+            Err(NekosBestUrlError::UnknownCategory(c)) if c == "notacategory"
+        ));
+        assert!(matches!(
+            NekosBestUrl::parse("https://nekos.best/api/v2/neko/a.jpg"),
+            Err(NekosBestUrlError::UnknownExtension(e)) if e == "jpg"
+        ));
+        assert!(matches!(
+            NekosBestUrl::parse("https://nekos.best/a.png"),
+            Err(NekosBestUrlError::MissingSegments)
+        ));
+        assert!(matches!(
+            NekosBestUrl::parse("https://nekos.best/api/v2/neko/.png"),
+            Err(NekosBestUrlError::MissingSegments)
+        ));
+        assert!(matches!(
+            NekosBestUrl::parse("neko/a.png"),
+            Err(NekosBestUrlError::Url(_))
+        ));
+    }
+}
@@ -1,11 +1,12 @@
-use reqwest::header::HeaderMap;
-use reqwest::IntoUrl;
-use serde::Serializer;
-use std::string::FromUtf8Error;
+use serde::de::DeserializeOwned;
 
 use crate::{
-    details::{GifDetails, ImageDetails},
-    Category, NekosBestError, NekosBestResponse, NekosBestResponseSingle, BASE_URL,
+    backend::{HttpRequest, HttpResponse},
+    category::CustomCategory,
+    details::{Details, GifDetails, ImageDetails},
+    endpoints::ImageFormat,
+    sans_io, Category, NekosBestError, NekosBestFile, NekosBestResponse, NekosBestResponseSingle,
+    SearchQuery,
 };
 
 #[cfg(feature = "blocking")]
@@ -15,32 +16,176 @@ use nb_blocking_util::blocking;
 #[path = "strong_types_impl.rs"]
 mod strong_types_impl;
 
-use crate::client::{Client, ClientConfig, ReqBuilder, ReqwestResponse};
+use crate::client::{default_client, Client};
 #[cfg(feature = "strong-types")]
+#[allow(deprecated)]
 pub use strong_types_impl::{
     get as st_get, get_amount as st_get_amount, get_with_client as st_get_with_client,
     get_with_client_amount as st_get_with_client_amount, search as st_search,
     search_with_client as st_search_with_client,
 };
 
+/// The body of a successful response, along with where it came from, for
+/// the decoding errors.
+struct ResponseBody {
+    url: String,
+    status: u16,
+    bytes: Vec<u8>,
+}
+
+impl ResponseBody {
+    /// Parses the body, see [`sans_io::parse_body`].
+    ///
+    /// # Errors
+    /// [`NekosBestError::DecodingResponse`] if the body can't be decoded.
+    fn parse<T: DeserializeOwned>(&self) -> Result<T, NekosBestError> {
+        let result = sans_io::parse_body(&self.bytes)
+            .map_err(|e| e.with_response(&self.url, self.status, &self.bytes));
+        #[cfg(feature = "metrics-facade")]
+        if let Err(e) = &result {
+            crate::telemetry::record_error(e);
+        }
+        result
+    }
+
+    /// Parses the body, for a single image, see
+    /// [`sans_io::parse_response_single`].
+    fn parse_single(&self) -> Result<NekosBestResponseSingle, NekosBestError> {
+        let mut response: NekosBestResponse = self.parse()?;
+        response.0.pop().ok_or(NekosBestError::NotFound)
+    }
+}
+
+/// Sends a `GET` request for `url` through the client, see
+/// [`Client::send_http`].
+///
+/// # Errors
+/// [`NekosBestError::Status`] (or [`NekosBestError::RateLimited`]) if the
+/// status isn't a success, see [`sans_io::check_status`], otherwise the
+/// errors of [`Client::send_http`].
+#[cfg_attr(feature = "blocking", blocking)]
+pub(crate) async fn http_get(client: &Client, url: &str) -> Result<HttpResponse, NekosBestError> {
+    let response = client.send_http(HttpRequest::get(url)).await?;
+    check_status(response)
+}
+
+/// Fails on the statuses of `response` that aren't successes, see
+/// [`sans_io::check_status`].
+pub(crate) fn check_status(response: HttpResponse) -> Result<HttpResponse, NekosBestError> {
+    let result = response.error_for_status();
+    #[cfg(feature = "metrics-facade")]
+    if let Err(e) = &result {
+        crate::telemetry::record_error(e);
+    }
+    result
+}
+
+impl ResponseBody {
+    fn new(url: String, response: HttpResponse) -> Self {
+        Self {
+            url,
+            status: response.status,
+            bytes: response.body,
+        }
+    }
+}
+
+#[cfg_attr(feature = "blocking", blocking)]
+async fn response_body(client: &Client, url: String) -> Result<ResponseBody, NekosBestError> {
+    let response = http_get(client, &url).await?;
+    Ok(ResponseBody::new(url, response))
+}
+
+/// Gets `amount` images from `category`, or the default of the API
+/// without an amount, going around the cache and the request coalescing
+/// of the client (but still caching the response).
+#[cfg_attr(feature = "blocking", blocking)]
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", skip(client))
+)]
+pub(crate) async fn fetch(
+    client: &Client,
+    category: Category,
+    amount: Option<u8>,
+) -> Result<NekosBestResponse, NekosBestError> {
+    let url = match amount {
+        Some(amount) => sans_io::category_amount_url(client.base_url(), category, amount),
+        None => sans_io::category_url(client.base_url(), category),
+    };
+    let body = response_body(client, url).await?;
+    let response: NekosBestResponse = body.parse()?;
+
+    if let Some(cache) = client.cache() {
+        cache.insert((category, amount), response.clone());
+    }
+    // Failing to cache the response shouldn't fail the request.
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(cache) = client.disk_cache() {
+        let _ = cache.insert(&(category, amount), &body.bytes);
+    }
+    Ok(response)
+}
+
+/// Gets `amount` images from `category` like [`get_with_client_amount`],
+/// but always from the API, like [`fetch`]: for the callers wanting new
+/// images on every call (batches, streams, buffers), which the cache of
+/// the client would answer with the same images until they expire.
+#[cfg_attr(feature = "blocking", blocking)]
+pub(crate) async fn fetch_amount(
+    client: &Client,
+    category: Category,
+    amount: u8,
+) -> Result<NekosBestResponse, NekosBestError> {
+    let amount = client.validate_amount(category, amount)?;
+    fetch(client, category, Some(amount)).await
+}
+
+/// Gets `amount` images from `category` like [`fetch`], but from the
+/// cache of the client if possible, coalescing the request with
+/// identical ones, and falling back to stale cached responses when
+/// offline, if enabled.
 #[cfg_attr(feature = "blocking", blocking)]
-async fn parse_from_response(
-    response: ReqwestResponse,
+async fn fetch_cached(
+    client: &Client,
+    category: Category,
+    amount: Option<u8>,
 ) -> Result<NekosBestResponse, NekosBestError> {
-    #[cfg(not(nekosbest_dbgjson))]
-    let v = response
-        .error_for_status()?
-        .json::<NekosBestResponse>()
-        .await?;
-
-    #[cfg(nekosbest_dbgjson)]
-    let v = {
-        let json = response.error_for_status()?.text().await?;
-        dbg!(&json);
-        serde_json::from_str::<NekosBestResponse>(&json)?
+    let key = (category, amount);
+    if let Some(response) = client.cache().and_then(|cache| cache.get(&key)) {
+        #[cfg(feature = "metrics-facade")]
+        crate::telemetry::record_cache_hit("memory");
+        return Ok(response);
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(response) = client.disk_cache().and_then(|cache| cache.get(&key)) {
+        #[cfg(feature = "metrics-facade")]
+        crate::telemetry::record_cache_hit("disk");
+        if let Some(cache) = client.cache() {
+            cache.insert(key, response.clone());
+        }
+        return Ok(response);
+    }
+    #[cfg(feature = "metrics-facade")]
+    if client.has_cache() {
+        crate::telemetry::record_cache_miss();
+    }
+
+    #[cfg(all(feature = "coalesce", not(feature = "blocking")))]
+    let result = match client.inflight() {
+        Some(inflight) => inflight.get(client, category, amount).await,
+        None => fetch(client, category, amount).await,
     };
+    #[cfg(not(all(feature = "coalesce", not(feature = "blocking"))))]
+    let result = fetch(client, category, amount).await;
 
-    Ok(v)
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Err(e) = &result {
+        if let Some(response) = client.offline_response(&key, e) {
+            return Ok(response);
+        }
+    }
+    result
 }
 
 /// Gets a single image, with a supplied client.
@@ -52,39 +197,158 @@ pub async fn get_with_client(
     client: &Client,
     category: impl Into<Category>,
 ) -> Result<NekosBestResponseSingle, NekosBestError> {
-    let r = client
-        .client
-        .get(format!("{BASE_URL}/{}", category.into()))
-        .send()
-        .await?;
-
-    let mut resp = parse_from_response(r).await?;
-    let resp = resp.0.pop().ok_or(NekosBestError::NotFound)?;
-
-    Ok(resp)
+    let mut response = fetch_cached(client, category.into(), None).await?;
+    response.0.pop().ok_or(NekosBestError::NotFound)
 }
 
 /// Gets `amount` images, with a supplied client.
-/// Note that the server clamps the amount to the 1..=20 range
 ///
 /// # Errors
-/// Any errors that can happen, refer to [`NekosBestError`].
+/// [`NekosBestError::InvalidAmount`] if `amount` is not in the
+/// `1..=`[`crate::MAX_AMOUNT`] range (unless the client clamps it, see
+/// [`crate::client::ClientBuilder::clamp_amount`]), or is more than the
+/// number of files in the category, if known. Otherwise, any errors
+/// that can happen, refer to [`NekosBestError`].
 #[cfg_attr(feature = "blocking", blocking)]
 pub async fn get_with_client_amount(
     client: &Client,
     category: impl Into<Category>,
     amount: impl Into<u8>,
 ) -> Result<NekosBestResponse, NekosBestError> {
-    let req = client
-        .client
-        .get(format!("{BASE_URL}/{}", category.into()))
-        .query(&[("amount", amount.into())]);
+    let category = category.into();
+    let amount = client.validate_amount(category, amount.into())?;
+
+    fetch_cached(client, category, Some(amount)).await
+}
+
+/// Gets exactly `amount` images, with a supplied client, splitting the
+/// request into several requests of at most [`crate::MAX_AMOUNT`] images.
+///
+/// The requests are sent one after the other, going around the cache of
+/// the client (which would otherwise return the same batch every time). If
+/// the API runs out of images (returns an empty batch), the images gathered
+/// so far are returned.
+///
+/// # Errors
+/// Same as [`get_with_client_amount`].
+#[cfg_attr(feature = "blocking", blocking)]
+pub async fn get_with_client_exact_amount(
+    client: &Client,
+    category: impl Into<Category>,
+    amount: usize,
+) -> Result<NekosBestResponse, NekosBestError> {
+    let category = category.into();
+    let mut images = Vec::with_capacity(amount);
+
+    while images.len() < amount {
+        let batch = (amount - images.len()).min(crate::MAX_AMOUNT.into()) as u8;
+        let response = fetch_amount(client, category, batch).await?;
+        if response.is_empty() {
+            break;
+        }
+        images.extend(response.0);
+    }
+
+    images.truncate(amount);
+    Ok(NekosBestResponse(images))
+}
+
+/// Gets exactly `amount` images, with the default client, see
+/// [`get_with_client_exact_amount`].
+///
+/// # Errors
+/// Same as [`get_with_client_amount`].
+#[cfg_attr(feature = "blocking", blocking)]
+pub async fn get_exact_amount(
+    category: impl Into<Category>,
+    amount: usize,
+) -> Result<NekosBestResponse, NekosBestError> {
+    get_with_client_exact_amount(&default_client(), category, amount).await
+}
+
+/// Gets `amount` distinct images, with a supplied client, requesting more
+/// images as long as there are duplicates, up to `max_requests` requests.
+///
+/// Images are considered the same if they have the same
+/// [`sans_io::file_id`]. If the API doesn't return enough distinct
+/// images within `max_requests` requests, fewer images are returned. Like
+/// [`get_with_client_exact_amount`], the requests go around the cache of
+/// the client.
+///
+/// # Errors
+/// Same as [`get_with_client_amount`].
+#[cfg_attr(feature = "blocking", blocking)]
+pub async fn get_with_client_unique_amount(
+    client: &Client,
+    category: impl Into<Category>,
+    amount: usize,
+    max_requests: usize,
+) -> Result<NekosBestResponse, NekosBestError> {
+    let category = category.into();
+    let mut seen = std::collections::HashSet::new();
+    let mut images = Vec::with_capacity(amount);
 
-    let r = req.send().await?;
+    for _ in 0..max_requests {
+        if images.len() >= amount {
+            break;
+        }
 
-    let v = parse_from_response(r).await?;
+        let batch = (amount - images.len()).min(crate::MAX_AMOUNT.into()) as u8;
+        let response = fetch_amount(client, category, batch).await?;
+        for image in response.0 {
+            if images.len() < amount && seen.insert(sans_io::file_id(&image.url).to_owned()) {
+                images.push(image);
+            }
+        }
+    }
 
-    Ok(v)
+    Ok(NekosBestResponse(images))
+}
+
+/// Gets `amount` distinct images, with the default client, see
+/// [`get_with_client_unique_amount`].
+///
+/// # Errors
+/// Same as [`get_with_client_amount`].
+#[cfg_attr(feature = "blocking", blocking)]
+pub async fn get_unique_amount(
+    category: impl Into<Category>,
+    amount: usize,
+    max_requests: usize,
+) -> Result<NekosBestResponse, NekosBestError> {
+    get_with_client_unique_amount(&default_client(), category, amount, max_requests).await
+}
+
+/// Gets a single image from a category by its name, with a supplied client.
+///
+/// # Errors
+/// Same as [`get_with_client`].
+#[cfg_attr(feature = "blocking", blocking)]
+pub async fn get_custom_with_client(
+    client: &Client,
+    category: &CustomCategory,
+) -> Result<NekosBestResponseSingle, NekosBestError> {
+    let url = sans_io::custom_category_url(client.base_url(), category);
+    response_body(client, url).await?.parse_single()
+}
+
+/// Gets `amount` images from a category by its name, with a supplied client.
+///
+/// # Errors
+/// [`NekosBestError::InvalidAmount`] if `amount` is not in the
+/// `1..=`[`crate::MAX_AMOUNT`] range (unless the client clamps it, see
+/// [`crate::client::ClientBuilder::clamp_amount`]). The number of files
+/// of a custom category isn't known, so it isn't checked. Otherwise, any
+/// errors that can happen, refer to [`NekosBestError`].
+#[cfg_attr(feature = "blocking", blocking)]
+pub async fn get_custom_with_client_amount(
+    client: &Client,
+    category: &CustomCategory,
+    amount: impl Into<u8>,
+) -> Result<NekosBestResponse, NekosBestError> {
+    let amount = client.amount_in_range(amount.into())?;
+    let url = sans_io::custom_category_amount_url(client.base_url(), category, amount);
+    response_body(client, url).await?.parse()
 }
 
 /// Gets a single image, with the default client.
@@ -93,7 +357,7 @@ pub async fn get_with_client_amount(
 /// Any errors that can happen, refer to [`NekosBestError`].
 #[cfg_attr(feature = "blocking", blocking)]
 pub async fn get(category: impl Into<Category>) -> Result<NekosBestResponseSingle, NekosBestError> {
-    get_with_client(&Client::new(ClientConfig::default()), category).await
+    get_with_client(&default_client(), category).await
 }
 
 /// Gets `amount` images, with the default client.
@@ -105,145 +369,129 @@ pub async fn get_amount(
     category: impl Into<Category>,
     amount: impl Into<u8>,
 ) -> Result<NekosBestResponse, NekosBestError> {
-    get_with_client_amount(&Client::new(ClientConfig::default()), category, amount).await
-}
-
-#[derive(Debug, thiserror::Error)]
-pub enum HeaderDeserializeUrlEncodedError {
-    #[error("Missing header")]
-    MissingHeader,
-    #[error("Not ASCII header")]
-    NotAsciiHeader(#[from] reqwest::header::ToStrError),
-    #[error("UTF8 error")]
-    Utf8(#[from] FromUtf8Error),
-}
-
-fn header_deserialize_urlencoded(
-    headers: &HeaderMap,
-    name: &str,
-) -> Result<String, HeaderDeserializeUrlEncodedError> {
-    let s = headers
-        .get(name)
-        .ok_or(HeaderDeserializeUrlEncodedError::MissingHeader)?
-        .to_str()?;
-
-    let s = urlencoding::decode(s)?.replace("+", " ");
-
-    Ok(s)
+    get_with_client_amount(&default_client(), category, amount).await
 }
 
 #[cfg_attr(feature = "blocking", blocking)]
 pub async fn get_with_client_image_details(
     client: &Client,
-    url: impl IntoUrl,
+    url: impl AsRef<str>,
 ) -> Result<ImageDetails, NekosBestError> {
-    let resp = client.client.get(url).send().await?.error_for_status()?;
-    let headers = resp.headers();
-
-    let details = ImageDetails {
-        artist_name: header_deserialize_urlencoded(headers, "artist_name")?,
-        artist_href: header_deserialize_urlencoded(headers, "artist_href")?.parse()?,
-        source_url: header_deserialize_urlencoded(headers, "source_url")?.parse()?,
-    };
-
-    Ok(details)
+    let resp = http_get(client, url.as_ref()).await?;
+    sans_io::parse_image_details(|name| resp.header_or_missing(name))
 }
 
 #[cfg_attr(feature = "blocking", blocking)]
-pub async fn get_image_details(url: impl IntoUrl) -> Result<ImageDetails, NekosBestError> {
-    get_with_client_image_details(&Client::new(ClientConfig::default()), url).await
+pub async fn get_image_details(url: impl AsRef<str>) -> Result<ImageDetails, NekosBestError> {
+    get_with_client_image_details(&default_client(), url).await
 }
 
 #[cfg_attr(feature = "blocking", blocking)]
 pub async fn get_with_client_gif_details(
     client: &Client,
-    url: impl IntoUrl,
+    url: impl AsRef<str>,
 ) -> Result<GifDetails, NekosBestError> {
-    let resp = client.client.get(url).send().await?.error_for_status()?;
-    let headers = resp.headers();
-
-    let details = GifDetails {
-        anime_name: header_deserialize_urlencoded(headers, "anime_name")?,
-    };
-
-    Ok(details)
+    let resp = http_get(client, url.as_ref()).await?;
+    sans_io::parse_gif_details(|name| resp.header_or_missing(name))
 }
 
 #[cfg_attr(feature = "blocking", blocking)]
-pub async fn get_gif_details(url: impl IntoUrl) -> Result<GifDetails, NekosBestError> {
-    get_with_client_gif_details(&Client::new(ClientConfig::default()), url).await
+pub async fn get_gif_details(url: impl AsRef<str>) -> Result<GifDetails, NekosBestError> {
+    get_with_client_gif_details(&default_client(), url).await
 }
 
-#[derive(serde::Serialize)]
-pub struct SearchQuery {
-    query: String,
-    #[serde(rename = "type")]
-    kind: SearchQueryKind,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    category: Option<Category>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    amount: Option<usize>,
-}
-
-impl SearchQuery {
-    pub fn new(query: impl Into<String>, kind: impl Into<SearchQueryKind>) -> Self {
-        Self {
-            query: query.into(),
-            kind: kind.into(),
-            category: None,
-            amount: None,
-        }
-    }
-
-    pub fn category(mut self, category: impl Into<Category>) -> Self {
-        self.category = Some(category.into());
-        self
-    }
-
-    pub fn amount(mut self, amount: usize) -> Self {
-        self.amount = Some(amount);
-        self
-    }
+/// Gets the file `file_name` of `category` (like `0001.gif`, or a uuid
+/// followed by the extension), with a supplied client, to get a known
+/// image again.
+///
+/// Without an extension, the one of the format of the category is added.
+///
+/// # Errors
+/// [`NekosBestError::Status`] with a `404` status if there is no such
+/// file, otherwise refer to [`NekosBestError`].
+#[cfg_attr(feature = "blocking", blocking)]
+pub async fn get_file_with_client(
+    client: &Client,
+    category: impl Into<Category>,
+    file_name: &str,
+) -> Result<NekosBestFile, NekosBestError> {
+    let category = category.into();
+    let format = file_name
+        .rsplit_once('.')
+        .map(|(_, extension)| ImageFormat::from_extension(extension));
+    let url = match format {
+        Some(_) => format!("{}/{file_name}", category.to_url(client.base_url())),
+        None => crate::NekosBestUrl::new(category, file_name).to_url(client.base_url()),
+    };
+    let format = format.flatten().unwrap_or(category.format());
+
+    let resp = http_get(client, &url).await?;
+    let details = match format {
+        ImageFormat::Gif => sans_io::parse_gif_details(|name| resp.header_or_missing(name))
+            .ok()
+            .map(Details::Gif),
+        ImageFormat::Png => sans_io::parse_image_details(|name| resp.header_or_missing(name))
+            .ok()
+            .map(Details::Image),
+    };
 
-    fn apply_to(&self, r: ReqBuilder) -> ReqBuilder {
-        r.query(self)
-    }
+    Ok(NekosBestFile {
+        url,
+        bytes: resp.body,
+        details,
+    })
 }
 
-#[derive(Copy, Clone)]
-#[repr(u32)]
-pub enum SearchQueryKind {
-    Image = 1,
-    Gif = 2,
+/// Gets the file `file_name` of `category`, with the default client.
+///
+/// # Errors
+/// Same as [`get_file_with_client`].
+#[cfg_attr(feature = "blocking", blocking)]
+pub async fn get_file(
+    category: impl Into<Category>,
+    file_name: &str,
+) -> Result<NekosBestFile, NekosBestError> {
+    get_file_with_client(&default_client(), category, file_name).await
 }
 
-impl serde::Serialize for SearchQueryKind {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        S: Serializer,
-    {
-        serializer.serialize_u32(*self as u32)
+impl NekosBestResponseSingle {
+    /// Fetches the details of the image again, from the headers sent
+    /// along with it, and replaces [`NekosBestResponseSingle::details`]
+    /// with them.
+    ///
+    /// Useful for responses built from a stored url, where the details
+    /// had to be filled in with placeholders.
+    #[cfg_attr(feature = "blocking", blocking)]
+    pub async fn fetch_details(&mut self, client: &Client) -> Result<&Details, NekosBestError> {
+        self.details = match self.details {
+            Details::Image(_) => {
+                Details::Image(get_with_client_image_details(client, &self.url).await?)
+            }
+            Details::Gif(_) => Details::Gif(get_with_client_gif_details(client, &self.url).await?),
+        };
+
+        Ok(&self.details)
     }
 }
 
 #[cfg_attr(feature = "blocking", blocking)]
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", skip(client))
+)]
 pub async fn search_with_client(
     client: &Client,
     query: SearchQuery,
 ) -> Result<NekosBestResponse, NekosBestError> {
-    let req = client.client.get(format!("{BASE_URL}/search"));
+    let url = sans_io::search_url(client.base_url(), &query);
 
-    #[cfg(not(feature = "blocking"))]
     client.handle_search_ratelimit().await?;
 
-    let req = query.apply_to(req);
-
-    let res = req.send().await?;
+    let res = client.send_http(HttpRequest::get(url.as_str())).await?;
 
-    #[cfg(not(feature = "blocking"))]
-    client.update_search_ratelimit_data(res.headers()).await;
+    client.update_search_ratelimit_data(&res);
 
-    Ok(parse_from_response(res).await?)
+    ResponseBody::new(url, check_status(res)?).parse()
 }
 
 #[deprecated(
@@ -252,5 +500,5 @@ pub async fn search_with_client(
 )]
 #[cfg_attr(feature = "blocking", blocking)]
 pub async fn search(query: SearchQuery) -> Result<NekosBestResponse, NekosBestError> {
-    search_with_client(&Client::new(ClientConfig::default()), query).await
+    search_with_client(&default_client(), query).await
 }
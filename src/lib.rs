@@ -1,25 +1,99 @@
 //! API wrapper for [nekos.best](https://nekos.best/)
 
+#[cfg(feature = "reqwest")]
 pub extern crate reqwest;
 
 #[cfg(feature = "metrics")]
 pub mod metrics;
 
+#[cfg(feature = "reqwest")]
+pub mod api;
+pub mod backend;
+pub mod cache;
 pub mod category;
+#[cfg(feature = "reqwest")]
+mod circuit_breaker;
+#[cfg(feature = "reqwest")]
 pub mod client;
+#[cfg(all(feature = "coalesce", not(feature = "blocking")))]
+mod coalesce;
+#[cfg(feature = "reqwest")]
+pub mod daily;
 pub mod details;
 #[cfg(feature = "download")]
 pub mod download;
+#[cfg(all(feature = "download", not(feature = "blocking")))]
+pub mod download_manager;
+pub mod embed;
+pub mod endpoints;
+#[cfg(feature = "feed")]
+pub mod feed;
+pub mod gallery;
+#[cfg(feature = "hash")]
+pub mod hash;
+pub mod image_url;
+#[cfg(feature = "reqwest")]
+pub mod middleware;
+#[cfg(all(feature = "download", not(feature = "blocking")))]
+pub mod mirror;
+#[cfg(feature = "otel")]
+mod otel;
+#[cfg(feature = "reqwest")]
+pub mod pack;
+#[cfg(feature = "poise")]
+pub mod poise_impl;
+#[cfg(feature = "reqwest")]
+pub mod prefetch;
+#[cfg(feature = "reqwest")]
+mod ratelimit;
+#[cfg(feature = "reqwest")]
+pub mod request;
 pub mod response;
+mod rt;
+pub mod sans_io;
+#[cfg(feature = "scheduler")]
+pub mod scheduler;
+mod search;
+#[cfg(feature = "reqwest")]
+pub mod seen;
+pub mod selection;
+#[cfg(all(feature = "tower", not(feature = "blocking")))]
+pub mod service;
+pub mod storage;
+#[cfg(all(feature = "stream", not(feature = "blocking")))]
+pub mod stream;
+#[cfg(feature = "metrics-facade")]
+mod telemetry;
+#[cfg(all(feature = "test-util", not(target_arch = "wasm32")))]
+pub mod test_util;
+#[cfg(feature = "twilight")]
+pub mod twilight;
+#[cfg(feature = "ureq")]
+pub mod ureq_client;
+#[cfg(all(feature = "test-util", not(target_arch = "wasm32")))]
+pub mod vcr;
+#[cfg(feature = "reqwest")]
+pub mod webhook;
 
 pub use category::Category;
+#[cfg(feature = "reqwest")]
+pub use circuit_breaker::CircuitState;
+pub use image_url::NekosBestUrl;
+#[cfg(feature = "reqwest")]
+pub use pack::CategoryPack;
+#[cfg(feature = "reqwest")]
+pub use request::{request, ImageRequest};
+pub use selection::Selection;
 use url::ParseError;
 
-pub use response::{NekosBestResponse, NekosBestResponseSingle};
+pub use response::{NekosBestFile, NekosBestResponse, NekosBestResponseSingle};
+pub use sans_io::HeaderDeserializeUrlEncodedError;
+pub use search::{SearchQuery, SearchQueryKind};
 
 #[derive(thiserror::Error, Debug)]
 pub enum NekosBestError {
-    #[error("reqwest error")]
+    #[cfg(feature = "reqwest")]
+    #[error("reqwest error: {0}")]
     ReqwestError(#[from] reqwest::Error),
 
     #[error("not found")]
@@ -28,28 +102,193 @@ pub enum NekosBestError {
     #[error("decoding")]
     Decoding(#[from] serde_json::Error),
 
+    /// The body of a response couldn't be decoded.
+    #[error("decoding the response of {url} (status {status}): {source}, body: {body:?}")]
+    DecodingResponse {
+        url: String,
+        status: u16,
+        /// The start of the body, see [`sans_io::body_snippet`].
+        body: String,
+        #[source]
+        source: serde_json::Error,
+    },
+
     #[error("decoding header values")]
     DecodingHeader(#[from] HeaderDeserializeUrlEncodedError),
 
     #[error("error parsing url")]
     UrlParseError(#[from] ParseError),
 
+    /// The url of an image isn't valid, see
+    /// [`NekosBestResponseSingle::parsed_url`].
+    #[cfg(feature = "url")]
+    #[error("invalid url {url:?}")]
+    InvalidUrl {
+        url: String,
+        #[source]
+        source: ParseError,
+    },
+
     #[cfg(feature = "download")]
     #[error("error decoding downloaded image")]
     ImageDecodeError(#[from] image::ImageError),
 
+    #[cfg(feature = "download")]
+    #[error("error reading gif metadata")]
+    GifDecodeError(#[from] gif::DecodingError),
+
+    /// A downloaded gif has no frames, so there is no image to encode, see
+    /// [`download::DownloadResult::encode`].
+    #[cfg(feature = "download")]
+    #[error("the downloaded gif has no frames")]
+    EmptyImage,
+
+    #[cfg(feature = "scheduler")]
+    #[error("invalid cron expression")]
+    InvalidCronExpression(#[from] cron::error::Error),
+
+    #[error("unexpected status code {0}")]
+    Status(u16),
+
+    #[error("http backend error")]
+    Backend(#[source] Box<dyn std::error::Error + Send + Sync>),
+
+    /// A middleware of the [`client::ClientBuilder::reqwest_middleware`]
+    /// client failed.
+    #[cfg(all(feature = "reqwest-middleware", not(feature = "blocking")))]
+    #[error("reqwest middleware error")]
+    Middleware(#[source] Box<dyn std::error::Error + Send + Sync>),
+
+    #[error("storage error")]
+    Storage(#[source] Box<dyn std::error::Error + Send + Sync>),
+
+    /// The circuit breaker of the client is open, after too many
+    /// consecutive failures, see
+    /// [`client::ClientBuilder::circuit_breaker`].
+    #[error("circuit breaker open, retry after {retry_after:?}")]
+    CircuitOpen { retry_after: std::time::Duration },
+
+    /// A request made with [`client::ClientBuilder::coalesce_requests`]
+    /// failed, whether it was shared by several callers or not.
+    #[cfg(all(feature = "coalesce", not(feature = "blocking")))]
+    #[error("coalesced request failed")]
+    Coalesced(#[source] std::sync::Arc<NekosBestError>),
+
+    /// A replayed [`vcr::Cassette`] has no response (left) for a request.
+    #[cfg(all(feature = "test-util", not(target_arch = "wasm32")))]
+    #[error("no recorded response for {method} {url}")]
+    NotRecorded { method: String, url: String },
+
     #[error("missing content type")]
     MissingContentType,
 
     #[error("io error")]
     IO(#[from] std::io::Error),
 
+    /// The server (or, for searches, the client-side tracking of the
+    /// search rate limit) refused the request with `429 Too Many Requests`.
     #[error("rate limited")]
-    RateLimited,
+    RateLimited {
+        /// How long to wait before trying again, if known.
+        retry_after: Option<std::time::Duration>,
+    },
+
+    #[error("no category to pick from the category pack")]
+    EmptyCategoryPack,
+
+    #[error("the endpoint does not advertise a numeric file range")]
+    NoFileRange,
+
+    #[error("invalid amount {requested}, expected an amount in {min}..={max}")]
+    InvalidAmount { requested: u64, min: u64, max: u64 },
+
+    /// The rate of [`client::ClientBuilder::rate_limit`] isn't a positive
+    /// number, or its burst is 0.
+    #[error(
+        "invalid rate limit of {requests_per_second} requests per second, with bursts of {burst}"
+    )]
+    InvalidRateLimit {
+        requests_per_second: f64,
+        burst: u32,
+    },
+}
+
+impl NekosBestError {
+    /// The status code of the response the error is about, if any.
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            #[cfg(feature = "reqwest")]
+            NekosBestError::ReqwestError(e) => e.status().map(|status| status.as_u16()),
+            NekosBestError::Status(status) => Some(*status),
+            NekosBestError::DecodingResponse { status, .. } => Some(*status),
+            #[cfg(all(feature = "coalesce", not(feature = "blocking")))]
+            NekosBestError::Coalesced(e) => e.status(),
+            _ => None,
+        }
+    }
+
+    /// The url of the request the error is about, if known.
+    pub fn url(&self) -> Option<&str> {
+        match self {
+            #[cfg(feature = "reqwest")]
+            NekosBestError::ReqwestError(e) => e.url().map(url::Url::as_str),
+            NekosBestError::DecodingResponse { url, .. } => Some(url),
+            #[cfg(all(feature = "test-util", not(target_arch = "wasm32")))]
+            NekosBestError::NotRecorded { url, .. } => Some(url),
+            #[cfg(all(feature = "coalesce", not(feature = "blocking")))]
+            NekosBestError::Coalesced(e) => e.url(),
+            _ => None,
+        }
+    }
+
+    /// Turns [`NekosBestError::Decoding`] into
+    /// [`NekosBestError::DecodingResponse`], for the response from `url`.
+    pub(crate) fn with_response(self, url: &str, status: u16, body: &[u8]) -> Self {
+        match self {
+            NekosBestError::Decoding(source) => NekosBestError::DecodingResponse {
+                url: url.to_owned(),
+                status,
+                body: sans_io::body_snippet(body),
+                source,
+            },
+            e => e,
+        }
+    }
+
+    /// Whether a request that failed with this error is worth retrying:
+    /// network errors (timeouts, failures to connect or to send the
+    /// request), server errors, rate limits and an open circuit breaker
+    /// are, anything else (like a `404`, a redirect loop, or a body that
+    /// can't be decoded) will fail again.
+    ///
+    /// For rate limits and the circuit breaker, wait for their
+    /// `retry_after` before retrying.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            NekosBestError::RateLimited { .. } => true,
+            NekosBestError::CircuitOpen { .. } => true,
+            #[cfg(all(feature = "coalesce", not(feature = "blocking")))]
+            NekosBestError::Coalesced(e) => e.is_retryable(),
+            NekosBestError::Status(status) => *status == 429 || *status >= 500,
+            #[cfg(feature = "reqwest")]
+            NekosBestError::ReqwestError(e) => match e.status() {
+                Some(status) => {
+                    status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+                }
+                None => crate::client::is_transient(e),
+            },
+            _ => false,
+        }
+    }
 }
 
 pub const API_VERSION: usize = 2;
 pub const BASE_URL: &str = "https://nekos.best/api/v2";
+/// The `User-Agent` the requests of the crate are sent with by default, as
+/// nekos.best asks its consumers to identify themselves.
+pub const USER_AGENT: &str = concat!("nb-rs/", env!("CARGO_PKG_VERSION"));
+/// The most images the API returns for a single request.
+pub const MAX_AMOUNT: u8 = 20;
 
 #[cfg(feature = "strong-types")]
 pub mod strong_types;
@@ -57,15 +296,16 @@ pub mod strong_types;
 #[cfg(feature = "strong-types")]
 pub use strong_types::*;
 
+#[cfg(feature = "reqwest")]
 #[path = "impl.rs"]
 mod implementation;
 
+#[cfg(feature = "reqwest")]
 pub use implementation::*;
 
-#[cfg(test)]
+#[cfg(all(test, feature = "reqwest"))]
 mod test {
     use crate::client::{Client, ClientConfig};
-    use std::collections::HashMap;
 
     use super::*;
 
@@ -81,48 +321,164 @@ mod test {
     }
 
     #[tokio::test]
-    async fn all_endpoints_work() {
-        let client = Client::new(ClientConfig::default());
-        for cat in Category::ALL_VARIANTS {
-            try_endpoint(&client, *cat).await.unwrap(); // test will fail if any of them error
+    async fn retryable() {
+        assert!(NekosBestError::Status(502).is_retryable());
+        assert!(NekosBestError::CircuitOpen {
+            retry_after: std::time::Duration::from_secs(1)
         }
-    }
+        .is_retryable());
+        assert!(!NekosBestError::Status(404).is_retryable());
+        assert!(!sans_io::parse_response(b"{").unwrap_err().is_retryable());
+        assert!(!NekosBestError::InvalidAmount {
+            requested: 0,
+            min: 1,
+            max: 20
+        }
+        .is_retryable());
 
-    #[tokio::test]
-    async fn no_new_endpoints() {
-        let client = reqwest::Client::new();
+        // nothing listens on the discard port
+        let connect = reqwest::get("http://127.0.0.1:9").await.unwrap_err();
+        assert!(NekosBestError::from(connect).is_retryable());
+        let builder = reqwest::get("not a url").await.unwrap_err();
+        assert!(!NekosBestError::from(builder).is_retryable());
+
+        #[cfg(feature = "test-util")]
+        {
+            use crate::test_util::{Fault, MockServer};
 
-        async fn get_endpoints(client: &reqwest::Client) -> HashMap<String, EndpointDesc> {
-            client
-                .get(format!("{BASE_URL}/endpoints"))
+            let server = MockServer::start().unwrap();
+            let url = format!("{}/neko", server.base_url());
+            server.fail_next(1, Fault::Delay(std::time::Duration::from_secs(2)));
+            let timeout = reqwest::Client::new()
+                .get(&url)
+                .timeout(std::time::Duration::from_millis(200))
                 .send()
                 .await
-                .unwrap()
-                .json()
+                .unwrap_err();
+            assert!(NekosBestError::from(timeout).is_retryable());
+
+            server.fail_next(1, Fault::MalformedJson);
+            let decode = reqwest::get(&url)
                 .await
                 .unwrap()
+                .json::<serde_json::Value>()
+                .await
+                .unwrap_err();
+            assert!(!NekosBestError::from(decode).is_retryable());
         }
+    }
 
-        #[derive(serde::Deserialize)]
-        #[allow(dead_code)]
-        struct EndpointDesc {
-            min: String,
-            max: String,
-            format: String,
+    #[tokio::test]
+    async fn all_endpoints_work() {
+        let client = Client::new(ClientConfig::default());
+        for cat in Category::iter() {
+            try_endpoint(&client, cat).await.unwrap(); // test will fail if any of them error
         }
+    }
+
+    #[tokio::test]
+    async fn no_new_endpoints() {
+        let client = Client::new(ClientConfig::default());
 
-        let endpoints = get_endpoints(&client).await;
+        let endpoints = endpoints::get_endpoints_with_client(&client).await.unwrap();
         let list = endpoints.keys();
 
         let mut unknown_endpoints = vec![];
         for item in list {
-            if item.as_str().parse::<Category>().is_err() {
-                unknown_endpoints.push(format!("{BASE_URL}/{item}"));
+            match Category::from_url_name(item) {
+                Some(category) => assert_eq!(
+                    endpoints[item].image_format(),
+                    Some(category.format()),
+                    "the format of {category} changed"
+                ),
+                None => unknown_endpoints.push(format!("{BASE_URL}/{item}")),
             }
         }
 
         if !unknown_endpoints.is_empty() {
             panic!("Looks like there are new endpoints, please add them: {unknown_endpoints:?}");
         }
+
+        // files are only added, so the snapshot must stay within the
+        // live ranges
+        for embedded in endpoints::EMBEDDED_ENDPOINTS {
+            let Some((min, max, _)) = embedded.desc().file_range() else {
+                continue;
+            };
+            let live = &endpoints[embedded.name()];
+            let (live_min, live_max, _) = live
+                .file_range()
+                .unwrap_or_else(|| panic!("{} lost its file range", embedded.name()));
+            assert!(
+                live_min <= min && max <= live_max,
+                "the snapshot of {} is outside of {live:?}",
+                embedded.name()
+            );
+        }
+    }
+
+    #[cfg(feature = "test-util")]
+    #[tokio::test]
+    async fn exact_amount_with_cache() {
+        let server = test_util::MockServer::start().unwrap();
+        let client = Client::builder()
+            .base_url(server.base_url())
+            .cache(std::time::Duration::from_secs(60), 16)
+            .build()
+            .unwrap();
+
+        let images = get_with_client_exact_amount(&client, Category::Pat, 45)
+            .await
+            .unwrap();
+        let urls: std::collections::HashSet<_> = images.iter().map(|image| &image.url).collect();
+        assert_eq!(urls.len(), 45);
+        assert_eq!(server.requests(), 3);
+    }
+
+    #[cfg(feature = "test-util")]
+    #[tokio::test]
+    async fn unique_amount_with_cache() {
+        let server = test_util::MockServer::start().unwrap();
+        let client = Client::builder()
+            .base_url(server.base_url())
+            .cache(std::time::Duration::from_secs(60), 16)
+            .build()
+            .unwrap();
+
+        get_with_client_amount(&client, Category::Pat, 5)
+            .await
+            .unwrap();
+        let images = get_with_client_unique_amount(&client, Category::Pat, 5, 1)
+            .await
+            .unwrap();
+        assert_eq!(images.len(), 5);
+        assert_eq!(server.requests(), 2);
+    }
+
+    #[cfg(feature = "test-util")]
+    #[tokio::test]
+    async fn file() {
+        let server = test_util::MockServer::start().unwrap();
+        let client = server.client();
+
+        let file = get_file_with_client(&client, Category::Pat, "0001.gif")
+            .await
+            .unwrap();
+        assert_eq!(file.url, format!("{}/pat/0001.gif", server.base_url()));
+        assert!(file.bytes.starts_with(b"GIF89a"));
+        assert_eq!(
+            file.details.unwrap().as_gif().unwrap().anime_name,
+            test_util::ANIME_NAME
+        );
+
+        let file = get_file_with_client(&client, Category::Neko, "0001")
+            .await
+            .unwrap();
+        assert!(file.url.ends_with("/neko/0001.png"));
+        assert!(file.details.unwrap().is_image());
+
+        assert!(get_file_with_client(&client, Category::Neko, "0001.gif")
+            .await
+            .is_err());
     }
 }
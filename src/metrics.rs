@@ -1,6 +1,10 @@
 use std::str::FromStr;
 use std::{collections::HashMap, convert::TryFrom};
 
+#[cfg(feature = "blocking")]
+use nb_blocking_util::blocking;
+
+use crate::client::default_client;
 use crate::{category::NoSuchVariant, Category, NekosBestError, BASE_URL};
 
 #[derive(serde::Deserialize)]
@@ -80,7 +84,11 @@ pub struct Metrics {
     pub reset_in: std::time::Duration,
 }
 
-pub async fn get_metrics_with_client(client: &reqwest::Client) -> Result<Metrics, NekosBestError> {
+/// Gets the metrics from [`BASE_URL`] with a plain [`reqwest::Client`],
+/// see [`get_metrics_with_client`] for a [`crate::client::Client`].
+pub async fn get_metrics_with_reqwest_client(
+    client: &reqwest::Client,
+) -> Result<Metrics, NekosBestError> {
     let resp = client.get(format!("{BASE_URL}/metrics")).send().await?;
 
     let metrics = resp.json::<Metrics>().await?;
@@ -88,6 +96,23 @@ pub async fn get_metrics_with_client(client: &reqwest::Client) -> Result<Metrics
     Ok(metrics)
 }
 
+/// Gets the metrics with a [`crate::client::Client`], from its
+/// [`base_url`](crate::client::Client::base_url) instead of [`BASE_URL`],
+/// and going through its rate limiting and retries.
+#[cfg_attr(feature = "blocking", blocking)]
+pub async fn get_metrics_with_client(
+    client: &crate::client::Client,
+) -> Result<Metrics, NekosBestError> {
+    let url = format!("{}/metrics", client.base_url());
+    let resp = crate::implementation::http_get(client, &url).await?;
+
+    crate::sans_io::parse_body(&resp.body)
+        .map_err(|e| e.with_response(&url, resp.status, &resp.body))
+}
+
+/// Gets the metrics with the default client, see
+/// [`get_metrics_with_client`].
+#[cfg_attr(feature = "blocking", blocking)]
 pub async fn get_metrics() -> Result<Metrics, NekosBestError> {
-    get_metrics_with_client(&reqwest::Client::new()).await
+    get_metrics_with_client(&default_client()).await
 }
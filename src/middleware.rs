@@ -0,0 +1,152 @@
+//! Hooks into the requests sent by the client, see [`Middleware`].
+
+use crate::NekosBestError;
+
+/// The requests passed to [`Middleware::on_request`]: a
+/// `reqwest::RequestBuilder`, or a `reqwest::blocking::RequestBuilder`
+/// with the `blocking` feature.
+pub type RequestBuilder = crate::client::ReqwestRequestBuilder;
+
+/// The responses passed to [`Middleware::on_response`]: a
+/// `reqwest::Response`, or a `reqwest::blocking::Response` with the
+/// `blocking` feature.
+pub type Response = crate::client::ReqwestResponse;
+
+/// Hooks called by the client around the requests it sends, registered
+/// with [`crate::client::ClientBuilder::middleware`], e.g. for logging,
+/// adding headers, or metrics.
+///
+/// The hooks are called for every request sent to the API, including
+/// retries, but not for the responses served from a cache or replayed
+/// from a cassette (with the `test-util` feature). With several
+/// middleware, they are called in the order they were registered.
+///
+/// ```
+/// use nekosbest::middleware::{Middleware, RequestBuilder};
+///
+/// struct ApiKey(String);
+///
+/// impl Middleware for ApiKey {
+///     fn on_request(&self, req: RequestBuilder) -> RequestBuilder {
+///         req.header("x-api-key", &self.0)
+///     }
+/// }
+///
+/// let client = nekosbest::client::Client::builder()
+///     .middleware(ApiKey("secret".to_owned()))
+///     .build()
+///     .unwrap();
+/// ```
+pub trait Middleware: Send + Sync {
+    /// Called before sending a request, returning the request to send.
+    fn on_request(&self, req: RequestBuilder) -> RequestBuilder {
+        req
+    }
+
+    /// Called with every response received, whatever its status.
+    fn on_response(&self, _response: &Response) {}
+
+    /// Called when sending a request failed, after the retries.
+    fn on_error(&self, _error: &NekosBestError) {}
+}
+
+#[cfg(all(test, feature = "test-util", not(feature = "blocking")))]
+mod test {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+
+    use crate::client::Client;
+    use crate::Category;
+
+    #[derive(Default)]
+    struct Counts {
+        requests: AtomicU32,
+        responses: AtomicU32,
+        errors: AtomicU32,
+    }
+
+    impl Middleware for Arc<Counts> {
+        fn on_request(&self, req: RequestBuilder) -> RequestBuilder {
+            self.requests.fetch_add(1, Ordering::SeqCst);
+            req
+        }
+
+        fn on_response(&self, _response: &Response) {
+            self.responses.fetch_add(1, Ordering::SeqCst);
+        }
+
+        fn on_error(&self, _error: &NekosBestError) {
+            self.errors.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[tokio::test]
+    async fn hooks() {
+        let server = crate::test_util::MockServer::start().unwrap();
+        let counts = Arc::new(Counts::default());
+        let client = Client::builder()
+            .base_url(server.base_url())
+            .middleware(Arc::clone(&counts))
+            .build()
+            .unwrap();
+        crate::get_with_client(&client, Category::Hug)
+            .await
+            .unwrap();
+
+        let unreachable = Client::builder()
+            .base_url("http://127.0.0.1:1")
+            .middleware(Arc::clone(&counts))
+            .build()
+            .unwrap();
+        crate::get_with_client(&unreachable, Category::Hug)
+            .await
+            .unwrap_err();
+
+        assert_eq!(counts.requests.load(Ordering::SeqCst), 2);
+        assert_eq!(counts.responses.load(Ordering::SeqCst), 1);
+        assert_eq!(counts.errors.load(Ordering::SeqCst), 1);
+    }
+
+    /// Adds a header, recording how many values of it the requests already
+    /// had.
+    #[derive(Default)]
+    struct Header(std::sync::Mutex<Vec<usize>>);
+
+    impl Middleware for Arc<Header> {
+        fn on_request(&self, req: RequestBuilder) -> RequestBuilder {
+            let request = req.try_clone().unwrap().build().unwrap();
+            let values = request.headers().get_all("x-test").iter().count();
+            self.0.lock().unwrap().push(values);
+            req.header("x-test", "1")
+        }
+    }
+
+    #[tokio::test]
+    async fn retries() {
+        let server = crate::test_util::MockServer::start().unwrap();
+        server.fail_next(
+            1,
+            crate::test_util::Fault::RateLimited {
+                retry_after: Some(0),
+            },
+        );
+        let (counts, header) = (Arc::new(Counts::default()), Arc::new(Header::default()));
+        let client = Client::builder()
+            .base_url(server.base_url())
+            .retry_rate_limited(1)
+            .middleware(Arc::clone(&counts))
+            .middleware(Arc::clone(&header))
+            .build()
+            .unwrap();
+        crate::get_with_client(&client, Category::Hug)
+            .await
+            .unwrap();
+
+        assert_eq!(server.requests(), 2);
+        assert_eq!(counts.requests.load(Ordering::SeqCst), 2);
+        assert_eq!(counts.responses.load(Ordering::SeqCst), 2);
+        assert_eq!(counts.errors.load(Ordering::SeqCst), 0);
+        assert_eq!(*header.0.lock().unwrap(), [0, 0]);
+    }
+}
@@ -0,0 +1,477 @@
+//! Mirroring whole categories to local directories.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+
+use crate::client::{default_client, Client};
+use crate::download::{download_bytes_from_url_with_client, DownloadOptions};
+use crate::download_manager::{DownloadManager, DownloadReport};
+use crate::endpoints::endpoint_desc;
+use crate::{sans_io, Category, NekosBestError};
+
+/// Options for [`mirror_category`].
+#[derive(Debug, Clone)]
+pub struct MirrorOptions {
+    concurrency: usize,
+    max_retries: u32,
+    download: DownloadOptions,
+    check_sizes: bool,
+    prune: bool,
+}
+
+impl Default for MirrorOptions {
+    fn default() -> Self {
+        Self {
+            concurrency: 4,
+            max_retries: 3,
+            download: DownloadOptions::default(),
+            check_sizes: false,
+            prune: false,
+        }
+    }
+}
+
+impl MirrorOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How many files are downloaded at the same time. Defaults to 4.
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency;
+        self
+    }
+
+    /// How many times a file is retried, see
+    /// [`DownloadManager::max_retries`]. Defaults to 3.
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// The options every file is downloaded with.
+    pub fn download_options(mut self, options: DownloadOptions) -> Self {
+        self.download = options;
+        self
+    }
+
+    /// Whether to compare the sizes of the files already in the mirror
+    /// with the ones on the server, with a `HEAD` request each, and
+    /// download them again if they differ. Defaults to `false`, where any
+    /// non-empty file already in the mirror is kept as is.
+    ///
+    /// Along with [`DownloadOptions::resume`], this finishes the files left
+    /// incomplete by an interrupted run.
+    pub fn check_sizes(mut self, check_sizes: bool) -> Self {
+        self.check_sizes = check_sizes;
+        self
+    }
+
+    /// Whether to delete the files in the mirror that are no longer in
+    /// the file range of the category. Defaults to `false`, where they are
+    /// only listed in [`MirrorReport::removed`].
+    pub fn prune(mut self, prune: bool) -> Self {
+        self.prune = prune;
+        self
+    }
+}
+
+/// The outcome of mirroring a category.
+#[derive(Debug, Default)]
+pub struct MirrorReport {
+    /// The files that were downloaded.
+    pub downloaded: Vec<PathBuf>,
+    /// The files that were already up to date.
+    pub skipped: Vec<PathBuf>,
+    /// The files in the mirror that are no longer in the category, and
+    /// were deleted if [`MirrorOptions::prune`] was set.
+    pub removed: Vec<PathBuf>,
+    /// The files that couldn't be downloaded, even after retrying.
+    pub failed: Vec<DownloadReport>,
+}
+
+/// The name of the manifest written to the mirror directory by every run.
+pub const MANIFEST_FILE_NAME: &str = "manifest.json";
+
+/// What a run of [`mirror_category`] did, as written to
+/// [`MANIFEST_FILE_NAME`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MirrorManifest {
+    pub category: String,
+    /// When the run finished, as a unix timestamp.
+    pub finished_at: u64,
+    pub added: Vec<String>,
+    pub skipped: Vec<String>,
+    pub removed: Vec<String>,
+    pub failed: Vec<String>,
+}
+
+impl MirrorManifest {
+    fn new(category: Category, report: &MirrorReport) -> Self {
+        fn names<'a>(paths: impl IntoIterator<Item = &'a PathBuf>) -> Vec<String> {
+            paths
+                .into_iter()
+                .filter_map(|path| Some(path.file_name()?.to_string_lossy().into_owned()))
+                .collect()
+        }
+
+        Self {
+            category: category.to_url_name().to_owned(),
+            finished_at: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+            added: names(&report.downloaded),
+            skipped: names(&report.skipped),
+            removed: names(&report.removed),
+            failed: names(report.failed.iter().map(|f| &f.job.path)),
+        }
+    }
+
+    /// Reads the manifest of the last run from the mirror in `dir`.
+    pub fn load(dir: impl AsRef<Path>) -> Result<Self, NekosBestError> {
+        let file = std::fs::read(dir.as_ref().join(MANIFEST_FILE_NAME))?;
+        Ok(serde_json::from_slice(&file)?)
+    }
+}
+
+/// Downloads every file of `category` into `dir`, with a supplied client.
+///
+/// The files are listed from the file range the endpoint advertises in
+/// `/endpoints`, and keep their names from the API (e.g. `0001.png`), so
+/// mirroring into the same directory again only downloads the files that
+/// are new, or changed if [`MirrorOptions::check_sizes`] is set. Every run
+/// writes a [`MirrorManifest`] to `dir`.
+/// Uses the metadata loaded by [`Client::refresh_endpoints`] if there is
+/// any, otherwise fetches it, falling back on the snapshot in
+/// [`EMBEDDED_ENDPOINTS`](crate::endpoints::EMBEDDED_ENDPOINTS) if
+/// `/endpoints` fails.
+///
+/// # Errors
+/// [`NekosBestError::NoFileRange`] if the endpoint doesn't advertise a
+/// numeric file range, or an error getting the endpoints or creating
+/// `dir`. Errors downloading single files are in the
+/// [`MirrorReport::failed`] instead.
+pub async fn mirror_category_with_client(
+    client: &Client,
+    category: impl Into<Category>,
+    dir: impl AsRef<Path>,
+    options: &MirrorOptions,
+) -> Result<MirrorReport, NekosBestError> {
+    let category = category.into();
+    let dir = dir.as_ref();
+    let (extension, files) = mirror_files(client, category, dir).await?;
+
+    std::fs::create_dir_all(dir)?;
+
+    let mut report = MirrorReport {
+        removed: stale_files(dir, &extension, &files)?,
+        ..MirrorReport::default()
+    };
+    if options.prune {
+        for path in &report.removed {
+            std::fs::remove_file(path)?;
+        }
+    }
+
+    let up_to_date: Vec<bool> = futures::stream::iter(&files)
+        .map(|(url, path)| is_up_to_date(client, url, path, options.check_sizes))
+        .buffered(options.concurrency.max(1))
+        .collect()
+        .await;
+
+    let mut manager = DownloadManager::new(client.clone())
+        .concurrency(options.concurrency)
+        .max_retries(options.max_retries)
+        .options(options.download.clone());
+    for ((url, path), up_to_date) in files.into_iter().zip(up_to_date) {
+        if up_to_date {
+            report.skipped.push(path);
+        } else {
+            manager.push(url, path);
+        }
+    }
+
+    for download in manager.run().await {
+        match download.result {
+            Ok(path) => report.downloaded.push(path),
+            Err(_) => report.failed.push(download),
+        }
+    }
+
+    let manifest = MirrorManifest::new(category, &report);
+    std::fs::write(
+        dir.join(MANIFEST_FILE_NAME),
+        serde_json::to_vec_pretty(&manifest)?,
+    )?;
+
+    Ok(report)
+}
+
+/// The extension of the files of `category`, and the url and path in
+/// `dir` of each of them.
+async fn mirror_files(
+    client: &Client,
+    category: Category,
+    dir: &Path,
+) -> Result<(String, Vec<(String, PathBuf)>), NekosBestError> {
+    let desc = endpoint_desc(client, category).await?;
+    let count = desc.file_count().ok_or(NekosBestError::NoFileRange)?;
+
+    let category_url = sans_io::category_url(client.base_url(), category);
+    let files = (0..count)
+        .filter_map(|n| desc.file_name(n))
+        .map(|name| (format!("{category_url}/{name}"), dir.join(name)))
+        .collect();
+
+    Ok((desc.format.clone(), files))
+}
+
+/// Whether the file already in the mirror at `path` can be kept.
+async fn is_up_to_date(client: &Client, url: &str, path: &Path, check_size: bool) -> bool {
+    let len = match std::fs::metadata(path) {
+        Ok(metadata) if metadata.len() > 0 => metadata.len(),
+        _ => return false,
+    };
+    if !check_size {
+        return true;
+    }
+
+    // keep the file if its size is unknown, the download would most
+    // likely fail as well
+    remote_size(client, url)
+        .await
+        .ok()
+        .flatten()
+        .is_none_or(|expected| expected == len)
+}
+
+/// The size of the file at `url`, from its `Content-Length`.
+async fn remote_size(client: &Client, url: &str) -> Result<Option<u64>, NekosBestError> {
+    let resp = client
+        .send(client.client.head(url))
+        .await?
+        .error_for_status()?;
+    // reqwest's `content_length` is the size of the (empty) body for `HEAD`
+    Ok(resp
+        .headers()
+        .get(reqwest::header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok()?.parse().ok()))
+}
+
+/// Options for [`verify_mirror`].
+#[derive(Debug, Clone)]
+pub struct VerifyOptions {
+    concurrency: usize,
+    refetch: bool,
+}
+
+impl Default for VerifyOptions {
+    fn default() -> Self {
+        Self {
+            concurrency: 4,
+            refetch: false,
+        }
+    }
+}
+
+impl VerifyOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How many files are checked at the same time. Defaults to 4.
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency;
+        self
+    }
+
+    /// Whether to download every file again and compare the contents,
+    /// instead of only the sizes. Defaults to `false`.
+    pub fn refetch(mut self, refetch: bool) -> Self {
+        self.refetch = refetch;
+        self
+    }
+}
+
+/// The outcome of [`verify_mirror`].
+#[derive(Debug, Default)]
+pub struct VerifyReport {
+    /// The files that match the ones on the server.
+    pub verified: Vec<PathBuf>,
+    /// The files of the category that aren't in the mirror.
+    pub missing: Vec<PathBuf>,
+    /// The files in the mirror that are no longer in the category.
+    pub extra: Vec<PathBuf>,
+    /// The files that differ from the ones on the server.
+    pub corrupted: Vec<PathBuf>,
+    /// The files that couldn't be checked.
+    pub failed: Vec<(PathBuf, NekosBestError)>,
+}
+
+impl VerifyReport {
+    /// Whether the mirror is complete and intact.
+    pub fn is_ok(&self) -> bool {
+        self.missing.is_empty()
+            && self.extra.is_empty()
+            && self.corrupted.is_empty()
+            && self.failed.is_empty()
+    }
+}
+
+enum FileStatus {
+    Verified,
+    Missing,
+    Corrupted,
+}
+
+/// Checks the mirror of `category` in `dir` against the server, with a
+/// supplied client.
+///
+/// Every file is compared by size, from a `HEAD` request, or by content
+/// with [`VerifyOptions::refetch`]. The mirror itself is never modified.
+///
+/// # Errors
+/// [`NekosBestError::NoFileRange`] if the endpoint doesn't advertise a
+/// numeric file range, or an error getting the endpoints or reading `dir`.
+/// Errors checking single files are in the [`VerifyReport::failed`]
+/// instead.
+pub async fn verify_mirror_with_client(
+    client: &Client,
+    dir: impl AsRef<Path>,
+    category: impl Into<Category>,
+    options: &VerifyOptions,
+) -> Result<VerifyReport, NekosBestError> {
+    let dir = dir.as_ref();
+    let (extension, files) = mirror_files(client, category.into(), dir).await?;
+
+    let mut report = VerifyReport {
+        extra: stale_files(dir, &extension, &files)?,
+        ..VerifyReport::default()
+    };
+
+    let statuses: Vec<_> = futures::stream::iter(&files)
+        .map(|(url, path)| verify_file(client, url, path, options.refetch))
+        .buffered(options.concurrency.max(1))
+        .collect()
+        .await;
+
+    for ((_, path), status) in files.into_iter().zip(statuses) {
+        match status {
+            Ok(FileStatus::Verified) => report.verified.push(path),
+            Ok(FileStatus::Missing) => report.missing.push(path),
+            Ok(FileStatus::Corrupted) => report.corrupted.push(path),
+            Err(e) => report.failed.push((path, e)),
+        }
+    }
+
+    Ok(report)
+}
+
+/// Checks the mirror of `category` in `dir` against the server, with
+/// the default client, see [`verify_mirror_with_client`].
+pub async fn verify_mirror(
+    dir: impl AsRef<Path>,
+    category: impl Into<Category>,
+    options: &VerifyOptions,
+) -> Result<VerifyReport, NekosBestError> {
+    verify_mirror_with_client(&default_client(), dir, category, options).await
+}
+
+async fn verify_file(
+    client: &Client,
+    url: &str,
+    path: &Path,
+    refetch: bool,
+) -> Result<FileStatus, NekosBestError> {
+    let len = match std::fs::metadata(path) {
+        Ok(metadata) => metadata.len(),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(FileStatus::Missing),
+        Err(e) => return Err(e.into()),
+    };
+
+    let intact = if refetch {
+        let expected = download_bytes_from_url_with_client(client, url).await?;
+        std::fs::read(path)? == expected
+    } else {
+        len > 0
+            && remote_size(client, url)
+                .await?
+                .is_none_or(|expected| expected == len)
+    };
+
+    Ok(if intact {
+        FileStatus::Verified
+    } else {
+        FileStatus::Corrupted
+    })
+}
+
+/// The files in `dir` with the extension of the category that aren't
+/// among its `files`.
+fn stale_files(
+    dir: &Path,
+    extension: &str,
+    files: &[(String, PathBuf)],
+) -> Result<Vec<PathBuf>, NekosBestError> {
+    let expected: HashSet<&Path> = files.iter().map(|(_, path)| path.as_path()).collect();
+    let mut stale = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == extension)
+            && !expected.contains(path.as_path())
+        {
+            stale.push(path);
+        }
+    }
+    stale.sort();
+
+    Ok(stale)
+}
+
+/// Downloads every file of `category` into `dir`, with the default
+/// client, see [`mirror_category_with_client`].
+pub async fn mirror_category(
+    category: impl Into<Category>,
+    dir: impl AsRef<Path>,
+    options: &MirrorOptions,
+) -> Result<MirrorReport, NekosBestError> {
+    mirror_category_with_client(&default_client(), category, dir, options).await
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn stale() {
+        let dir =
+            std::env::temp_dir().join(format!("nekosbest-mirror-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for name in ["0001.png", "0002.png", "0003.png", MANIFEST_FILE_NAME] {
+            std::fs::write(dir.join(name), b"x").unwrap();
+        }
+
+        let files = [
+            (String::new(), dir.join("0001.png")),
+            (String::new(), dir.join("0002.png")),
+        ];
+        assert_eq!(
+            stale_files(&dir, "png", &files).unwrap(),
+            vec![dir.join("0003.png")]
+        );
+
+        let report = MirrorReport {
+            skipped: vec![dir.join("0001.png")],
+            removed: vec![dir.join("0003.png")],
+            ..MirrorReport::default()
+        };
+        let manifest = MirrorManifest::new(Category::Neko, &report);
+        assert_eq!(manifest.skipped, ["0001.png"]);
+        assert_eq!(manifest.removed, ["0003.png"]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
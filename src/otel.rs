@@ -0,0 +1,115 @@
+//! OpenTelemetry-compatible spans for the requests of the client, with
+//! the `otel` feature.
+//!
+//! Every request sent (including retries) gets a span with the names and
+//! fields of the OpenTelemetry semantic conventions for HTTP clients,
+//! picked up by `tracing-opentelemetry`. With
+//! [`crate::client::ClientBuilder::propagate_trace_context`], its context
+//! is also sent to the API, in the W3C trace-context headers.
+
+use opentelemetry::trace::{SpanContext, TraceContextExt};
+use reqwest::header::{HeaderMap, HeaderValue};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+use crate::client::{ReqwestRequestBuilder, ReqwestResponse};
+
+/// The span of sending `req`, after `retries` retries.
+pub(crate) fn client_span(req: &ReqwestRequestBuilder, retries: u32) -> tracing::Span {
+    let request = req.try_clone().and_then(|req| req.build().ok());
+    let method = request.as_ref().map(|req| req.method().as_str().to_owned());
+    let url = request.as_ref().map(|req| req.url());
+
+    tracing::info_span!(
+        "HTTP request",
+        otel.name = method.as_deref().unwrap_or("HTTP"),
+        otel.kind = "client",
+        otel.status_code = tracing::field::Empty,
+        http.request.method = method.as_deref().unwrap_or("_OTHER"),
+        http.request.resend_count = (retries > 0).then_some(retries),
+        http.response.status_code = tracing::field::Empty,
+        url.full = url.map(|url| url.as_str()),
+        server.address = url.and_then(|url| url.host_str()),
+        server.port = url.and_then(|url| url.port_or_known_default()),
+        error.type = tracing::field::Empty,
+    )
+}
+
+/// Records the response to the request of `span` (or its failure).
+pub(crate) fn record_response(
+    span: &tracing::Span,
+    result: &Result<ReqwestResponse, reqwest::Error>,
+) {
+    match result {
+        Ok(resp) => {
+            let status = resp.status();
+            span.record("http.response.status_code", status.as_u16());
+            if status.is_client_error() || status.is_server_error() {
+                span.record("error.type", status.as_str());
+                span.record("otel.status_code", "ERROR");
+            }
+        }
+        Err(e) => {
+            span.record(
+                "error.type",
+                if e.is_timeout() { "timeout" } else { "_OTHER" },
+            );
+            span.record("otel.status_code", "ERROR");
+        }
+    }
+}
+
+/// The `traceparent` and `tracestate` headers carrying the context of
+/// `span`, or none if it isn't recorded by `tracing-opentelemetry`.
+pub(crate) fn trace_context_headers(span: &tracing::Span) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    let cx = span.context();
+    let span = cx.span();
+    let span_context = span.span_context();
+    if !span_context.is_valid() {
+        return headers;
+    }
+
+    if let Ok(value) = HeaderValue::from_str(&traceparent(span_context)) {
+        headers.insert("traceparent", value);
+    }
+    let state = span_context.trace_state().header();
+    if let (false, Ok(value)) = (state.is_empty(), HeaderValue::from_str(&state)) {
+        headers.insert("tracestate", value);
+    }
+    headers
+}
+
+/// The `traceparent` header of `span_context`, in version `00` of the
+/// format.
+fn traceparent(span_context: &SpanContext) -> String {
+    format!(
+        "00-{}-{}-{:02x}",
+        span_context.trace_id(),
+        span_context.span_id(),
+        span_context.trace_flags().to_u8()
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use opentelemetry::trace::{SpanId, TraceFlags, TraceId, TraceState};
+
+    #[test]
+    fn headers() {
+        let span_context = SpanContext::new(
+            TraceId::from(0x4bf92f3577b34da6a3ce929d0e0e4736),
+            SpanId::from(0x00f067aa0ba902b7),
+            TraceFlags::SAMPLED,
+            true,
+            TraceState::default(),
+        );
+        assert_eq!(
+            traceparent(&span_context),
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"
+        );
+
+        // Without `tracing-opentelemetry`, there is no context to send.
+        assert!(trace_context_headers(&tracing::info_span!("test")).is_empty());
+    }
+}
@@ -0,0 +1,206 @@
+//! Themed groups of categories.
+
+#[cfg(feature = "blocking")]
+use nb_blocking_util::blocking;
+use rand::Rng;
+
+use crate::client::{default_client, Client};
+use crate::{get_with_client, Category, NekosBestError, NekosBestResponseSingle, Selection};
+
+/// Several categories grouped under a name, e.g. `"affection"` for
+/// [`Category::Hug`], [`Category::Cuddle`] and [`Category::Pat`].
+///
+/// Each category has a weight, and [`CategoryPack::pick`] picks one of
+/// them with a probability proportional to it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CategoryPack {
+    name: String,
+    categories: Vec<(Category, u32)>,
+}
+
+impl CategoryPack {
+    /// Creates a new, empty pack.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            categories: Vec::new(),
+        }
+    }
+
+    /// Creates a pack where all the given categories are equally likely.
+    pub fn uniform(
+        name: impl Into<String>,
+        categories: impl IntoIterator<Item = impl Into<Category>>,
+    ) -> Self {
+        categories
+            .into_iter()
+            .fold(Self::new(name), |pack, category| pack.with(category))
+    }
+
+    /// Adds a category to the pack, with a weight of 1.
+    pub fn with(self, category: impl Into<Category>) -> Self {
+        self.with_weighted(category, 1)
+    }
+
+    /// Adds a category to the pack, with the given weight.
+    pub fn with_weighted(mut self, category: impl Into<Category>, weight: u32) -> Self {
+        self.categories.push((category.into(), weight));
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The categories in this pack, along with their weights.
+    pub fn categories(&self) -> &[(Category, u32)] {
+        &self.categories
+    }
+
+    /// Picks a category, or [`None`] if the pack has no categories
+    /// with a non-zero weight.
+    pub fn pick(&self) -> Option<Category> {
+        self.pick_with(&mut rand::thread_rng())
+    }
+
+    /// Picks a category according to `selection`, or [`None`] if there
+    /// is nothing to pick from.
+    ///
+    /// [`Selection::Index`] picks the category at that index in
+    /// [`CategoryPack::categories`], regardless of the weights.
+    pub fn select(&self, selection: Selection) -> Option<Category> {
+        match selection {
+            Selection::Index(index) => self.categories.get(index).map(|(c, _)| *c),
+            Selection::Random => self.pick(),
+            Selection::Seeded(_) => self.pick_nth_weight(selection.pick(self.total_weight())?),
+        }
+    }
+
+    /// Like [`CategoryPack::pick`], but with the given random number generator.
+    pub fn pick_with(&self, rng: &mut impl Rng) -> Option<Category> {
+        let total = self.total_weight();
+        if total == 0 {
+            return None;
+        }
+
+        self.pick_nth_weight(rng.gen_range(0..total))
+    }
+
+    fn total_weight(&self) -> u64 {
+        self.categories.iter().map(|(_, w)| u64::from(*w)).sum()
+    }
+
+    /// The category that the `n`-th unit of weight belongs to.
+    fn pick_nth_weight(&self, mut n: u64) -> Option<Category> {
+        for (category, weight) in &self.categories {
+            let weight = u64::from(*weight);
+            if n < weight {
+                return Some(*category);
+            }
+            n -= weight;
+        }
+
+        None
+    }
+}
+
+/// Gets a single image from a category picked from `pack`, with a supplied client.
+///
+/// # Errors
+/// [`NekosBestError::EmptyCategoryPack`] if there is nothing to pick
+/// from, otherwise the same as [`get_with_client`].
+#[cfg_attr(feature = "blocking", blocking)]
+pub async fn get_from_pack_with_client(
+    client: &Client,
+    pack: &CategoryPack,
+) -> Result<NekosBestResponseSingle, NekosBestError> {
+    let category = pack.pick().ok_or(NekosBestError::EmptyCategoryPack)?;
+    get_with_client(client, category).await
+}
+
+/// Gets a single image from a category picked from `pack` according
+/// to `selection`, with a supplied client.
+///
+/// # Errors
+/// Same as [`get_from_pack_with_client`].
+#[cfg_attr(feature = "blocking", blocking)]
+pub async fn select_from_pack_with_client(
+    client: &Client,
+    pack: &CategoryPack,
+    selection: Selection,
+) -> Result<NekosBestResponseSingle, NekosBestError> {
+    let category = pack
+        .select(selection)
+        .ok_or(NekosBestError::EmptyCategoryPack)?;
+    get_with_client(client, category).await
+}
+
+/// Gets a single image from a category picked from `pack` according
+/// to `selection`, with the default client.
+///
+/// # Errors
+/// Same as [`get_from_pack_with_client`].
+#[cfg_attr(feature = "blocking", blocking)]
+pub async fn select_from_pack(
+    pack: &CategoryPack,
+    selection: Selection,
+) -> Result<NekosBestResponseSingle, NekosBestError> {
+    select_from_pack_with_client(&default_client(), pack, selection).await
+}
+
+/// Gets a single image from a category picked from `pack`, with the default client.
+///
+/// # Errors
+/// Same as [`get_from_pack_with_client`].
+#[cfg_attr(feature = "blocking", blocking)]
+pub async fn get_from_pack(pack: &CategoryPack) -> Result<NekosBestResponseSingle, NekosBestError> {
+    get_from_pack_with_client(&default_client(), pack).await
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn weighted_pick() {
+        let pack = CategoryPack::new("affection")
+            .with_weighted(Category::Hug, 3)
+            .with_weighted(Category::Cuddle, 0)
+            .with_weighted(Category::Pat, 1);
+
+        let mut rng = StdRng::seed_from_u64(0);
+        let hugs = (0..1000)
+            .map(|_| pack.pick_with(&mut rng).unwrap())
+            .inspect(|c| assert_ne!(*c, Category::Cuddle))
+            .filter(|c| *c == Category::Hug)
+            .count();
+        assert!((650..850).contains(&hugs), "{hugs}");
+    }
+
+    #[test]
+    fn select() {
+        let pack = CategoryPack::uniform("affection", [Category::Hug, Category::Pat]);
+        assert_eq!(pack.select(Selection::Index(1)), Some(Category::Pat));
+        assert_eq!(pack.select(Selection::Index(2)), None);
+        assert_eq!(
+            pack.select(Selection::Seeded(42)),
+            pack.select(Selection::Seeded(42))
+        );
+        assert_eq!(
+            CategoryPack::new("nothing").select(Selection::Seeded(42)),
+            None
+        );
+    }
+
+    #[test]
+    fn empty_pack() {
+        assert_eq!(CategoryPack::new("nothing").pick(), None);
+        assert_eq!(
+            CategoryPack::new("zero")
+                .with_weighted(Category::Hug, 0)
+                .pick(),
+            None
+        );
+    }
+}
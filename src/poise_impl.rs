@@ -0,0 +1,39 @@
+//! [poise] integration for [`Category`].
+//!
+//! `Category` implements [`FromStr`](std::str::FromStr), so poise
+//! already accepts it as a command parameter: slash commands take it as
+//! a string option, and both slash and prefix commands parse it with
+//! [`Category::from_alias`] semantics (aliases included).
+//!
+//! There are more categories than the 25 choices Discord allows for
+//! a slash command parameter, so none are registered as choices;
+//! use [`autocomplete_category`] to suggest them instead:
+//!
+//! ```ignore
+//! #[poise::command(slash_command)]
+//! async fn neko(
+//!     ctx: Context<'_>,
+//!     #[autocomplete = "nekosbest::poise_impl::autocomplete_category"]
+//!     category: nekosbest::Category,
+//! ) -> Result<(), Error> {
+//!     // ...
+//! }
+//! ```
+
+use crate::Category;
+
+/// An autocomplete function suggesting the url names of the categories
+/// with a name (url name or alias) starting with `partial`.
+///
+/// Discord shows at most 25 suggestions, so only the first 25 are
+/// returned.
+#[allow(clippy::unused_async)] // Required by poise's `#[autocomplete]`
+pub async fn autocomplete_category<U, E>(
+    _ctx: poise::Context<'_, U, E>,
+    partial: &str,
+) -> impl Iterator<Item = &'static str> {
+    Category::completions(partial)
+        .into_iter()
+        .take(25)
+        .map(Category::to_url_name)
+}
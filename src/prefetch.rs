@@ -0,0 +1,227 @@
+//! Keeping images buffered in memory, so they can be handed out without
+//! waiting on a request.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+#[cfg(feature = "blocking")]
+use nb_blocking_util::blocking;
+
+use crate::client::Client;
+use crate::implementation::fetch_amount;
+use crate::rt::Instant;
+use crate::{get_with_client, Category, NekosBestError, NekosBestResponseSingle, MAX_AMOUNT};
+
+type ErrorCallback = Arc<dyn Fn(Category, &NekosBestError) + Send + Sync>;
+
+/// Keeps up to [`Prefetcher::capacity`] images buffered per category.
+///
+/// The buffers are refilled by [`Prefetcher::run`], which should be
+/// spawned as a background task on the executor of your choice. The
+/// prefetcher is cheap to clone, and all clones share the same buffers.
+///
+/// ```no_run
+/// # use nekosbest::{client::{Client, ClientConfig}, prefetch::Prefetcher, Category};
+/// # async fn f() {
+/// let prefetcher = Prefetcher::new(
+///     Client::new(ClientConfig::default()),
+///     [Category::Neko, Category::Hug],
+/// );
+/// tokio::spawn(prefetcher.clone().run());
+///
+/// // later, e.g. in a command handler
+/// let neko = prefetcher.get(Category::Neko).await.unwrap();
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct Prefetcher {
+    client: Client,
+    buffers: Arc<Mutex<HashMap<Category, VecDeque<NekosBestResponseSingle>>>>,
+    capacity: usize,
+    poll_interval: Duration,
+    on_error: Option<ErrorCallback>,
+}
+
+impl Prefetcher {
+    /// The default for [`Prefetcher::capacity`].
+    pub const DEFAULT_CAPACITY: usize = 10;
+
+    /// Creates a prefetcher keeping images from `categories` buffered.
+    pub fn new(client: Client, categories: impl IntoIterator<Item = impl Into<Category>>) -> Self {
+        Self {
+            client,
+            buffers: Arc::new(Mutex::new(
+                categories
+                    .into_iter()
+                    .map(|category| (category.into(), VecDeque::new()))
+                    .collect(),
+            )),
+            capacity: Self::DEFAULT_CAPACITY,
+            poll_interval: Duration::from_secs(1),
+            on_error: None,
+        }
+    }
+
+    /// How many images to keep buffered per category. Defaults to
+    /// [`Prefetcher::DEFAULT_CAPACITY`].
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// How long [`Prefetcher::run`] waits between refills. Defaults to
+    /// 1 second.
+    pub fn poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Sets a callback invoked when refilling a buffer failed.
+    pub fn on_error(
+        mut self,
+        f: impl Fn(Category, &NekosBestError) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_error = Some(Arc::new(f));
+        self
+    }
+
+    /// How many images are currently buffered for `category`.
+    pub fn buffered(&self, category: impl Into<Category>) -> usize {
+        self.buffers
+            .lock()
+            .unwrap()
+            .get(&category.into())
+            .map_or(0, VecDeque::len)
+    }
+
+    /// Takes a buffered image from `category`, if there is one.
+    ///
+    /// This never waits on a request; categories that weren't passed to
+    /// [`Prefetcher::new`] are never buffered.
+    pub fn pop(&self, category: impl Into<Category>) -> Option<NekosBestResponseSingle> {
+        self.buffers
+            .lock()
+            .unwrap()
+            .get_mut(&category.into())?
+            .pop_front()
+    }
+
+    /// Takes a buffered image from `category`, or requests one if the
+    /// buffer is empty.
+    ///
+    /// # Errors
+    /// Same as [`get_with_client`], if a request was needed.
+    #[cfg_attr(feature = "blocking", blocking)]
+    pub async fn get(
+        &self,
+        category: impl Into<Category>,
+    ) -> Result<NekosBestResponseSingle, NekosBestError> {
+        let category = category.into();
+        match self.pop(category) {
+            Some(image) => Ok(image),
+            None => get_with_client(&self.client, category).await,
+        }
+    }
+
+    /// Refills the buffers once.
+    ///
+    /// At most [`MAX_AMOUNT`] images are requested per category, so it can
+    /// take several refills to fill a buffer with a larger capacity. The
+    /// requests go around the cache of the client, not to buffer the same
+    /// images again.
+    #[cfg_attr(feature = "blocking", blocking)]
+    pub async fn refill(&self) {
+        for (category, missing) in self.missing() {
+            let amount = missing.min(usize::from(MAX_AMOUNT)) as u8;
+            match fetch_amount(&self.client, category, amount).await {
+                Ok(response) => {
+                    if let Some(buffer) = self.buffers.lock().unwrap().get_mut(&category) {
+                        buffer.extend(response.0);
+                        buffer.truncate(self.capacity);
+                    }
+                }
+                Err(e) => {
+                    if let Some(on_error) = &self.on_error {
+                        on_error(category, &e);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Keeps the buffers filled, forever.
+    #[cfg_attr(feature = "blocking", blocking)]
+    pub async fn run(self) {
+        loop {
+            self.refill().await;
+            crate::rt::sleep_until(Instant::now() + self.poll_interval).await;
+        }
+    }
+
+    /// The categories with room in their buffers, and how much room.
+    fn missing(&self) -> Vec<(Category, usize)> {
+        self.buffers
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(category, buffer)| (*category, self.capacity.saturating_sub(buffer.len())))
+            .filter(|(_, missing)| *missing > 0)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::client::ClientConfig;
+
+    #[test]
+    fn buffers() {
+        let prefetcher =
+            Prefetcher::new(Client::new(ClientConfig::default()), [Category::Neko]).capacity(30);
+        assert_eq!(prefetcher.missing(), vec![(Category::Neko, 30)]);
+        assert!(prefetcher.pop(Category::Neko).is_none());
+        assert!(prefetcher.pop(Category::Hug).is_none());
+
+        let image = crate::sans_io::parse_response_single(
+            br#"{"results":[{"artist_href":"https://example.com/a","artist_name":"a","url":"https://nekos.best/api/v2/neko/a.png"}]}"#,
+        )
+        .unwrap();
+        prefetcher
+            .buffers
+            .lock()
+            .unwrap()
+            .get_mut(&Category::Neko)
+            .unwrap()
+            .push_back(image);
+
+        let clone = prefetcher.clone();
+        assert_eq!(clone.buffered(Category::Neko), 1);
+        assert_eq!(prefetcher.missing(), vec![(Category::Neko, 29)]);
+        assert_eq!(
+            clone.pop(Category::Neko).unwrap().url,
+            "https://nekos.best/api/v2/neko/a.png"
+        );
+        assert_eq!(prefetcher.buffered(Category::Neko), 0);
+    }
+
+    #[cfg(all(feature = "test-util", not(feature = "blocking")))]
+    #[tokio::test]
+    async fn refill_with_cache() {
+        let server = crate::test_util::MockServer::start().unwrap();
+        let client = Client::builder()
+            .base_url(server.base_url())
+            .cache(Duration::from_secs(60), 16)
+            .build()
+            .unwrap();
+        let prefetcher = Prefetcher::new(client, [Category::Pat]).capacity(40);
+
+        prefetcher.refill().await;
+        prefetcher.refill().await;
+        let urls = std::iter::from_fn(|| prefetcher.pop(Category::Pat))
+            .map(|image| image.url)
+            .collect::<std::collections::HashSet<_>>();
+        assert_eq!(urls.len(), 40);
+    }
+}
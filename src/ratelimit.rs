@@ -0,0 +1,87 @@
+//! The client-side rate limiter of [`crate::client::Client`].
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+#[cfg(feature = "blocking")]
+use nb_blocking_util::blocking;
+
+use crate::rt::Instant;
+
+/// A token bucket, refilled with `rate` tokens per second up to `burst`.
+///
+/// Tokens can go negative, in which case callers are queued behind the
+/// ones that already took a token, in order.
+pub(crate) struct RateLimiter {
+    rate: f64,
+    burst: f64,
+    state: Mutex<Bucket>,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+impl RateLimiter {
+    pub(crate) fn new(requests_per_second: f64, burst: u32) -> Self {
+        let burst = f64::from(burst.max(1));
+        Self {
+            rate: requests_per_second,
+            burst,
+            state: Mutex::new(Bucket {
+                tokens: burst,
+                updated_at: Instant::now(),
+            }),
+        }
+    }
+
+    /// Takes a token, returning when the request it is for may be sent.
+    fn reserve(&self, now: Instant) -> Instant {
+        let mut bucket = self.state.lock().unwrap();
+        let elapsed = now.saturating_duration_since(bucket.updated_at);
+        bucket.tokens = (bucket.tokens + elapsed.as_secs_f64() * self.rate).min(self.burst);
+        bucket.updated_at = now;
+        bucket.tokens -= 1.0;
+
+        if bucket.tokens >= 0.0 {
+            now
+        } else {
+            now + Duration::from_secs_f64(-bucket.tokens / self.rate)
+        }
+    }
+
+    /// Waits until a request may be sent.
+    #[cfg_attr(feature = "blocking", blocking)]
+    pub(crate) async fn acquire(&self) {
+        let now = Instant::now();
+        let at = self.reserve(now);
+        if at > now {
+            crate::rt::sleep_until(at).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn token_bucket() {
+        let limiter = RateLimiter::new(2.0, 2);
+        let start = Instant::now();
+
+        // the burst goes through at once
+        assert_eq!(limiter.reserve(start), start);
+        assert_eq!(limiter.reserve(start), start);
+        // then one every half second
+        assert_eq!(limiter.reserve(start), start + Duration::from_millis(500));
+        assert_eq!(limiter.reserve(start), start + Duration::from_millis(1000));
+
+        // after a long pause, the bucket is full again, but not more
+        let later = start + Duration::from_secs(60);
+        assert_eq!(limiter.reserve(later), later);
+        assert_eq!(limiter.reserve(later), later);
+        assert!(limiter.reserve(later) > later);
+    }
+}
@@ -0,0 +1,167 @@
+//! A builder for requests of images, see [`request`].
+
+#[cfg(feature = "blocking")]
+use nb_blocking_util::blocking;
+use std::time::Duration;
+
+use crate::client::{default_client, Client};
+use crate::{Category, NekosBestError, NekosBestResponse};
+
+/// Starts building a request for images from `category`:
+///
+/// ```no_run
+/// # async fn f(client: &nekosbest::client::Client) -> Result<(), nekosbest::NekosBestError> {
+/// # use std::time::Duration;
+/// use nekosbest::Category;
+///
+/// let images = nekosbest::request(Category::Hug)
+///     .amount(5)
+///     .unique(true)
+///     .retries(2)
+///     .timeout(Duration::from_secs(2))
+///     .send(client)
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn request(category: impl Into<Category>) -> ImageRequest {
+    ImageRequest::new(category)
+}
+
+/// A request for images, see [`request`].
+#[derive(Debug, Clone, PartialEq)]
+#[must_use = "requests do nothing until sent"]
+pub struct ImageRequest {
+    category: Category,
+    amount: Option<usize>,
+    unique: bool,
+    max_requests: usize,
+    retries: Option<u32>,
+    timeout: Option<Duration>,
+}
+
+impl ImageRequest {
+    /// The default for [`ImageRequest::max_requests`].
+    pub const DEFAULT_MAX_REQUESTS: usize = 5;
+
+    pub fn new(category: impl Into<Category>) -> Self {
+        Self {
+            category: category.into(),
+            amount: None,
+            unique: false,
+            max_requests: Self::DEFAULT_MAX_REQUESTS,
+            retries: None,
+            timeout: None,
+        }
+    }
+
+    /// How many images to get. Amounts over [`crate::MAX_AMOUNT`] are split
+    /// into several requests, see [`crate::get_with_client_exact_amount`].
+    /// Without an amount, a single image is requested.
+    pub fn amount(mut self, amount: usize) -> Self {
+        self.amount = Some(amount);
+        self
+    }
+
+    /// Whether the images should all be different, requesting more images
+    /// as long as there are duplicates, see
+    /// [`crate::get_with_client_unique_amount`]. Defaults to `false`.
+    pub fn unique(mut self, unique: bool) -> Self {
+        self.unique = unique;
+        self
+    }
+
+    /// The most requests sent to get [`ImageRequest::unique`] images.
+    /// Defaults to [`ImageRequest::DEFAULT_MAX_REQUESTS`].
+    pub fn max_requests(mut self, max_requests: usize) -> Self {
+        self.max_requests = max_requests;
+        self
+    }
+
+    /// Retries the requests failing with transient errors up to
+    /// `max_retries` times, with the delays of the retry policy of the
+    /// client, or the default [`crate::client::RetryPolicy`].
+    pub fn retries(mut self, max_retries: u32) -> Self {
+        self.retries = Some(max_retries);
+        self
+    }
+
+    /// The timeout for each of the requests, see
+    /// [`Client::with_timeout`].
+    ///
+    /// Ignored on wasm32, where the browser handles timeouts.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Sends the request through `client`.
+    ///
+    /// # Errors
+    /// Same as [`crate::get_with_client_amount`].
+    #[cfg_attr(feature = "blocking", blocking)]
+    pub async fn send(&self, client: &Client) -> Result<NekosBestResponse, NekosBestError> {
+        let mut client = client.clone();
+        if let Some(max_retries) = self.retries {
+            let policy = client.retry_policy().copied().unwrap_or_default();
+            client = client.with_retry_policy(policy.max_retries(max_retries));
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(timeout) = self.timeout {
+            client = client.with_timeout(timeout);
+        }
+
+        match self.amount {
+            None => {
+                let image = crate::get_with_client(&client, self.category).await?;
+                Ok(NekosBestResponse(vec![image]))
+            }
+            Some(amount) if self.unique => {
+                crate::get_with_client_unique_amount(
+                    &client,
+                    self.category,
+                    amount,
+                    self.max_requests,
+                )
+                .await
+            }
+            Some(amount) => {
+                crate::get_with_client_exact_amount(&client, self.category, amount).await
+            }
+        }
+    }
+
+    /// Sends the request through the default client, see
+    /// [`crate::client::default_client`].
+    ///
+    /// # Errors
+    /// Same as [`ImageRequest::send`].
+    #[cfg_attr(feature = "blocking", blocking)]
+    pub async fn send_default(&self) -> Result<NekosBestResponse, NekosBestError> {
+        self.send(&default_client()).await
+    }
+}
+
+#[cfg(all(test, feature = "test-util", not(feature = "blocking")))]
+mod test {
+    use super::*;
+    use crate::test_util::MockServer;
+
+    #[tokio::test]
+    async fn send() {
+        let server = MockServer::start().unwrap();
+        let client = server.client();
+
+        assert_eq!(request(Category::Pat).send(&client).await.unwrap().len(), 1);
+        let images = request(Category::Pat)
+            .amount(25)
+            .unique(true)
+            .retries(1)
+            .timeout(Duration::from_secs(5))
+            .send(&client)
+            .await
+            .unwrap();
+        assert_eq!(images.len(), 25);
+        assert_eq!(server.requests(), 3);
+    }
+}
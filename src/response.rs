@@ -1,6 +1,8 @@
 use std::ops::{Deref, DerefMut, Index, IndexMut};
 
-use crate::details::Details;
+use crate::details::{ArtistInfo, Details, GifDetails, ImageDetails, SourceInfo};
+#[cfg(feature = "url")]
+use crate::NekosBestError;
 
 #[derive(serde::Deserialize, Debug, Clone, Hash)]
 struct NekosBestResponseV2 {
@@ -8,6 +10,8 @@ struct NekosBestResponseV2 {
 }
 
 /// A response from the api
+///
+/// Serializes back to the format of the API, `{"results": [...]}`.
 #[derive(serde::Deserialize, Debug, Clone, Hash)]
 #[serde(from = "NekosBestResponseV2")]
 pub struct NekosBestResponse(pub Vec<NekosBestResponseSingle>);
@@ -18,6 +22,57 @@ impl From<NekosBestResponseV2> for NekosBestResponse {
     }
 }
 
+impl serde::Serialize for NekosBestResponse {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use serde::ser::SerializeStruct;
+
+        let mut s = serializer.serialize_struct("NekosBestResponseV2", 1)?;
+        s.serialize_field("results", &self.0)?;
+        s.end()
+    }
+}
+
+impl NekosBestResponse {
+    /// How many images are in the response.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl IntoIterator for NekosBestResponse {
+    type Item = NekosBestResponseSingle;
+    type IntoIter = std::vec::IntoIter<NekosBestResponseSingle>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a NekosBestResponse {
+    type Item = &'a NekosBestResponseSingle;
+    type IntoIter = std::slice::Iter<'a, NekosBestResponseSingle>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a mut NekosBestResponse {
+    type Item = &'a mut NekosBestResponseSingle;
+    type IntoIter = std::slice::IterMut<'a, NekosBestResponseSingle>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter_mut()
+    }
+}
+
 impl Index<usize> for NekosBestResponse {
     type Output = NekosBestResponseSingle;
 
@@ -48,13 +103,152 @@ impl DerefMut for NekosBestResponse {
 
 /// A response from the api, in the case of requesting a single
 /// url with [`get`] or [`get_with_client`]
-#[derive(Debug, Clone, Hash, serde::Deserialize)]
+///
+/// Serializes back to the format of the API, with the fields of the
+/// details next to the url.
+///
+/// Non-exhaustive, to add fields filled in by the crate (like `stale`); use
+/// [`NekosBestResponseSingle::new`] to build one.
+#[derive(Debug, Clone, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(try_from = "RawResponseSingle")]
+#[non_exhaustive]
 pub struct NekosBestResponseSingle {
     /// The url
     pub url: String,
     /// The details
     #[serde(flatten)]
     pub details: Details,
+    /// Whether this image was read from the disk cache past its ttl,
+    /// because the API couldn't be reached, see
+    /// [`crate::client::ClientBuilder::offline_fallback`].
+    ///
+    /// Not serialized, like it isn't part of the responses of the API.
+    #[serde(skip)]
+    pub stale: bool,
+}
+
+/// The wire format of a [`NekosBestResponseSingle`].
+///
+/// Deserializing through `#[serde(flatten)]` and an untagged [`Details`]
+/// buffers every entry before trying each variant in turn, so instead
+/// all the fields are read in a single pass, and the details are picked
+/// afterwards based on which of them are present.
+#[derive(serde::Deserialize)]
+struct RawResponseSingle {
+    url: String,
+    artist_href: Option<String>,
+    artist_name: Option<String>,
+    source_url: Option<String>,
+    anime_name: Option<String>,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub(crate) enum RawResponseError {
+    #[error("response has neither image nor gif details")]
+    UnknownDetails,
+    #[error("invalid url in details: {0}")]
+    Url(#[from] url::ParseError),
+}
+
+impl TryFrom<RawResponseSingle> for NekosBestResponseSingle {
+    type Error = RawResponseError;
+
+    fn try_from(raw: RawResponseSingle) -> Result<Self, Self::Error> {
+        let details = match raw {
+            RawResponseSingle {
+                artist_href: Some(artist_href),
+                artist_name: Some(artist_name),
+                source_url,
+                ..
+            } => Details::Image(ImageDetails {
+                artist: ArtistInfo {
+                    name: artist_name,
+                    href: artist_href.parse()?,
+                },
+                source: source_url
+                    .map(|url| url.parse().map(|url| SourceInfo { url }))
+                    .transpose()?,
+            }),
+            RawResponseSingle {
+                anime_name: Some(anime_name),
+                ..
+            } => Details::Gif(GifDetails { anime_name }),
+            _ => return Err(RawResponseError::UnknownDetails),
+        };
+
+        Ok(Self {
+            url: raw.url,
+            details,
+            stale: false,
+        })
+    }
+}
+
+impl NekosBestResponseSingle {
+    /// An image at `url`, with its `details`.
+    pub fn new(url: impl Into<String>, details: Details) -> Self {
+        Self {
+            url: url.into(),
+            details,
+            stale: false,
+        }
+    }
+}
+
+/// Fixtures for the tests of the rest of the crate.
+#[cfg(test)]
+impl NekosBestResponseSingle {
+    /// A gif at `url`, from the anime `anime_name`.
+    pub(crate) fn test_gif(url: impl Into<String>, anime_name: &str) -> Self {
+        Self::new(
+            url,
+            Details::Gif(GifDetails {
+                anime_name: anime_name.to_owned(),
+            }),
+        )
+    }
+
+    /// An image at `url`, by the artist `artist_name` at `artist_href`,
+    /// with `https://example.com/source` as source.
+    pub(crate) fn test_image(url: impl Into<String>, artist_name: &str, artist_href: &str) -> Self {
+        Self::new(
+            url,
+            Details::Image(ImageDetails {
+                artist: ArtistInfo {
+                    name: artist_name.to_owned(),
+                    href: artist_href.parse().unwrap(),
+                },
+                source: Some(SourceInfo {
+                    url: "https://example.com/source".parse().unwrap(),
+                }),
+            }),
+        )
+    }
+}
+
+#[cfg(feature = "url")]
+impl NekosBestResponseSingle {
+    /// The url, parsed.
+    ///
+    /// # Errors
+    /// [`NekosBestError::InvalidUrl`] if the url isn't valid.
+    pub fn parsed_url(&self) -> Result<url::Url, NekosBestError> {
+        url::Url::parse(&self.url).map_err(|source| NekosBestError::InvalidUrl {
+            url: self.url.clone(),
+            source,
+        })
+    }
+}
+
+/// An image file, as served by the API, see [`crate::get_file_with_client`].
+#[derive(Debug, Clone, Hash)]
+pub struct NekosBestFile {
+    /// The url the file was fetched from
+    pub url: String,
+    /// The contents of the file
+    pub bytes: Vec<u8>,
+    /// The details sent along with the file, if there were any
+    pub details: Option<Details>,
 }
 
 impl Deref for NekosBestResponseSingle {
@@ -70,3 +264,63 @@ impl DerefMut for NekosBestResponseSingle {
         &mut self.url
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn iterate() {
+        let mut resp: NekosBestResponse = serde_json::from_str(
+            r#"{"results":[{"anime_name":"a","url":"http://localhost/first"},{"anime_name":"b","url":"http://localhost/second"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(resp.len(), 2);
+        assert!(!resp.is_empty());
+
+        for image in &mut resp {
+            image.url.push('!');
+        }
+        let urls = (&resp)
+            .into_iter()
+            .map(|image| &image.url)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            urls,
+            ["http://localhost/first!", "http://localhost/second!"]
+        );
+        let urls = resp.into_iter().map(|image| image.url).collect::<Vec<_>>();
+        assert_eq!(
+            urls,
+            ["http://localhost/first!", "http://localhost/second!"]
+        );
+    }
+
+    #[test]
+    fn serialize_round_trips() {
+        let body = r#"{"results":[{"url":"https://nekos.best/api/v2/neko/a.png","artist_name":"a","artist_href":"https://example.com/a","source_url":"https://example.com/s"},{"url":"https://nekos.best/api/v2/pat/b.gif","anime_name":"b"}]}"#;
+        let resp: NekosBestResponse = serde_json::from_str(body).unwrap();
+        assert_eq!(serde_json::to_string(&resp).unwrap(), body);
+
+        let no_source = r#"{"url":"https://nekos.best/api/v2/neko/c.png","artist_name":"c","artist_href":"https://example.com/c"}"#;
+        let single: NekosBestResponseSingle = serde_json::from_str(no_source).unwrap();
+        assert_eq!(serde_json::to_string(&single).unwrap(), no_source);
+    }
+
+    #[cfg(feature = "url")]
+    #[test]
+    fn parsed_url() {
+        let image: NekosBestResponseSingle = serde_json::from_str(
+            r#"{"url":"https://nekos.best/api/v2/pat/a.gif","anime_name":"a"}"#,
+        )
+        .unwrap();
+        assert_eq!(image.parsed_url().unwrap().path(), "/api/v2/pat/a.gif");
+
+        let image: NekosBestResponseSingle =
+            serde_json::from_str(r#"{"url":"not a url","anime_name":"a"}"#).unwrap();
+        assert!(matches!(
+            image.parsed_url(),
+            Err(NekosBestError::InvalidUrl { .. })
+        ));
+    }
+}
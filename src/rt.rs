@@ -0,0 +1,96 @@
+//! The few runtime-specific operations the crate needs.
+//!
+//! Everything else in the crate is executor-agnostic; only the
+//! transport (reqwest, with the `reqwest` feature) assumes tokio. With the `tokio` feature
+//! disabled, timers are driven by `futures-timer` and file output
+//! goes through `std::fs`, so the crate can be used from async-std
+//! or smol applications without pulling in a second runtime.
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) use std::time::Instant;
+// `std::time::Instant::now` panics on wasm32-unknown-unknown.
+#[cfg(target_arch = "wasm32")]
+pub(crate) use web_time::Instant;
+
+#[cfg(all(feature = "reqwest", not(feature = "blocking"), feature = "tokio"))]
+pub(crate) async fn sleep_until(deadline: Instant) {
+    tokio::time::sleep_until(deadline.into()).await;
+}
+
+#[cfg(all(feature = "reqwest", not(feature = "blocking"), not(feature = "tokio")))]
+pub(crate) async fn sleep_until(deadline: Instant) {
+    futures_timer::Delay::new(deadline.saturating_duration_since(Instant::now())).await;
+}
+
+#[cfg(feature = "blocking")]
+pub(crate) fn sleep_until(deadline: Instant) {
+    std::thread::sleep(deadline.saturating_duration_since(Instant::now()));
+}
+
+#[cfg(all(feature = "download", not(feature = "blocking"), feature = "tokio"))]
+pub(crate) use self::tokio_file::File;
+
+#[cfg(all(
+    feature = "download",
+    any(feature = "blocking", not(feature = "tokio"))
+))]
+pub(crate) use self::std_file::File;
+
+#[cfg(all(feature = "download", not(feature = "blocking"), feature = "tokio"))]
+mod tokio_file {
+    use tokio::io::AsyncWriteExt;
+
+    pub(crate) struct File(tokio::fs::File);
+
+    impl File {
+        pub(crate) async fn create(path: impl AsRef<std::path::Path>) -> std::io::Result<Self> {
+            tokio::fs::File::create(path).await.map(Self)
+        }
+
+        pub(crate) async fn append(path: impl AsRef<std::path::Path>) -> std::io::Result<Self> {
+            tokio::fs::OpenOptions::new()
+                .append(true)
+                .create(true)
+                .open(path)
+                .await
+                .map(Self)
+        }
+
+        pub(crate) async fn write_all(&mut self, buf: &[u8]) -> std::io::Result<()> {
+            self.0.write_all(buf).await
+        }
+    }
+}
+
+#[cfg(all(
+    feature = "download",
+    any(feature = "blocking", not(feature = "tokio"))
+))]
+mod std_file {
+    #[cfg(feature = "blocking")]
+    use nb_blocking_util::blocking;
+    use std::io::Write;
+
+    pub(crate) struct File(std::fs::File);
+
+    impl File {
+        #[cfg_attr(feature = "blocking", blocking)]
+        pub(crate) async fn create(path: impl AsRef<std::path::Path>) -> std::io::Result<Self> {
+            std::fs::File::create(path).map(Self)
+        }
+
+        #[cfg_attr(feature = "blocking", blocking)]
+        pub(crate) async fn append(path: impl AsRef<std::path::Path>) -> std::io::Result<Self> {
+            std::fs::OpenOptions::new()
+                .append(true)
+                .create(true)
+                .open(path)
+                .map(Self)
+        }
+
+        #[cfg_attr(feature = "blocking", blocking)]
+        pub(crate) async fn write_all(&mut self, buf: &[u8]) -> std::io::Result<()> {
+            self.0.write_all(buf)
+        }
+    }
+}
@@ -0,0 +1,465 @@
+//! Transport-independent request construction and response parsing.
+//!
+//! Nothing in here performs I/O or touches reqwest types: URLs are
+//! built as plain strings, and responses are parsed from the raw body
+//! bytes and header values. Every transport in the crate goes through
+//! these functions, and other HTTP stacks can reuse them as well.
+//!
+//! With the `http` feature, [`build_request`] and [`parse_http_response`]
+//! do the same with the types of the `http` crate, for the HTTP stacks
+//! built on it.
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::category::CustomCategory;
+use crate::details::{ArtistInfo, GifDetails, ImageDetails, SourceInfo};
+use crate::{Category, NekosBestError, NekosBestResponse, NekosBestResponseSingle};
+
+/// The url of the endpoint for `category`.
+pub fn category_url(base_url: &str, category: Category) -> String {
+    category.to_url(base_url)
+}
+
+/// The url of the endpoint for `category`, requesting `amount` images.
+pub fn category_amount_url(base_url: &str, category: Category, amount: u8) -> String {
+    with_amount(category.to_url(base_url), amount)
+}
+
+/// The url of the endpoint for the custom `category`.
+pub fn custom_category_url(base_url: &str, category: &CustomCategory) -> String {
+    category.to_url(base_url)
+}
+
+/// The url of the endpoint for the custom `category`, requesting
+/// `amount` images.
+pub fn custom_category_amount_url(base_url: &str, category: &CustomCategory, amount: u8) -> String {
+    with_amount(category.to_url(base_url), amount)
+}
+
+fn with_amount(category_url: String, amount: u8) -> String {
+    format!("{category_url}?amount={amount}")
+}
+
+/// Checks that `amount` is in the `1..=`[`crate::MAX_AMOUNT`] range the
+/// API accepts.
+///
+/// # Errors
+/// [`NekosBestError::InvalidAmount`] if it isn't.
+pub fn check_amount(amount: u8) -> Result<u8, NekosBestError> {
+    match amount {
+        1..=crate::MAX_AMOUNT => Ok(amount),
+        _ => Err(NekosBestError::InvalidAmount {
+            requested: amount.into(),
+            min: 1,
+            max: crate::MAX_AMOUNT.into(),
+        }),
+    }
+}
+
+/// The request for `amount` images of `category`, or for the default of
+/// the API without an amount, with the [`crate::USER_AGENT`] of the crate.
+///
+/// # Errors
+/// [`NekosBestError::InvalidAmount`] if `amount` is not in the
+/// `1..=`[`crate::MAX_AMOUNT`] range, and [`NekosBestError::Backend`] if
+/// `base_url` doesn't make a valid url.
+#[cfg(feature = "http")]
+pub fn build_request(
+    base_url: &str,
+    category: Category,
+    amount: Option<u8>,
+) -> Result<http::Request<()>, NekosBestError> {
+    let url = match amount {
+        Some(amount) => category_amount_url(base_url, category, check_amount(amount)?),
+        None => category_url(base_url, category),
+    };
+    http::Request::get(url)
+        .header(http::header::USER_AGENT, crate::USER_AGENT)
+        .body(())
+        .map_err(|e| NekosBestError::Backend(Box::new(e)))
+}
+
+/// Parses the response to a [`build_request`] request, checking its status
+/// like [`check_status`], then its body like [`parse_response`].
+#[cfg(feature = "http")]
+pub fn parse_http_response(
+    response: &http::Response<impl AsRef<[u8]>>,
+) -> Result<NekosBestResponse, NekosBestError> {
+    let retry_after = response
+        .headers()
+        .get(http::header::RETRY_AFTER)
+        .and_then(|value| value.to_str().ok());
+    check_status(response.status().as_u16(), retry_after)?;
+    parse_response(response.body().as_ref())
+}
+
+/// The url of the endpoint describing all the other endpoints.
+pub fn endpoints_url(base_url: &str) -> String {
+    format!("{base_url}/endpoints")
+}
+
+/// The url of the search endpoint, for the given query.
+///
+/// `query` is serialized as the query string of the url, see [`crate::SearchQuery`].
+pub fn search_url(base_url: &str, query: &impl Serialize) -> String {
+    let query = serde_urlencoded::to_string(query).expect("search queries always serialize");
+    format!("{base_url}/search?{query}")
+}
+
+/// The id of the file an image url points to, i.e. its file name without
+/// the extension, ignoring any query string.
+///
+/// Two urls with the same id are the same image, even if served from
+/// different hosts.
+pub fn file_id(url: &str) -> &str {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    let name = path.rsplit('/').next().unwrap_or(path);
+    name.rsplit_once('.').map_or(name, |(id, _)| id)
+}
+
+/// Checks the status code of a response, along with the value of its
+/// `Retry-After` header, if any.
+///
+/// # Errors
+/// [`NekosBestError::RateLimited`] for `429 Too Many Requests`, and
+/// [`NekosBestError::Status`] for any other non-success status.
+pub fn check_status(status: u16, retry_after: Option<&str>) -> Result<(), NekosBestError> {
+    match status {
+        200..=299 => Ok(()),
+        429 => Err(NekosBestError::RateLimited {
+            retry_after: retry_after.and_then(parse_retry_after),
+        }),
+        status => Err(NekosBestError::Status(status)),
+    }
+}
+
+/// Parses the value of a `Retry-After` header, either a number of
+/// seconds or an HTTP date.
+pub fn parse_retry_after(value: &str) -> Option<std::time::Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse() {
+        return Some(std::time::Duration::from_secs(seconds));
+    }
+
+    let at = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    Some(
+        (at.with_timezone(&chrono::Utc) - chrono::Utc::now())
+            .to_std()
+            .unwrap_or_default(),
+    )
+}
+
+/// Parses a response body into `T`.
+pub fn parse_body<T: DeserializeOwned>(body: &[u8]) -> Result<T, NekosBestError> {
+    #[cfg(nekosbest_dbgjson)]
+    dbg!(String::from_utf8_lossy(body));
+
+    Ok(serde_json::from_slice(body)?)
+}
+
+/// How many characters of a body [`body_snippet`] keeps.
+pub const BODY_SNIPPET_LEN: usize = 200;
+
+/// The start of a response body, for error messages: its first
+/// [`BODY_SNIPPET_LEN`] characters, with invalid UTF-8 replaced.
+pub fn body_snippet(body: &[u8]) -> String {
+    let body = String::from_utf8_lossy(body);
+    match body.char_indices().nth(BODY_SNIPPET_LEN) {
+        Some((end, _)) => format!("{}...", &body[..end]),
+        None => body.into_owned(),
+    }
+}
+
+/// Parses the body of a response from a category endpoint.
+pub fn parse_response(body: &[u8]) -> Result<NekosBestResponse, NekosBestError> {
+    parse_body(body)
+}
+
+/// Parses the body of a response from a category endpoint, when
+/// only a single image was requested.
+///
+/// # Errors
+/// [`NekosBestError::NotFound`] if the response contains no images.
+pub fn parse_response_single(body: &[u8]) -> Result<NekosBestResponseSingle, NekosBestError> {
+    parse_response(body)?
+        .0
+        .pop()
+        .ok_or(NekosBestError::NotFound)
+}
+
+/// An error getting or decoding the value of a header.
+///
+/// Transports convert their own header errors into it, e.g. a
+/// [`MissingHeader`](HeaderDeserializeUrlEncodedError::MissingHeader) for
+/// a header that isn't there.
+#[derive(Debug, thiserror::Error)]
+pub enum HeaderDeserializeUrlEncodedError {
+    #[error("Missing header")]
+    MissingHeader,
+    #[error("Not ASCII header")]
+    NotAsciiHeader,
+    #[error("UTF8 error")]
+    Utf8(#[from] std::string::FromUtf8Error),
+}
+
+/// Decodes the value of an url-encoded header, like the details
+/// headers sent along with the images.
+pub fn decode_header_value(value: &str) -> Result<String, HeaderDeserializeUrlEncodedError> {
+    Ok(urlencoding::decode(value)?.replace('+', " "))
+}
+
+/// Parses the [`ImageDetails`] from the headers sent along with an image.
+///
+/// `header` looks up the (still url-encoded) value of a header by name.
+/// The `source_url` header is optional.
+pub fn parse_image_details<'a>(
+    header: impl Fn(&str) -> Result<&'a str, HeaderDeserializeUrlEncodedError>,
+) -> Result<ImageDetails, NekosBestError> {
+    let source = match header("source_url") {
+        Ok(url) => Some(SourceInfo {
+            url: decode_header_value(url)?.parse()?,
+        }),
+        Err(HeaderDeserializeUrlEncodedError::MissingHeader) => None,
+        Err(e) => return Err(e.into()),
+    };
+
+    Ok(ImageDetails {
+        artist: ArtistInfo {
+            name: decode_header_value(header("artist_name")?)?,
+            href: decode_header_value(header("artist_href")?)?.parse()?,
+        },
+        source,
+    })
+}
+
+/// Parses the [`GifDetails`] from the headers sent along with a gif.
+///
+/// `header` looks up the (still url-encoded) value of a header by name.
+pub fn parse_gif_details<'a>(
+    header: impl Fn(&str) -> Result<&'a str, HeaderDeserializeUrlEncodedError>,
+) -> Result<GifDetails, NekosBestError> {
+    Ok(GifDetails {
+        anime_name: decode_header_value(header("anime_name")?)?,
+    })
+}
+
+/// The state of a rate limit, as advertised by the server.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct RatelimitInfo {
+    /// How many requests are left until the reset.
+    pub remaining: u32,
+    /// When the rate limit resets.
+    pub resets_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Parses the values of the `X-Rate-Limit-Remaining` and
+/// `X-Rate-Limit-Reset` headers.
+pub fn parse_ratelimit(remaining: &str, reset: &str) -> Option<RatelimitInfo> {
+    Some(RatelimitInfo {
+        remaining: remaining.parse().ok()?,
+        resets_at: reset.parse().ok()?,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::SearchQuery;
+    use crate::SearchQueryKind;
+
+    const NEKO_BODY: &str = r#"{"results":[{"artist_href":"https://www.pixiv.net/en/users/1","artist_name":"someone","source_url":"https://www.pixiv.net/en/artworks/2","url":"https://nekos.best/api/v2/neko/a.png"}]}"#;
+    const PAT_BODY: &str = r#"{"results":[{"anime_name":"Senko","url":"https://nekos.best/api/v2/pat/a.gif"},{"anime_name":"Other","url":"https://nekos.best/api/v2/pat/b.gif"}]}"#;
+
+    #[test]
+    fn urls() {
+        assert_eq!(
+            category_url(crate::BASE_URL, Category::Neko),
+            "https://nekos.best/api/v2/neko"
+        );
+        assert_eq!(
+            category_amount_url("http://localhost", Category::ThumbsUp, 5),
+            "http://localhost/thumbsup?amount=5"
+        );
+        let custom = CustomCategory::new("new-category");
+        assert_eq!(
+            custom_category_url("http://localhost/", &custom),
+            "http://localhost/new-category"
+        );
+        assert_eq!(
+            custom_category_amount_url("http://localhost", &custom, 5),
+            "http://localhost/new-category?amount=5"
+        );
+        assert_eq!(
+            search_url(
+                "http://localhost",
+                &SearchQuery::new("senko san", SearchQueryKind::Gif).category(Category::Pat)
+            ),
+            "http://localhost/search?query=senko+san&type=2&category=pat"
+        );
+    }
+
+    #[test]
+    fn amounts() {
+        assert_eq!(check_amount(1).unwrap(), 1);
+        assert_eq!(check_amount(crate::MAX_AMOUNT).unwrap(), crate::MAX_AMOUNT);
+        for amount in [0, crate::MAX_AMOUNT + 1] {
+            assert!(matches!(
+                check_amount(amount),
+                Err(NekosBestError::InvalidAmount { .. })
+            ));
+        }
+    }
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn http() {
+        let request = build_request("http://localhost", Category::Pat, Some(2)).unwrap();
+        assert_eq!(request.uri(), "http://localhost/pat?amount=2");
+        assert_eq!(
+            request.headers()[http::header::USER_AGENT],
+            crate::USER_AGENT
+        );
+        assert_eq!(
+            build_request("http://localhost", Category::Pat, None)
+                .unwrap()
+                .uri(),
+            "http://localhost/pat"
+        );
+        assert!(matches!(
+            build_request("http://localhost", Category::Pat, Some(0)),
+            Err(NekosBestError::InvalidAmount { .. })
+        ));
+
+        let response = http::Response::new(PAT_BODY.as_bytes());
+        assert_eq!(parse_http_response(&response).unwrap().len(), 2);
+        let response = http::Response::builder()
+            .status(429)
+            .header(http::header::RETRY_AFTER, "3")
+            .body(Vec::new())
+            .unwrap();
+        assert!(matches!(
+            parse_http_response(&response),
+            Err(NekosBestError::RateLimited {
+                retry_after: Some(retry_after)
+            }) if retry_after.as_secs() == 3
+        ));
+    }
+
+    #[test]
+    fn file_ids() {
+        assert_eq!(
+            file_id("https://nekos.best/api/v2/neko/1efcda2d-d0d3-4e96-9b40-86852374b4bc.png"),
+            "1efcda2d-d0d3-4e96-9b40-86852374b4bc"
+        );
+        assert_eq!(file_id("http://localhost/pat/0001.gif?x=1.2"), "0001");
+        assert_eq!(file_id("noextension"), "noextension");
+    }
+
+    #[test]
+    fn parse_image() {
+        let resp = parse_response_single(NEKO_BODY.as_bytes()).unwrap();
+        assert_eq!(resp.url, "https://nekos.best/api/v2/neko/a.png");
+        assert_eq!(resp.details.as_image().unwrap().artist.name, "someone");
+
+        let no_source = parse_response_single(
+            br#"{"results":[{"artist_href":"https://example.com/a","artist_name":"a","url":"https://nekos.best/api/v2/neko/b.png"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(no_source.details.as_image().unwrap().source, None);
+    }
+
+    #[test]
+    fn parse_gif() {
+        let resp = parse_response(PAT_BODY.as_bytes()).unwrap();
+        assert_eq!(resp.len(), 2);
+        assert_eq!(resp[1].details.as_gif().unwrap().anime_name, "Other");
+    }
+
+    #[test]
+    fn parse_empty_and_malformed() {
+        assert!(matches!(
+            parse_response_single(br#"{"results":[]}"#),
+            Err(NekosBestError::NotFound)
+        ));
+        assert!(matches!(
+            parse_response(b"{\"results\":"),
+            Err(NekosBestError::Decoding(_))
+        ));
+    }
+
+    #[test]
+    fn decoding_context() {
+        assert_eq!(body_snippet(b"<html>"), "<html>");
+        let long = "é".repeat(BODY_SNIPPET_LEN + 1);
+        assert_eq!(
+            body_snippet(long.as_bytes()),
+            format!("{}...", "é".repeat(BODY_SNIPPET_LEN))
+        );
+
+        let body = b"<html>bad gateway</html>";
+        let err = parse_response(body).unwrap_err();
+        let err = err.with_response("http://localhost/neko", 200, body);
+        assert!(matches!(
+            &err,
+            NekosBestError::DecodingResponse { body, .. } if body == "<html>bad gateway</html>"
+        ));
+        assert_eq!(err.status(), Some(200));
+        assert_eq!(err.url(), Some("http://localhost/neko"));
+        assert!(err.to_string().contains("http://localhost/neko"));
+    }
+
+    #[test]
+    fn details_headers() {
+        let headers = |name: &str| match name {
+            "artist_name" => Ok("some+one%21"),
+            "artist_href" => Ok("https%3A%2F%2Fexample.com%2Fartist"),
+            "source_url" => Ok("https://example.com/source"),
+            "anime_name" => Ok("Sewayaki+Kitsune"),
+            _ => Err(HeaderDeserializeUrlEncodedError::MissingHeader),
+        };
+
+        let image = parse_image_details(headers).unwrap();
+        assert_eq!(image.artist.name, "some one!");
+        assert_eq!(image.artist.href.as_str(), "https://example.com/artist");
+        assert_eq!(
+            image.source.unwrap().url.as_str(),
+            "https://example.com/source"
+        );
+        assert_eq!(
+            parse_gif_details(headers).unwrap().anime_name,
+            "Sewayaki Kitsune"
+        );
+        assert!(matches!(
+            parse_image_details(|_| Err(HeaderDeserializeUrlEncodedError::MissingHeader)),
+            Err(NekosBestError::DecodingHeader(_))
+        ));
+    }
+
+    #[test]
+    fn retry_after() {
+        assert_eq!(
+            parse_retry_after("120"),
+            Some(std::time::Duration::from_secs(120))
+        );
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"),
+            Some(std::time::Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("soon"), None);
+        assert!(matches!(
+            check_status(429, Some("5")),
+            Err(NekosBestError::RateLimited { retry_after: Some(d) }) if d.as_secs() == 5
+        ));
+        assert!(check_status(204, None).is_ok());
+    }
+
+    #[test]
+    fn ratelimit() {
+        let info = parse_ratelimit("3", "2023-04-24T10:00:00Z").unwrap();
+        assert_eq!(info.remaining, 3);
+        assert_eq!(info.resets_at.to_rfc3339(), "2023-04-24T10:00:00+00:00");
+        assert_eq!(parse_ratelimit("x", "2023-04-24T10:00:00Z"), None);
+        assert_eq!(parse_ratelimit("3", "tomorrow"), None);
+    }
+}
@@ -0,0 +1,286 @@
+//! Posting images on a schedule, e.g. for "daily neko" channels.
+
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+#[cfg(feature = "blocking")]
+use nb_blocking_util::blocking;
+
+use crate::client::Client;
+use crate::rt::Instant;
+use crate::webhook::{post_payload, slack_payload, webhook_payload};
+use crate::{Category, NekosBestError, NekosBestResponseSingle};
+
+pub type SinkCallback = Arc<dyn Fn(Category, &NekosBestResponseSingle) + Send + Sync>;
+
+/// Where a [`Scheduler`] delivers the images.
+#[derive(Clone)]
+#[non_exhaustive]
+pub enum Sink {
+    /// Calls the callback with every image.
+    Callback(SinkCallback),
+    /// Posts the images to a Discord webhook, see [`crate::webhook::post_to_webhook`].
+    DiscordWebhook(String),
+    /// Posts the images to a Slack incoming webhook, see
+    /// [`crate::webhook::post_to_slack_webhook`].
+    SlackWebhook(String),
+}
+
+impl Sink {
+    pub fn callback(
+        f: impl Fn(Category, &NekosBestResponseSingle) + Send + Sync + 'static,
+    ) -> Self {
+        Self::Callback(Arc::new(f))
+    }
+}
+
+struct Job {
+    schedule: cron::Schedule,
+    category: Category,
+    sink: Sink,
+    next: Option<DateTime<Utc>>,
+}
+
+type ErrorCallback = Box<dyn Fn(Category, &NekosBestError) + Send + Sync>;
+
+/// Posts images from some categories on cron schedules.
+///
+/// ```no_run
+/// # use nekosbest::{client::{Client, ClientConfig}, scheduler::{Scheduler, Sink}, Category};
+/// # async fn f() -> Result<(), nekosbest::NekosBestError> {
+/// let mut scheduler = Scheduler::new(Client::new(ClientConfig::default()));
+/// scheduler.add(
+///     "0 0 9 * * *",
+///     Category::Neko,
+///     Sink::DiscordWebhook("https://discord.com/api/webhooks/...".to_owned()),
+/// )?;
+/// scheduler.run().await;
+/// # Ok(())
+/// # }
+/// ```
+pub struct Scheduler {
+    client: Client,
+    jobs: Vec<Job>,
+    max_retries: u32,
+    retry_delay: Duration,
+    on_error: Option<ErrorCallback>,
+}
+
+impl Scheduler {
+    pub fn new(client: Client) -> Self {
+        Self {
+            client,
+            jobs: Vec::new(),
+            max_retries: 3,
+            retry_delay: Duration::from_secs(5),
+            on_error: None,
+        }
+    }
+
+    /// How many times delivering an image is retried before giving up
+    /// until the next run of the job. Defaults to 3.
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// The delay before the first retry, doubled for every retry after
+    /// that. Defaults to 5 seconds.
+    ///
+    /// Retries after [`NekosBestError::RateLimited`] wait for the
+    /// `retry_after` of the error instead, when the API sent one.
+    pub fn retry_delay(mut self, retry_delay: Duration) -> Self {
+        self.retry_delay = retry_delay;
+        self
+    }
+
+    /// Sets a callback invoked when delivering an image failed even
+    /// after retrying.
+    pub fn on_error(
+        mut self,
+        f: impl Fn(Category, &NekosBestError) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_error = Some(Box::new(f));
+        self
+    }
+
+    /// Adds a job posting an image from `category` to `sink` on the
+    /// schedule described by the cron expression `cron`, with seconds,
+    /// e.g. `"0 0 9 * * *"` for every day at 09:00 UTC.
+    ///
+    /// # Errors
+    /// [`NekosBestError::InvalidCronExpression`] if `cron` can't be parsed.
+    pub fn add(
+        &mut self,
+        cron: &str,
+        category: impl Into<Category>,
+        sink: Sink,
+    ) -> Result<&mut Self, NekosBestError> {
+        let schedule = cron::Schedule::from_str(cron)?;
+        let next = schedule.upcoming(Utc).next();
+        self.jobs.push(Job {
+            schedule,
+            category: category.into(),
+            sink,
+            next,
+        });
+        Ok(self)
+    }
+
+    /// When the next job is due, if any.
+    pub fn next_run(&self) -> Option<DateTime<Utc>> {
+        self.jobs.iter().filter_map(|job| job.next).min()
+    }
+
+    /// Runs the jobs as they become due, forever, or until no job has
+    /// any upcoming run.
+    #[cfg_attr(feature = "blocking", blocking)]
+    pub async fn run(mut self) {
+        while let Some(next) = self.next_run() {
+            let wait = (next - Utc::now()).to_std().unwrap_or_default();
+            crate::rt::sleep_until(Instant::now() + wait).await;
+            self.run_pending().await;
+        }
+    }
+
+    /// Runs the jobs that are due now, once.
+    #[cfg_attr(feature = "blocking", blocking)]
+    pub async fn run_pending(&mut self) {
+        let now = Utc::now();
+        for i in 0..self.jobs.len() {
+            match self.jobs[i].next {
+                Some(next) if next <= now => {}
+                _ => continue,
+            }
+
+            let job = &self.jobs[i];
+            if let Err(e) = self.deliver_with_retries(job.category, &job.sink).await {
+                if let Some(on_error) = &self.on_error {
+                    on_error(job.category, &e);
+                }
+            }
+
+            let job = &mut self.jobs[i];
+            job.next = job.schedule.after(&now).next();
+        }
+    }
+
+    #[cfg_attr(feature = "blocking", blocking)]
+    async fn deliver_with_retries(
+        &self,
+        category: Category,
+        sink: &Sink,
+    ) -> Result<(), NekosBestError> {
+        let mut delay = self.retry_delay;
+        let mut attempt = 0;
+        loop {
+            match deliver(&self.client, category, sink).await {
+                Ok(()) => return Ok(()),
+                Err(e) if attempt >= self.max_retries || !e.is_retryable() => return Err(e),
+                Err(e) => {
+                    let wait = match e {
+                        NekosBestError::RateLimited {
+                            retry_after: Some(retry_after),
+                        } => retry_after,
+                        _ => delay,
+                    };
+                    crate::rt::sleep_until(Instant::now() + wait).await;
+                    delay *= 2;
+                    attempt += 1;
+                }
+            }
+        }
+    }
+}
+
+/// Delivers a new image from `category` to `sink`, going around the cache
+/// of the client, which would otherwise deliver the same image every time.
+#[cfg_attr(feature = "blocking", blocking)]
+async fn deliver(client: &Client, category: Category, sink: &Sink) -> Result<(), NekosBestError> {
+    let mut response = crate::implementation::fetch(client, category, None).await?;
+    let response = response.0.pop().ok_or(NekosBestError::NotFound)?;
+    match sink {
+        Sink::Callback(f) => f(category, &response),
+        Sink::DiscordWebhook(url) => {
+            post_payload(client, url.as_str(), &webhook_payload(category, &response)).await?
+        }
+        Sink::SlackWebhook(url) => {
+            post_payload(client, url.as_str(), &slack_payload(category, &response)).await?
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::client::ClientConfig;
+
+    #[test]
+    fn add_jobs() {
+        let mut scheduler = Scheduler::new(Client::new(ClientConfig::default()));
+        assert_eq!(scheduler.next_run(), None);
+        assert!(matches!(
+            scheduler.add("every day", Category::Neko, Sink::callback(|_, _| {})),
+            Err(NekosBestError::InvalidCronExpression(_))
+        ));
+
+        scheduler
+            .add("0 0 9 * * *", Category::Neko, Sink::callback(|_, _| {}))
+            .unwrap()
+            .add("0 30 * * * *", Category::Pat, Sink::callback(|_, _| {}))
+            .unwrap();
+        let next = scheduler.next_run().unwrap();
+        assert!(next > Utc::now());
+        assert!(next - Utc::now() <= chrono::Duration::hours(1));
+    }
+
+    #[cfg(all(feature = "test-util", not(feature = "blocking")))]
+    #[tokio::test]
+    async fn deliver_with_cache() {
+        let server = crate::test_util::MockServer::start().unwrap();
+        let client = Client::builder()
+            .base_url(server.base_url())
+            .cache(Duration::from_secs(60), 16)
+            .build()
+            .unwrap();
+        let urls = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = Sink::callback({
+            let urls = Arc::clone(&urls);
+            move |_, image| urls.lock().unwrap().push(image.url.clone())
+        });
+
+        for _ in 0..2 {
+            deliver(&client, Category::Neko, &sink).await.unwrap();
+        }
+        let urls = urls.lock().unwrap();
+        assert_eq!(urls.len(), 2);
+        assert_ne!(urls[0], urls[1]);
+    }
+
+    #[cfg(all(feature = "test-util", not(feature = "blocking")))]
+    #[tokio::test]
+    async fn retry_after_rate_limited() {
+        use crate::test_util::Fault;
+
+        let server = crate::test_util::MockServer::start().unwrap();
+        let scheduler = Scheduler::new(server.client()).retry_delay(Duration::from_secs(3600));
+        server.fail_next(
+            1,
+            Fault::RateLimited {
+                retry_after: Some(0),
+            },
+        );
+
+        let delivered = tokio::time::timeout(
+            Duration::from_secs(5),
+            scheduler.deliver_with_retries(Category::Neko, &Sink::callback(|_, _| {})),
+        )
+        .await
+        .expect("waited for `retry_delay` instead of `retry_after`");
+        delivered.unwrap();
+        assert_eq!(server.requests(), 2);
+    }
+}
@@ -0,0 +1,53 @@
+//! The queries of the search endpoint, see [`crate::sans_io::search_url`].
+
+use serde::Serializer;
+
+use crate::Category;
+
+#[derive(serde::Serialize, Debug, Clone)]
+pub struct SearchQuery {
+    query: String,
+    #[serde(rename = "type")]
+    kind: SearchQueryKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    category: Option<Category>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    amount: Option<usize>,
+}
+
+impl SearchQuery {
+    pub fn new(query: impl Into<String>, kind: impl Into<SearchQueryKind>) -> Self {
+        Self {
+            query: query.into(),
+            kind: kind.into(),
+            category: None,
+            amount: None,
+        }
+    }
+
+    pub fn category(mut self, category: impl Into<Category>) -> Self {
+        self.category = Some(category.into());
+        self
+    }
+
+    pub fn amount(mut self, amount: usize) -> Self {
+        self.amount = Some(amount);
+        self
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+#[repr(u32)]
+pub enum SearchQueryKind {
+    Image = 1,
+    Gif = 2,
+}
+
+impl serde::Serialize for SearchQueryKind {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_u32(*self as u32)
+    }
+}
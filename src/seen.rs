@@ -0,0 +1,295 @@
+//! Remembering the images already delivered, to avoid repeats.
+
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+#[cfg(feature = "blocking")]
+use nb_blocking_util::blocking;
+
+use crate::client::Client;
+use crate::implementation::fetch_amount;
+use crate::storage::Storage;
+use crate::{Category, NekosBestError, NekosBestResponseSingle};
+
+/// The urls of the images already delivered, see [`get_unseen_with_client`].
+///
+/// The tracker forgets the oldest urls beyond its capacity, and the
+/// ones older than its ttl, if it has one. It can be saved to and
+/// loaded from a file or a [`Storage`], to survive restarts.
+#[derive(Debug, Clone)]
+pub struct SeenTracker {
+    capacity: usize,
+    ttl: Option<Duration>,
+    seen: HashMap<String, DateTime<Utc>>,
+    order: VecDeque<String>,
+}
+
+impl Default for SeenTracker {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_CAPACITY)
+    }
+}
+
+impl SeenTracker {
+    /// The capacity of [`SeenTracker::default`].
+    pub const DEFAULT_CAPACITY: usize = 1000;
+
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            ttl: None,
+            seen: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    /// Forgets urls after `ttl`, so they can be delivered again.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.seen.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.seen.is_empty()
+    }
+
+    /// Whether `url` was delivered (and not forgotten yet).
+    pub fn contains(&self, url: &str) -> bool {
+        self.seen
+            .get(url)
+            .is_some_and(|seen_at| !self.is_expired(*seen_at, Utc::now()))
+    }
+
+    /// Records `url` as delivered now.
+    pub fn insert(&mut self, url: impl Into<String>) {
+        self.insert_at(url.into(), Utc::now());
+    }
+
+    fn insert_at(&mut self, url: String, at: DateTime<Utc>) {
+        if self.seen.insert(url.clone(), at).is_some() {
+            self.order.retain(|u| *u != url);
+        }
+        self.order.push_back(url);
+
+        while self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.seen.remove(&oldest);
+            }
+        }
+    }
+
+    fn is_expired(&self, seen_at: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+        self.ttl
+            .and_then(|ttl| chrono::Duration::from_std(ttl).ok())
+            .is_some_and(|ttl| seen_at + ttl <= now)
+    }
+
+    /// Forgets the urls older than the ttl.
+    pub fn prune(&mut self) {
+        let now = Utc::now();
+        let expired = self
+            .order
+            .iter()
+            .take_while(|url| self.is_expired(self.seen[*url], now))
+            .count();
+        for url in self.order.drain(..expired) {
+            self.seen.remove(&url);
+        }
+    }
+
+    /// The seen urls with when they were seen, oldest first.
+    pub fn entries(&self) -> impl Iterator<Item = (&str, DateTime<Utc>)> {
+        self.order.iter().map(|url| (url.as_str(), self.seen[url]))
+    }
+
+    fn to_bytes(&self) -> Result<Vec<u8>, NekosBestError> {
+        let entries = self
+            .entries()
+            .map(|(url, at)| (url, at.timestamp()))
+            .collect::<Vec<_>>();
+        Ok(serde_json::to_vec(&entries)?)
+    }
+
+    fn extend_from_bytes(mut self, bytes: &[u8]) -> Result<Self, NekosBestError> {
+        let entries: Vec<(String, i64)> = serde_json::from_slice(bytes)?;
+        for (url, at) in entries {
+            self.insert_at(url, DateTime::from_timestamp(at, 0).unwrap_or_default());
+        }
+        self.prune();
+        Ok(self)
+    }
+
+    /// Saves the tracker to `path`, as JSON.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), NekosBestError> {
+        std::fs::write(path, self.to_bytes()?)?;
+        Ok(())
+    }
+
+    /// Loads the urls saved with [`SeenTracker::save`] into this tracker.
+    ///
+    /// A missing file is treated like an empty one, so this can be called
+    /// unconditionally on startup.
+    pub fn load(self, path: impl AsRef<Path>) -> Result<Self, NekosBestError> {
+        match std::fs::read(path) {
+            Ok(bytes) => self.extend_from_bytes(&bytes),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(self),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Saves the tracker under `key` in `storage`.
+    pub async fn save_to(&self, storage: &impl Storage, key: &str) -> Result<(), NekosBestError> {
+        storage.set(key, &self.to_bytes()?).await
+    }
+
+    /// Loads the urls saved with [`SeenTracker::save_to`] into this
+    /// tracker. A missing key is treated like an empty tracker.
+    pub async fn load_from(
+        self,
+        storage: &impl Storage,
+        key: &str,
+    ) -> Result<Self, NekosBestError> {
+        match storage.get(key).await? {
+            Some(bytes) => self.extend_from_bytes(&bytes),
+            None => Ok(self),
+        }
+    }
+}
+
+/// How many images [`get_unseen_with_client`] requests at once.
+const BATCH_SIZE: u8 = 20;
+/// How many batches [`get_unseen_with_client`] requests before giving up.
+const MAX_BATCHES: usize = 3;
+
+/// Gets an image from `category` that `tracker` hasn't seen yet, and
+/// records it as seen, with a supplied client.
+///
+/// If every image fetched was already seen, a seen image is returned
+/// anyway, rather than fetching forever from small categories. The
+/// batches go around the cache of the client, which would otherwise
+/// return the same (seen) images every time.
+///
+/// # Errors
+/// Same as [`crate::get_with_client_amount`], and [`NekosBestError::NotFound`]
+/// if the API returned no images at all.
+#[cfg_attr(feature = "blocking", blocking)]
+pub async fn get_unseen_with_client(
+    client: &Client,
+    category: impl Into<Category>,
+    tracker: &mut SeenTracker,
+) -> Result<NekosBestResponseSingle, NekosBestError> {
+    let category = category.into();
+    let mut fallback = None;
+
+    for _ in 0..MAX_BATCHES {
+        let mut batch = fetch_amount(client, category, BATCH_SIZE).await?.0;
+        if let Some(i) = batch.iter().position(|r| !tracker.contains(&r.url)) {
+            let response = batch.swap_remove(i);
+            tracker.insert(response.url.clone());
+            return Ok(response);
+        }
+        fallback = fallback.or(batch.pop());
+    }
+
+    let response = fallback.ok_or(NekosBestError::NotFound)?;
+    tracker.insert(response.url.clone());
+    Ok(response)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn capacity() {
+        let mut tracker = SeenTracker::new(2);
+        tracker.insert("a");
+        tracker.insert("b");
+        tracker.insert("a");
+        tracker.insert("c");
+        assert!(tracker.contains("a"));
+        assert!(!tracker.contains("b"));
+        assert!(tracker.contains("c"));
+        assert_eq!(tracker.len(), 2);
+    }
+
+    #[test]
+    fn ttl() {
+        let mut tracker = SeenTracker::new(10).with_ttl(Duration::from_secs(60));
+        tracker.insert_at("old".to_owned(), Utc::now() - chrono::Duration::minutes(2));
+        tracker.insert("new");
+        assert!(!tracker.contains("old"));
+        assert!(tracker.contains("new"));
+
+        tracker.prune();
+        assert_eq!(tracker.len(), 1);
+    }
+
+    #[test]
+    fn save_and_load() {
+        let path = std::env::temp_dir().join(format!("nekosbest-seen-{}.json", std::process::id()));
+        let mut tracker = SeenTracker::default();
+        tracker.insert("a");
+        tracker.insert("b");
+        tracker.save(&path).unwrap();
+
+        let loaded = SeenTracker::new(1).load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            loaded.entries().map(|(url, _)| url).collect::<Vec<_>>(),
+            ["b"]
+        );
+
+        let missing = SeenTracker::default().load(&path).unwrap();
+        assert!(missing.is_empty());
+    }
+
+    #[tokio::test]
+    async fn storage() {
+        let storage = crate::storage::MemoryStorage::new();
+        let mut tracker = SeenTracker::default();
+        tracker.insert("a");
+        tracker.save_to(&storage, "seen").await.unwrap();
+
+        let loaded = SeenTracker::default()
+            .load_from(&storage, "seen")
+            .await
+            .unwrap();
+        assert!(loaded.contains("a"));
+        let missing = SeenTracker::default()
+            .load_from(&storage, "other")
+            .await
+            .unwrap();
+        assert!(missing.is_empty());
+    }
+
+    #[cfg(all(feature = "test-util", not(feature = "blocking")))]
+    #[tokio::test]
+    async fn unseen_with_cache() {
+        let server = crate::test_util::MockServer::start().unwrap();
+        let client = Client::builder()
+            .base_url(server.base_url())
+            .cache(Duration::from_secs(60), 16)
+            .build()
+            .unwrap();
+        let mut tracker = SeenTracker::default();
+        let cached = crate::get_with_client_amount(&client, Category::Pat, BATCH_SIZE)
+            .await
+            .unwrap();
+        for image in cached.iter() {
+            tracker.insert(image.url.clone());
+        }
+
+        let image = get_unseen_with_client(&client, Category::Pat, &mut tracker)
+            .await
+            .unwrap();
+        assert!(cached.iter().all(|cached| cached.url != image.url));
+        assert_eq!(server.requests(), 2);
+    }
+}
@@ -0,0 +1,66 @@
+//! How random-selection helpers pick among their options.
+
+use rand::Rng;
+
+/// How to pick among a number of options, in the helpers that would
+/// otherwise pick randomly, like [`crate::pack::select_from_pack`] or
+/// [`crate::daily::get_from_range`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub enum Selection {
+    /// Pick randomly.
+    #[default]
+    Random,
+    /// Pick deterministically, based on the given seed.
+    ///
+    /// The same seed always picks the same option for the same number of
+    /// options, across runs, platforms and versions of this crate, so it
+    /// is suitable for tests and reproducible demos.
+    Seeded(u64),
+    /// Pick the option at the given index.
+    Index(usize),
+}
+
+impl Selection {
+    /// Picks an index in `0..len`, or [`None`] if `len` is 0, or the
+    /// index of [`Selection::Index`] is out of bounds.
+    pub fn pick(self, len: u64) -> Option<u64> {
+        if len == 0 {
+            return None;
+        }
+
+        match self {
+            Selection::Random => Some(rand::thread_rng().gen_range(0..len)),
+            Selection::Seeded(seed) => Some(splitmix64(seed) % len),
+            Selection::Index(index) => Some(index as u64).filter(|index| *index < len),
+        }
+    }
+}
+
+/// The SplitMix64 finalizer, spreading similar seeds over the whole range.
+pub(crate) fn splitmix64(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn pick() {
+        assert_eq!(Selection::Random.pick(0), None);
+        assert!(Selection::Random.pick(3).unwrap() < 3);
+
+        assert_eq!(Selection::Index(2).pick(3), Some(2));
+        assert_eq!(Selection::Index(3).pick(3), None);
+
+        assert_eq!(
+            Selection::Seeded(7).pick(1000),
+            Selection::Seeded(7).pick(1000)
+        );
+        // pinned, so that seeded outputs stay stable across versions
+        assert_eq!(splitmix64(0), 0xe220a8397b1dcdaf);
+    }
+}
@@ -0,0 +1,122 @@
+//! The client as a [`tower_service::Service`], to compose it with tower
+//! layers (rate limiting, retries, timeouts, load shedding, ...).
+//!
+//! Only available with the `tower` feature, and not with `blocking`.
+//!
+//! ```no_run
+//! # async fn f() -> Result<(), nekosbest::NekosBestError> {
+//! use nekosbest::{client::Client, service::NekosBestRequest, Category};
+//! use tower_service::Service;
+//!
+//! let mut client = Client::new(Default::default());
+//! std::future::poll_fn(|cx| client.poll_ready(cx)).await?;
+//! let images = client.call(NekosBestRequest::amount(Category::Pat, 3)).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::task::{Context, Poll};
+
+use crate::api::ApiCall;
+use crate::client::Client;
+use crate::{Category, NekosBestError, NekosBestResponse, SearchQuery};
+
+/// A request the [`Client`] service answers with a [`NekosBestResponse`].
+#[derive(Debug, Clone)]
+pub enum NekosBestRequest {
+    /// Images of `category`, like [`crate::get_with_client_amount`], or a
+    /// single one without an `amount`, like [`crate::get_with_client`].
+    Category {
+        category: Category,
+        amount: Option<u8>,
+    },
+    /// A search, like [`crate::search_with_client`].
+    Search(SearchQuery),
+}
+
+impl NekosBestRequest {
+    /// `amount` images of `category`.
+    pub fn amount(category: impl Into<Category>, amount: u8) -> Self {
+        Self::Category {
+            category: category.into(),
+            amount: Some(amount),
+        }
+    }
+}
+
+/// A single image of the category.
+impl From<Category> for NekosBestRequest {
+    fn from(category: Category) -> Self {
+        Self::Category {
+            category,
+            amount: None,
+        }
+    }
+}
+
+impl From<SearchQuery> for NekosBestRequest {
+    fn from(query: SearchQuery) -> Self {
+        Self::Search(query)
+    }
+}
+
+/// Always ready: the limits configured on the client (like
+/// [`crate::client::ClientBuilder::rate_limit`]) still apply, when sending
+/// the requests.
+impl tower_service::Service<NekosBestRequest> for Client {
+    type Response = NekosBestResponse;
+    type Error = NekosBestError;
+    type Future = ApiCall<'static, NekosBestResponse>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: NekosBestRequest) -> Self::Future {
+        let client = self.clone();
+        Box::pin(async move {
+            match req {
+                NekosBestRequest::Category {
+                    category,
+                    amount: None,
+                } => {
+                    let image = crate::get_with_client(&client, category).await?;
+                    Ok(NekosBestResponse(vec![image]))
+                }
+                NekosBestRequest::Category {
+                    category,
+                    amount: Some(amount),
+                } => crate::get_with_client_amount(&client, category, amount).await,
+                NekosBestRequest::Search(query) => crate::search_with_client(&client, query).await,
+            }
+        })
+    }
+}
+
+#[cfg(all(test, feature = "test-util"))]
+mod test {
+    use super::*;
+    use tower_service::Service;
+
+    #[tokio::test]
+    async fn service() {
+        let server = crate::test_util::MockServer::start().unwrap();
+        let mut client = server.client();
+
+        std::future::poll_fn(|cx| client.poll_ready(cx))
+            .await
+            .unwrap();
+        let image = client.call(Category::Hug.into()).await.unwrap();
+        assert_eq!(image.len(), 1);
+        let images = client
+            .call(NekosBestRequest::amount(Category::Neko, 3))
+            .await
+            .unwrap();
+        assert_eq!(images.len(), 3);
+        let found = client
+            .call(SearchQuery::new("senko", crate::SearchQueryKind::Gif).into())
+            .await
+            .unwrap();
+        assert!(!found.is_empty());
+    }
+}
@@ -0,0 +1,205 @@
+//! Pluggable persistence for the crate's stateful helpers.
+//!
+//! [`Storage`] is a small async key-value interface, implemented by
+//! [`MemoryStorage`], and by [`SledStorage`] and [`SqliteStorage`]
+//! behind the `sled` and `sqlite` features respectively. Embedding
+//! applications can implement it on top of their own database.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Mutex;
+
+use crate::NekosBestError;
+
+/// An async key-value store.
+///
+/// Errors of the underlying store should be reported as
+/// [`NekosBestError::Storage`].
+pub trait Storage: Send + Sync {
+    fn get(
+        &self,
+        key: &str,
+    ) -> impl Future<Output = Result<Option<Vec<u8>>, NekosBestError>> + Send;
+
+    fn set(
+        &self,
+        key: &str,
+        value: &[u8],
+    ) -> impl Future<Output = Result<(), NekosBestError>> + Send;
+
+    fn remove(&self, key: &str) -> impl Future<Output = Result<(), NekosBestError>> + Send;
+}
+
+/// A [`Storage`] keeping everything in memory, e.g. for tests.
+#[derive(Debug, Default)]
+pub struct MemoryStorage {
+    entries: Mutex<HashMap<String, Vec<u8>>>,
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Storage for MemoryStorage {
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, NekosBestError> {
+        Ok(self.entries.lock().unwrap().get(key).cloned())
+    }
+
+    async fn set(&self, key: &str, value: &[u8]) -> Result<(), NekosBestError> {
+        self.entries
+            .lock()
+            .unwrap()
+            .insert(key.to_owned(), value.to_owned());
+        Ok(())
+    }
+
+    async fn remove(&self, key: &str) -> Result<(), NekosBestError> {
+        self.entries.lock().unwrap().remove(key);
+        Ok(())
+    }
+}
+
+#[cfg(any(feature = "sled", feature = "sqlite"))]
+fn storage_error(e: impl std::error::Error + Send + Sync + 'static) -> NekosBestError {
+    NekosBestError::Storage(Box::new(e))
+}
+
+/// A [`Storage`] backed by a [`sled`] tree.
+#[cfg(feature = "sled")]
+#[derive(Debug, Clone)]
+pub struct SledStorage {
+    tree: sled::Tree,
+}
+
+#[cfg(feature = "sled")]
+impl SledStorage {
+    pub fn new(tree: sled::Tree) -> Self {
+        Self { tree }
+    }
+
+    /// Opens (or creates) the database at `path`, and stores the entries
+    /// in its default tree.
+    pub fn open(path: impl AsRef<std::path::Path>) -> Result<Self, NekosBestError> {
+        let db = sled::open(path).map_err(storage_error)?;
+        Ok(Self::new(std::ops::Deref::deref(&db).clone()))
+    }
+}
+
+#[cfg(feature = "sled")]
+impl Storage for SledStorage {
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, NekosBestError> {
+        let value = self.tree.get(key).map_err(storage_error)?;
+        Ok(value.map(|v| v.to_vec()))
+    }
+
+    async fn set(&self, key: &str, value: &[u8]) -> Result<(), NekosBestError> {
+        self.tree.insert(key, value).map_err(storage_error)?;
+        Ok(())
+    }
+
+    async fn remove(&self, key: &str) -> Result<(), NekosBestError> {
+        self.tree.remove(key).map_err(storage_error)?;
+        Ok(())
+    }
+}
+
+/// A [`Storage`] backed by a table in a SQLite database.
+#[cfg(feature = "sqlite")]
+#[derive(Debug)]
+pub struct SqliteStorage {
+    conn: Mutex<rusqlite::Connection>,
+}
+
+#[cfg(feature = "sqlite")]
+impl SqliteStorage {
+    /// Uses the `nekosbest_storage` table of `conn`, creating it if needed.
+    pub fn new(conn: rusqlite::Connection) -> Result<Self, NekosBestError> {
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS nekosbest_storage (key TEXT PRIMARY KEY, value BLOB NOT NULL)",
+            [],
+        )
+        .map_err(storage_error)?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    /// Opens (or creates) the database at `path`.
+    pub fn open(path: impl AsRef<std::path::Path>) -> Result<Self, NekosBestError> {
+        Self::new(rusqlite::Connection::open(path).map_err(storage_error)?)
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl Storage for SqliteStorage {
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, NekosBestError> {
+        use rusqlite::OptionalExtension;
+
+        self.conn
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT value FROM nekosbest_storage WHERE key = ?1",
+                [key],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(storage_error)
+    }
+
+    async fn set(&self, key: &str, value: &[u8]) -> Result<(), NekosBestError> {
+        self.conn
+            .lock()
+            .unwrap()
+            .execute(
+                "INSERT OR REPLACE INTO nekosbest_storage (key, value) VALUES (?1, ?2)",
+                rusqlite::params![key, value],
+            )
+            .map_err(storage_error)?;
+        Ok(())
+    }
+
+    async fn remove(&self, key: &str) -> Result<(), NekosBestError> {
+        self.conn
+            .lock()
+            .unwrap()
+            .execute("DELETE FROM nekosbest_storage WHERE key = ?1", [key])
+            .map_err(storage_error)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    async fn roundtrip(storage: &impl Storage) {
+        assert_eq!(storage.get("a").await.unwrap(), None);
+        storage.set("a", b"1").await.unwrap();
+        storage.set("a", b"2").await.unwrap();
+        assert_eq!(storage.get("a").await.unwrap().as_deref(), Some(&b"2"[..]));
+        storage.remove("a").await.unwrap();
+        assert_eq!(storage.get("a").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn memory() {
+        roundtrip(&MemoryStorage::new()).await;
+    }
+
+    #[cfg(feature = "sled")]
+    #[tokio::test]
+    async fn sled() {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        roundtrip(&SledStorage::new(db.open_tree("test").unwrap())).await;
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[tokio::test]
+    async fn sqlite() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        roundtrip(&SqliteStorage::new(conn).unwrap()).await;
+    }
+}
@@ -0,0 +1,86 @@
+//! Endless streams of images, behind the `stream` feature.
+
+use std::borrow::Borrow;
+use std::collections::VecDeque;
+
+use futures::Stream;
+
+use crate::client::{default_client, Client};
+use crate::implementation::fetch_amount;
+use crate::{Category, NekosBestError, NekosBestResponseSingle, MAX_AMOUNT};
+
+/// An endless stream of images from `category`, with a supplied client.
+///
+/// Images are requested lazily, [`MAX_AMOUNT`] at a time, whenever the
+/// previous batch runs out, going around the cache of the client. Errors are yielded as they happen, and the
+/// stream carries on with a new request when polled again, so it never ends.
+///
+/// ```no_run
+/// # use futures::StreamExt;
+/// # async fn f() {
+/// let client = nekosbest::client::Client::new(nekosbest::client::ClientConfig::default());
+/// let nekos = nekosbest::stream::stream_with_client(&client, nekosbest::Category::Neko);
+/// let mut nekos = Box::pin(nekos.take(50));
+/// while let Some(neko) = nekos.next().await {
+///     println!("{}", neko.unwrap().url);
+/// }
+/// # }
+/// ```
+pub fn stream_with_client(
+    client: &Client,
+    category: impl Into<Category>,
+) -> impl Stream<Item = Result<NekosBestResponseSingle, NekosBestError>> + '_ {
+    images(client, category.into())
+}
+
+/// An endless stream of images from `category`, with the default client,
+/// see [`stream_with_client`].
+pub fn stream(
+    category: impl Into<Category>,
+) -> impl Stream<Item = Result<NekosBestResponseSingle, NekosBestError>> {
+    images(default_client(), category.into())
+}
+
+fn images<C: Borrow<Client>>(
+    client: C,
+    category: Category,
+) -> impl Stream<Item = Result<NekosBestResponseSingle, NekosBestError>> {
+    futures::stream::unfold(
+        (client, VecDeque::new()),
+        move |(client, mut buffer)| async move {
+            if buffer.is_empty() {
+                match fetch_amount(client.borrow(), category, MAX_AMOUNT).await {
+                    Ok(response) => buffer.extend(response.0),
+                    Err(e) => return Some((Err(e), (client, buffer))),
+                }
+            }
+
+            let item = buffer.pop_front().ok_or(NekosBestError::NotFound);
+            Some((item, (client, buffer)))
+        },
+    )
+}
+
+#[cfg(all(test, feature = "test-util"))]
+mod test {
+    use super::*;
+    use futures::StreamExt;
+
+    #[tokio::test]
+    async fn with_cache() {
+        let server = crate::test_util::MockServer::start().unwrap();
+        let client = Client::builder()
+            .base_url(server.base_url())
+            .cache(std::time::Duration::from_secs(60), 16)
+            .build()
+            .unwrap();
+
+        let urls = stream_with_client(&client, Category::Pat)
+            .take(25)
+            .map(|image| image.unwrap().url)
+            .collect::<std::collections::HashSet<_>>()
+            .await;
+        assert_eq!(urls.len(), 25);
+        assert_eq!(server.requests(), 2);
+    }
+}
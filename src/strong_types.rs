@@ -1,4 +1,3 @@
-use reqwest::RequestBuilder;
 use std::{
     fmt::Debug,
     ops::{Deref, DerefMut, Index, IndexMut},
@@ -6,7 +5,6 @@ use std::{
 
 use serde::Deserialize;
 
-use crate::client::ReqBuilder;
 use crate::{
     details::{GifDetails, ImageDetails},
     Category,
@@ -140,13 +138,10 @@ impl<C: STCategory> DerefMut for STNekosBestResponse<C> {
     }
 }
 
-#[derive(serde::Serialize)]
 pub struct STNekosBestSearchQuery<C: STCategory> {
     pub(crate) query: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
     pub(crate) amount: Option<usize>,
 
-    #[serde(skip)]
     _phantom: std::marker::PhantomData<C>,
 }
 
@@ -165,10 +160,26 @@ impl<C: STCategory> STNekosBestSearchQuery<C> {
         self
     }
 
-    pub(crate) fn apply_to(&self, r: ReqBuilder) -> ReqBuilder {
-        r.query(self)
-            .query(&[("type", C::SearchQueryType::TYPE)])
-            .query(&[("category", C::CATEGORY.to_url_path())])
+    pub(crate) fn search_url(&self, base_url: &str) -> String {
+        #[derive(serde::Serialize)]
+        struct Query<'a> {
+            query: &'a str,
+            #[serde(rename = "type")]
+            kind: i32,
+            category: Category,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            amount: Option<usize>,
+        }
+
+        crate::sans_io::search_url(
+            base_url,
+            &Query {
+                query: &self.query,
+                kind: C::SearchQueryType::TYPE,
+                category: C::CATEGORY,
+                amount: self.amount,
+            },
+        )
     }
 }
 
@@ -1,10 +1,13 @@
 #[cfg(feature = "blocking")]
 use nb_blocking_util::blocking;
 
-use crate::client::{Client, ClientConfig};
+use super::{check_status, response_body, ResponseBody};
+use crate::backend::HttpRequest;
+use crate::client::{default_client, Client};
 use crate::{
+    sans_io,
     strong_types::{STCategory, STNekosBestResponse, STNekosBestResponseSingle},
-    NekosBestError, STNekosBestSearchQuery, BASE_URL,
+    NekosBestError, STNekosBestSearchQuery,
 };
 
 /// Gets a single image, with a supplied client.
@@ -15,41 +18,26 @@ use crate::{
 pub async fn get_with_client<C: STCategory>(
     client: &Client,
 ) -> Result<STNekosBestResponseSingle<C>, NekosBestError> {
-    let r = client
-        .client
-        .get(format!("{BASE_URL}/{}", C::CATEGORY))
-        .send()
-        .await?;
-
-    let mut resp = r
-        .error_for_status()?
-        .json::<STNekosBestResponse<C>>()
-        .await?;
+    let url = sans_io::category_url(client.base_url(), C::CATEGORY);
+    let mut resp: STNekosBestResponse<C> = response_body(client, url).await?.parse()?;
     let resp = resp.0.pop().ok_or(NekosBestError::NotFound)?;
 
     Ok(resp)
 }
 
 /// Gets `amount` images, with a supplied client.
-/// Note that the server clamps the amount to the 1..=20 range
 ///
 /// # Errors
-/// Any errors that can happen, refer to [`NekosBestError`].
+/// Same as [`crate::get_with_client_amount`].
 #[cfg_attr(feature = "blocking", blocking)]
 pub async fn get_with_client_amount<C: STCategory>(
     client: &Client,
     amount: impl Into<u8>,
 ) -> Result<STNekosBestResponse<C>, NekosBestError> {
-    let req = client
-        .client
-        .get(format!("{BASE_URL}/{}", C::CATEGORY))
-        .query(&[("amount", amount.into())]);
-
-    let r = req.send().await?;
+    let amount = client.validate_amount(C::CATEGORY, amount.into())?;
 
-    let v = r.error_for_status()?.json().await?;
-
-    Ok(v)
+    let url = sans_io::category_amount_url(client.base_url(), C::CATEGORY, amount);
+    response_body(client, url).await?.parse()
 }
 
 /// Gets a single image, with the default client.
@@ -58,7 +46,7 @@ pub async fn get_with_client_amount<C: STCategory>(
 /// Any errors that can happen, refer to [`NekosBestError`].
 #[cfg_attr(feature = "blocking", blocking)]
 pub async fn get<C: STCategory>() -> Result<STNekosBestResponseSingle<C>, NekosBestError> {
-    get_with_client(&Client::new(ClientConfig::default())).await
+    get_with_client(&default_client()).await
 }
 
 /// Gets `amount` images, with the default client.
@@ -69,7 +57,7 @@ pub async fn get<C: STCategory>() -> Result<STNekosBestResponseSingle<C>, NekosB
 pub async fn get_amount<C: STCategory>(
     amount: impl Into<u8>,
 ) -> Result<STNekosBestResponse<C>, NekosBestError> {
-    get_with_client_amount(&Client::new(ClientConfig::default()), amount).await
+    get_with_client_amount(&default_client(), amount).await
 }
 
 #[cfg_attr(feature = "blocking", blocking)]
@@ -77,19 +65,15 @@ pub async fn search_with_client<C: STCategory>(
     client: &Client,
     query: STNekosBestSearchQuery<C>,
 ) -> Result<STNekosBestResponse<C>, NekosBestError> {
-    #[cfg(not(feature = "blocking"))]
     client.handle_search_ratelimit().await?;
 
-    let req = client.client.get(format!("{BASE_URL}/search"));
-
-    let req = query.apply_to(req);
+    let url = query.search_url(client.base_url());
 
-    let res = req.send().await?;
+    let res = client.send_http(HttpRequest::get(url.as_str())).await?;
 
-    #[cfg(not(feature = "blocking"))]
-    client.update_search_ratelimit_data(res.headers()).await;
+    client.update_search_ratelimit_data(&res);
 
-    Ok(res.error_for_status()?.json().await?)
+    ResponseBody::new(url, check_status(res)?).parse()
 }
 
 #[deprecated(
@@ -100,5 +84,5 @@ pub async fn search_with_client<C: STCategory>(
 pub async fn search<C: STCategory>(
     query: STNekosBestSearchQuery<C>,
 ) -> Result<STNekosBestResponse<C>, NekosBestError> {
-    search_with_client(&Client::new(ClientConfig::default()), query).await
+    search_with_client(&default_client(), query).await
 }
@@ -0,0 +1,155 @@
+//! Metrics about the usage of the API, through the [`metrics`] facade,
+//! with the `metrics-facade` feature.
+//!
+//! Emitted:
+//! - `nekosbest_requests_total` (counter, by `status`, `0` for requests
+//!   that got no response), for every request sent, including retries;
+//! - `nekosbest_request_duration_seconds` (histogram), for every request
+//!   sent;
+//! - `nekosbest_errors_total` (counter, by `kind`, see [`error_kind`]);
+//! - `nekosbest_cache_hits_total` (counter, by `cache`, `memory` or
+//!   `disk`), and `nekosbest_cache_misses_total` (counter) when the client
+//!   has a cache;
+//! - `nekosbest_response_bytes` (histogram), the size of the bodies
+//!   read.
+
+use std::time::Duration;
+
+use crate::NekosBestError;
+
+/// The `kind` label of `nekosbest_errors_total` for `e`.
+pub(crate) fn error_kind(e: &NekosBestError) -> &'static str {
+    match e {
+        NekosBestError::ReqwestError(e) if e.is_timeout() => "timeout",
+        NekosBestError::ReqwestError(e) if e.status().is_some() => "status",
+        NekosBestError::ReqwestError(_) => "network",
+        NekosBestError::Status(_) => "status",
+        NekosBestError::NotFound => "not_found",
+        NekosBestError::Decoding(_) | NekosBestError::DecodingResponse { .. } => "decoding",
+        NekosBestError::RateLimited { .. } => "rate_limited",
+        NekosBestError::CircuitOpen { .. } => "circuit_open",
+        NekosBestError::InvalidAmount { .. } => "invalid_amount",
+        #[cfg(all(feature = "coalesce", not(feature = "blocking")))]
+        NekosBestError::Coalesced(e) => error_kind(e),
+        _ => "other",
+    }
+}
+
+pub(crate) fn record_request(status: Option<u16>, latency: Duration) {
+    let status = status.unwrap_or(0).to_string();
+    metrics::counter!("nekosbest_requests_total", "status" => status).increment(1);
+    metrics::histogram!("nekosbest_request_duration_seconds").record(latency.as_secs_f64());
+}
+
+pub(crate) fn record_error(e: &NekosBestError) {
+    metrics::counter!("nekosbest_errors_total", "kind" => error_kind(e)).increment(1);
+}
+
+pub(crate) fn record_cache_hit(cache: &'static str) {
+    metrics::counter!("nekosbest_cache_hits_total", "cache" => cache).increment(1);
+}
+
+pub(crate) fn record_cache_miss() {
+    metrics::counter!("nekosbest_cache_misses_total").increment(1);
+}
+
+pub(crate) fn record_bytes(bytes: usize) {
+    metrics::histogram!("nekosbest_response_bytes").record(bytes as f64);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    use metrics::{
+        Counter, Gauge, Histogram, Key, KeyName, Metadata, Recorder, SharedString, Unit,
+    };
+
+    /// Keeps the counters, by name and labels.
+    #[derive(Default)]
+    struct Counters(Mutex<HashMap<String, Arc<AtomicU64>>>);
+
+    impl Counters {
+        fn get(&self, key: &str) -> u64 {
+            let counters = self.0.lock().unwrap();
+            counters.get(key).map_or(0, |c| c.load(Ordering::SeqCst))
+        }
+    }
+
+    struct CounterValue(Arc<AtomicU64>);
+
+    impl metrics::CounterFn for CounterValue {
+        fn increment(&self, value: u64) {
+            self.0.fetch_add(value, Ordering::SeqCst);
+        }
+
+        fn absolute(&self, value: u64) {
+            self.0.store(value, Ordering::SeqCst);
+        }
+    }
+
+    impl Recorder for Counters {
+        fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+        fn register_counter(&self, key: &Key, _: &Metadata<'_>) -> Counter {
+            let name = key.labels().fold(key.name().to_owned(), |name, label| {
+                format!("{name},{}={}", label.key(), label.value())
+            });
+            let value = Arc::clone(self.0.lock().unwrap().entry(name).or_default());
+            Counter::from_arc(Arc::new(CounterValue(value)))
+        }
+
+        fn register_gauge(&self, _: &Key, _: &Metadata<'_>) -> Gauge {
+            Gauge::noop()
+        }
+
+        fn register_histogram(&self, _: &Key, _: &Metadata<'_>) -> Histogram {
+            Histogram::noop()
+        }
+    }
+
+    #[cfg(not(feature = "blocking"))]
+    #[test]
+    fn cache_and_errors() {
+        use crate::{client::Client, Category};
+
+        let counters = Counters::default();
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let client = Client::builder()
+            .base_url("http://127.0.0.1:1")
+            .cache(Duration::from_secs(60), 10)
+            .build()
+            .unwrap();
+        client.cache().unwrap().insert(
+            (Category::Hug, None),
+            crate::sans_io::parse_response(
+                br#"{"results":[{"anime_name":"a","url":"http://localhost/a.gif"}]}"#,
+            )
+            .unwrap(),
+        );
+
+        metrics::with_local_recorder(&counters, || {
+            runtime.block_on(async {
+                crate::get_with_client(&client, Category::Hug)
+                    .await
+                    .unwrap();
+                crate::get_with_client(&client, Category::Pat)
+                    .await
+                    .unwrap_err();
+            })
+        });
+
+        assert_eq!(counters.get("nekosbest_cache_hits_total,cache=memory"), 1);
+        assert_eq!(counters.get("nekosbest_cache_misses_total"), 1);
+        assert_eq!(counters.get("nekosbest_requests_total,status=0"), 1);
+        assert_eq!(counters.get("nekosbest_errors_total,kind=network"), 1);
+    }
+}
@@ -0,0 +1,554 @@
+//! Helpers for testing code using the client, without network access.
+//!
+//! [`MockServer`] is an in-process HTTP server answering like nekos.best,
+//! with made-up images. See also [`crate::vcr`], to replay real responses.
+//!
+//! Only available with the `test-util` feature, and not on wasm32.
+//!
+//! ```no_run
+//! # async fn f() -> Result<(), Box<dyn std::error::Error>> {
+//! use nekosbest::{test_util::MockServer, Category};
+//!
+//! let server = MockServer::start()?;
+//! let image = nekosbest::get_with_client(&server.client(), Category::Pat).await?;
+//! assert!(image.url.starts_with(&server.base_url()));
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use crate::client::Client;
+use crate::endpoints::{ImageFormat, EMBEDDED_ENDPOINTS};
+use crate::Category;
+
+/// The details of every image served.
+pub const ARTIST_NAME: &str = "Mock Artist";
+pub const ARTIST_HREF: &str = "https://example.com/artist";
+pub const SOURCE_URL: &str = "https://example.com/source";
+/// The details of every gif served.
+pub const ANIME_NAME: &str = "Mock Anime";
+
+/// A 1x1 gif, served for every gif.
+const GIF: &[u8] = &[
+    0x47, 0x49, 0x46, 0x38, 0x39, 0x61, 0x01, 0x00, 0x01, 0x00, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0xff, 0xff, 0xff, 0x21, 0xf9, 0x04, 0x01, 0x00, 0x00, 0x00, 0x00, 0x2c, 0x00, 0x00, 0x00, 0x00,
+    0x01, 0x00, 0x01, 0x00, 0x00, 0x02, 0x01, 0x44, 0x00, 0x3b,
+];
+
+/// A 1x1 png, served for every image.
+const PNG: &[u8] = &[
+    0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48, 0x44, 0x52,
+    0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x08, 0x06, 0x00, 0x00, 0x00, 0x1f, 0x15, 0xc4,
+    0x89, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x44, 0x41, 0x54, 0x78, 0xda, 0x63, 0x64, 0x60, 0xf8, 0x5f,
+    0x0f, 0x00, 0x02, 0x87, 0x01, 0x80, 0xeb, 0x47, 0xba, 0x92, 0x00, 0x00, 0x00, 0x00, 0x49, 0x45,
+    0x4e, 0x44, 0xae, 0x42, 0x60, 0x82,
+];
+
+/// The path the API is served under, like on nekos.best.
+const API_PATH: &str = "/api/v2";
+
+/// An HTTP server on localhost answering like nekos.best, until dropped.
+///
+/// It serves:
+/// - the category endpoints, with or without an `amount`, every image
+///   having a new file name;
+/// - the images themselves (a 1x1 png or gif), with their details headers;
+/// - `/endpoints`, listing every [`Category`], with the file ranges of
+///   [`EMBEDDED_ENDPOINTS`];
+/// - `/search`, answering any query with images of the requested kind
+///   and category.
+///
+/// Requests can be answered with a [`Fault`] instead, see
+/// [`MockServer::fail_next`] and [`MockServer::fail_with_probability`].
+pub struct MockServer {
+    addr: SocketAddr,
+    state: Arc<State>,
+    thread: Option<JoinHandle<()>>,
+}
+
+#[derive(Default)]
+struct State {
+    requests: AtomicU64,
+    next_id: AtomicU64,
+    shutdown: AtomicBool,
+    faults: Mutex<VecDeque<Fault>>,
+    random_fault: Mutex<Option<RandomFault>>,
+}
+
+/// A fault answering requests at random, see
+/// [`MockServer::fail_with_probability`].
+struct RandomFault {
+    probability: f64,
+    seed: u64,
+    draws: u64,
+    fault: Fault,
+}
+
+impl RandomFault {
+    /// Whether the next request fails, drawn from the seed with
+    /// [`splitmix64`](crate::selection::splitmix64).
+    fn draw(&mut self) -> Option<Fault> {
+        let x = crate::selection::splitmix64(self.seed.wrapping_add(self.draws));
+        self.draws += 1;
+        // the top 53 bits, as a float in [0, 1)
+        let x = (x >> 11) as f64 / (1u64 << 53) as f64;
+        (x < self.probability).then(|| self.fault.clone())
+    }
+}
+
+/// A failure to answer a request of a [`MockServer`] with, instead of the
+/// response of the API.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Fault {
+    /// `429 Too Many Requests`, with a `Retry-After` header of
+    /// `retry_after` seconds, if set.
+    RateLimited { retry_after: Option<u64> },
+    /// The response, but only after the delay, e.g. for the request to
+    /// time out.
+    Delay(Duration),
+    /// `200 OK`, with a body that isn't valid JSON.
+    MalformedJson,
+    /// Closing the connection without answering.
+    Disconnect,
+}
+
+impl MockServer {
+    /// Starts a server on a free port.
+    ///
+    /// # Errors
+    /// Any I/O error binding the port.
+    pub fn start() -> std::io::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        let state = Arc::new(State::default());
+
+        let thread = std::thread::spawn({
+            let state = Arc::clone(&state);
+            move || {
+                for stream in listener.incoming() {
+                    if state.shutdown.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    let Ok(stream) = stream else { continue };
+                    let state = Arc::clone(&state);
+                    std::thread::spawn(move || {
+                        // The client hanging up is not our problem.
+                        let _ = serve(stream, addr, &state);
+                    });
+                }
+            }
+        });
+
+        Ok(Self {
+            addr,
+            state,
+            thread: Some(thread),
+        })
+    }
+
+    /// The base url of the API served, to pass to
+    /// [`crate::client::ClientBuilder::base_url`].
+    pub fn base_url(&self) -> String {
+        base_url(self.addr)
+    }
+
+    /// A client sending its requests to this server.
+    pub fn client(&self) -> Client {
+        Client::builder()
+            .base_url(self.base_url())
+            .build()
+            .expect("the default client builds")
+    }
+
+    /// How many requests the server answered so far.
+    pub fn requests(&self) -> u64 {
+        self.state.requests.load(Ordering::SeqCst)
+    }
+
+    /// Answers the next `count` requests with `fault`, after the faults
+    /// already scripted, whatever they are for.
+    ///
+    /// ```no_run
+    /// # async fn f() -> Result<(), Box<dyn std::error::Error>> {
+    /// use nekosbest::test_util::{Fault, MockServer};
+    /// use nekosbest::{Category, NekosBestError};
+    ///
+    /// let server = MockServer::start()?;
+    /// server.fail_next(1, Fault::RateLimited { retry_after: Some(2) });
+    /// let result = nekosbest::get_with_client(&server.client(), Category::Pat).await;
+    /// assert!(matches!(result, Err(NekosBestError::RateLimited { .. })));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn fail_next(&self, count: usize, fault: Fault) {
+        let mut faults = self.state.faults.lock().unwrap();
+        faults.extend(std::iter::repeat_n(fault, count));
+    }
+
+    /// Answers every request with `fault` with a probability of
+    /// `probability`, once the faults of [`MockServer::fail_next`]
+    /// are used up, replacing the previous probabilistic fault.
+    ///
+    /// The failures are drawn from `seed`, so a given seed fails the
+    /// same requests on every run. A `probability` of 0 disables it.
+    ///
+    /// # Panics
+    /// If `probability` isn't between 0 and 1.
+    pub fn fail_with_probability(&self, probability: f64, seed: u64, fault: Fault) {
+        assert!(
+            (0.0..=1.0).contains(&probability),
+            "probability {probability} isn't between 0 and 1"
+        );
+        *self.state.random_fault.lock().unwrap() = Some(RandomFault {
+            probability,
+            seed,
+            draws: 0,
+            fault,
+        });
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.state.shutdown.store(true, Ordering::SeqCst);
+        // Wakes up the listener, to see the shutdown flag.
+        let _ = TcpStream::connect(self.addr);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn base_url(addr: SocketAddr) -> String {
+    format!("http://{addr}{API_PATH}")
+}
+
+struct Response {
+    status: &'static str,
+    headers: Vec<(&'static str, String)>,
+    body: Vec<u8>,
+}
+
+impl Response {
+    fn json(body: String) -> Self {
+        Self {
+            status: "200 OK",
+            headers: vec![("content-type", "application/json".to_owned())],
+            body: body.into_bytes(),
+        }
+    }
+
+    fn rate_limited(retry_after: Option<u64>) -> Self {
+        Self {
+            status: "429 Too Many Requests",
+            headers: retry_after
+                .map(|retry_after| ("retry-after", retry_after.to_string()))
+                .into_iter()
+                .collect(),
+            body: b"rate limited".to_vec(),
+        }
+    }
+
+    fn not_found() -> Self {
+        Self {
+            status: "404 Not Found",
+            headers: Vec::new(),
+            body: b"not found".to_vec(),
+        }
+    }
+}
+
+fn serve(stream: TcpStream, addr: SocketAddr, state: &State) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 || line == "\r\n" {
+            break;
+        }
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let (method, target) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+    state.requests.fetch_add(1, Ordering::SeqCst);
+
+    let fault = state.faults.lock().unwrap().pop_front().or_else(|| {
+        state
+            .random_fault
+            .lock()
+            .unwrap()
+            .as_mut()
+            .and_then(RandomFault::draw)
+    });
+    if let Some(Fault::Delay(delay)) = fault {
+        std::thread::sleep(delay);
+    }
+    let response = match (fault, method) {
+        // dropping the stream closes the connection
+        (Some(Fault::Disconnect), _) => return Ok(()),
+        (Some(Fault::RateLimited { retry_after }), _) => Response::rate_limited(retry_after),
+        (Some(Fault::MalformedJson), _) => Response::json(r#"{"results":[{"url":"#.to_owned()),
+        (Some(Fault::Delay(_)) | None, "GET" | "HEAD") => route(target, addr, state),
+        (Some(Fault::Delay(_)) | None, _) => Response::not_found(),
+    };
+
+    let mut stream = reader.into_inner();
+    write!(stream, "HTTP/1.1 {}\r\n", response.status)?;
+    for (name, value) in &response.headers {
+        write!(stream, "{name}: {value}\r\n")?;
+    }
+    write!(
+        stream,
+        "content-length: {}\r\nconnection: close\r\n\r\n",
+        response.body.len()
+    )?;
+    if method != "HEAD" {
+        stream.write_all(&response.body)?;
+    }
+    stream.flush()
+}
+
+fn route(target: &str, addr: SocketAddr, state: &State) -> Response {
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let query = |name: &str| {
+        query
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .find(|(n, _)| *n == name)
+            .map(|(_, v)| v)
+    };
+    let amount = query("amount")
+        .and_then(|amount| amount.parse::<u8>().ok())
+        .map_or(1, |amount| amount.clamp(1, crate::MAX_AMOUNT));
+
+    let Some(path) = path.strip_prefix(API_PATH) else {
+        return Response::not_found();
+    };
+    let segments = path.trim_matches('/').split('/').collect::<Vec<_>>();
+
+    match segments[..] {
+        ["endpoints"] => endpoints(),
+        ["search"] => {
+            let format = match query("type") {
+                Some("2") => ImageFormat::Gif,
+                _ => ImageFormat::Png,
+            };
+            let category = query("category")
+                .and_then(Category::from_url_name)
+                .or_else(|| Category::iter().find(|c| c.format() == format));
+            match category {
+                Some(category) => images(category, amount, addr, state),
+                None => Response::json(r#"{"results":[]}"#.to_owned()),
+            }
+        }
+        [category] => match Category::from_url_name(category) {
+            Some(category) => images(category, amount, addr, state),
+            None => Response::not_found(),
+        },
+        [category, file] => match Category::from_url_name(category) {
+            Some(category) if file.ends_with(&format!(".{}", category.format())) => {
+                image_file(category)
+            }
+            _ => Response::not_found(),
+        },
+        _ => Response::not_found(),
+    }
+}
+
+fn endpoints() -> Response {
+    let endpoints = EMBEDDED_ENDPOINTS
+        .iter()
+        .map(|endpoint| {
+            let mut desc = serde_json::json!({ "format": endpoint.format.extension() });
+            if let (Some(min), Some(max)) = (endpoint.min, endpoint.max) {
+                desc["min"] = min.into();
+                desc["max"] = max.into();
+            }
+            (endpoint.name().to_owned(), desc)
+        })
+        .collect::<serde_json::Map<_, _>>();
+    Response::json(serde_json::Value::Object(endpoints).to_string())
+}
+
+fn images(category: Category, amount: u8, addr: SocketAddr, state: &State) -> Response {
+    let results = (0..amount)
+        .map(|_| {
+            let id = state.next_id.fetch_add(1, Ordering::SeqCst);
+            let url = format!(
+                "{}/{}/00000000-0000-4000-8000-{id:012x}.{}",
+                base_url(addr),
+                category.to_url_name(),
+                category.format()
+            );
+            match category.format() {
+                ImageFormat::Gif => serde_json::json!({
+                    "anime_name": ANIME_NAME,
+                    "url": url,
+                }),
+                _ => serde_json::json!({
+                    "artist_href": ARTIST_HREF,
+                    "artist_name": ARTIST_NAME,
+                    "source_url": SOURCE_URL,
+                    "url": url,
+                }),
+            }
+        })
+        .collect::<Vec<_>>();
+    Response::json(serde_json::json!({ "results": results }).to_string())
+}
+
+fn image_file(category: Category) -> Response {
+    let encode = |value: &str| urlencoding::encode(value).into_owned();
+    let (body, headers) = match category.format() {
+        ImageFormat::Gif => (GIF, vec![("anime_name", encode(ANIME_NAME))]),
+        _ => (
+            PNG,
+            vec![
+                ("artist_name", encode(ARTIST_NAME)),
+                ("artist_href", encode(ARTIST_HREF)),
+                ("source_url", encode(SOURCE_URL)),
+            ],
+        ),
+    };
+
+    let mut response = Response {
+        status: "200 OK",
+        headers,
+        body: body.to_vec(),
+    };
+    response
+        .headers
+        .push(("content-type", category.format().mime_type().to_owned()));
+    response
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[cfg(not(feature = "blocking"))]
+    #[tokio::test]
+    async fn mock_server() {
+        let server = MockServer::start().unwrap();
+        let client = server.client();
+
+        let gif = crate::get_with_client(&client, Category::Pat)
+            .await
+            .unwrap();
+        assert!(gif.url.starts_with(&format!("{}/pat/", server.base_url())));
+        assert_eq!(gif.details.as_gif().unwrap().anime_name, ANIME_NAME);
+        let details = crate::get_with_client_gif_details(&client, &gif.url)
+            .await
+            .unwrap();
+        assert_eq!(details.anime_name, ANIME_NAME);
+
+        let images = crate::get_with_client_amount(&client, Category::Neko, 3)
+            .await
+            .unwrap();
+        assert_eq!(images.len(), 3);
+        assert_ne!(images[0].url, images[1].url);
+        let details = crate::get_with_client_image_details(&client, &images[0].url)
+            .await
+            .unwrap();
+        assert_eq!(details.artist.name, ARTIST_NAME);
+
+        let endpoints = crate::endpoints::get_endpoints_with_client(&client)
+            .await
+            .unwrap();
+        assert_eq!(endpoints.len(), Category::ALL.len());
+        assert_eq!(endpoints["neko"].format, "png");
+
+        let found = crate::search_with_client(
+            &client,
+            crate::SearchQuery::new("senko", crate::SearchQueryKind::Gif).amount(2),
+        )
+        .await
+        .unwrap();
+        assert_eq!(found.len(), 2);
+        assert!(found[0].details.as_gif().is_some());
+
+        assert_eq!(server.requests(), 6);
+    }
+
+    #[cfg(not(feature = "blocking"))]
+    #[tokio::test]
+    async fn rate_limited() {
+        let server = MockServer::start().unwrap();
+        server.fail_next(
+            2,
+            Fault::RateLimited {
+                retry_after: Some(3),
+            },
+        );
+        server.fail_next(1, Fault::RateLimited { retry_after: None });
+        let client = server.client();
+
+        for retry_after in [Some(3), Some(3), None] {
+            assert!(matches!(
+                crate::get_with_client(&client, Category::Pat).await,
+                Err(crate::NekosBestError::RateLimited { retry_after: r })
+                    if r == retry_after.map(std::time::Duration::from_secs)
+            ));
+        }
+        crate::get_with_client(&client, Category::Pat)
+            .await
+            .unwrap();
+    }
+
+    #[cfg(not(feature = "blocking"))]
+    #[tokio::test]
+    async fn faults() {
+        let server = MockServer::start().unwrap();
+        server.fail_next(1, Fault::Delay(Duration::from_secs(2)));
+        server.fail_next(1, Fault::MalformedJson);
+        server.fail_next(1, Fault::Disconnect);
+        let client = server.client().with_timeout(Duration::from_millis(200));
+
+        assert!(matches!(
+            crate::get_with_client(&client, Category::Pat).await,
+            Err(crate::NekosBestError::ReqwestError(e)) if e.is_timeout()
+        ));
+        assert!(matches!(
+            crate::get_with_client(&client, Category::Pat).await,
+            Err(crate::NekosBestError::DecodingResponse { status: 200, .. })
+        ));
+        assert!(matches!(
+            crate::get_with_client(&client, Category::Pat).await,
+            Err(crate::NekosBestError::ReqwestError(_))
+        ));
+        crate::get_with_client(&client, Category::Pat)
+            .await
+            .unwrap();
+    }
+
+    #[cfg(not(feature = "blocking"))]
+    #[tokio::test]
+    async fn fail_with_probability() {
+        let server = MockServer::start().unwrap();
+        server.fail_next(1, Fault::Disconnect);
+        server.fail_with_probability(0.3, 42, Fault::MalformedJson);
+        let client = server.client();
+
+        let mut failures = 0;
+        for _ in 0..101 {
+            if crate::get_with_client(&client, Category::Pat)
+                .await
+                .is_err()
+            {
+                failures += 1;
+            }
+        }
+        // the scripted fault, then 28 of the 100 drawn from the seed,
+        // pinned so that seeded faults stay stable across versions
+        assert_eq!(failures, 1 + 28);
+        assert_eq!(server.requests(), 101);
+    }
+}
@@ -0,0 +1,200 @@
+//! Helpers for building twilight interaction commands taking a
+//! [`Category`], and for paging through responses with buttons.
+
+use twilight_model::application::command::{CommandOptionChoice, CommandOptionChoiceValue};
+use twilight_model::channel::message::component::{ActionRow, Button, ButtonStyle, Component};
+
+use crate::Category;
+
+/// The most choices Discord accepts for a single option, or in a
+/// single autocomplete response.
+pub const MAX_CHOICES: usize = 25;
+
+/// The choice for `category`, with its url name as both the name and
+/// the (string) value, so that the value of the option can be parsed
+/// back with [`FromStr`](std::str::FromStr).
+pub fn category_choice(category: Category) -> CommandOptionChoice {
+    CommandOptionChoice {
+        name: category.to_url_name().to_owned(),
+        name_localizations: None,
+        value: CommandOptionChoiceValue::String(category.to_url_name().to_owned()),
+    }
+}
+
+/// The choices for all the categories, in the order of
+/// [`Category::ALL_VARIANTS`].
+///
+/// There are more categories than [`MAX_CHOICES`], so either register
+/// a subset of them with [`category_choices_for`], or make the option
+/// autocomplete with [`autocomplete_choices`].
+pub fn category_choices() -> Vec<CommandOptionChoice> {
+    category_choices_for(Category::ALL_VARIANTS.iter().copied())
+}
+
+/// The choices for the given categories.
+pub fn category_choices_for(
+    categories: impl IntoIterator<Item = Category>,
+) -> Vec<CommandOptionChoice> {
+    categories.into_iter().map(category_choice).collect()
+}
+
+/// The choices to respond with to an autocomplete interaction for a
+/// category option, given what the user typed so far.
+///
+/// Categories whose name starts with `partial` come first, followed by
+/// those containing it, at most [`MAX_CHOICES`] in total.
+pub fn autocomplete_choices(partial: &str) -> Vec<CommandOptionChoice> {
+    let partial = partial.trim().to_lowercase();
+    let names = || Category::ALL_VARIANTS.iter().copied();
+
+    let prefixed = names().filter(|c| c.to_url_name().starts_with(&partial));
+    let containing = names()
+        .filter(|c| !c.to_url_name().starts_with(&partial) && c.to_url_name().contains(&partial));
+
+    category_choices_for(prefixed.chain(containing).take(MAX_CHOICES))
+}
+
+/// The prefix of the custom ids of the gallery buttons.
+const GALLERY_PREFIX: &str = "nekosbest:gallery";
+
+/// The state of a gallery of images, as encoded in the custom ids of
+/// the buttons created by [`GalleryState::components`].
+///
+/// `key` identifies the gallery for the bot (e.g. the id of the command
+/// invocation), so that it can find the response the gallery pages
+/// through; the images themselves are not encoded, since custom ids are
+/// limited to 100 characters.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GalleryState {
+    pub key: String,
+    pub index: usize,
+    pub len: usize,
+}
+
+impl GalleryState {
+    /// The state of a new gallery, over `len` images and showing the first one.
+    pub fn new(key: impl Into<String>, len: usize) -> Self {
+        Self {
+            key: key.into(),
+            index: 0,
+            len,
+        }
+    }
+
+    /// The state of `gallery`, at its current position.
+    pub fn for_gallery(key: impl Into<String>, gallery: &crate::gallery::Gallery) -> Self {
+        Self {
+            key: key.into(),
+            index: gallery.position(),
+            len: gallery.len(),
+        }
+    }
+
+    /// The image currently shown, from the response the gallery pages through.
+    pub fn current<'a>(
+        &self,
+        response: &'a crate::NekosBestResponse,
+    ) -> Option<&'a crate::NekosBestResponseSingle> {
+        response.get(self.index)
+    }
+
+    /// The gallery buttons, in an action row: previous, the (disabled)
+    /// position, and next. The previous and next buttons are disabled
+    /// at the first and last image respectively.
+    pub fn components(&self) -> Vec<Component> {
+        let button = |id: String, label: String, disabled: bool| {
+            Component::Button(Button {
+                custom_id: Some(id),
+                disabled,
+                emoji: None,
+                label: Some(label),
+                style: ButtonStyle::Secondary,
+                url: None,
+            })
+        };
+
+        let Self { key, index, len } = self;
+        vec![Component::ActionRow(ActionRow {
+            components: vec![
+                button(
+                    format!("{GALLERY_PREFIX}:{key}:{}:{len}", index.saturating_sub(1)),
+                    "◀".to_owned(),
+                    *index == 0,
+                ),
+                button(
+                    format!("{GALLERY_PREFIX}:{key}:pos"),
+                    format!("{}/{len}", index + 1),
+                    true,
+                ),
+                button(
+                    format!("{GALLERY_PREFIX}:{key}:{}:{len}", index + 1),
+                    "▶".to_owned(),
+                    index + 1 >= *len,
+                ),
+            ],
+        })]
+    }
+
+    /// Advances the gallery, from the custom id of the button that was
+    /// pressed, returning the new state.
+    ///
+    /// Returns [`None`] if `custom_id` doesn't belong to a gallery
+    /// button, so bots can route the interaction elsewhere.
+    pub fn from_interaction(custom_id: &str) -> Option<Self> {
+        let rest = custom_id.strip_prefix(GALLERY_PREFIX)?.strip_prefix(':')?;
+        // the key might contain `:` itself, so parse from the end
+        let (rest, len) = rest.rsplit_once(':')?;
+        let (key, index) = rest.rsplit_once(':')?;
+        let (index, len) = (index.parse().ok()?, len.parse().ok()?);
+
+        (index < len).then(|| Self {
+            key: key.to_owned(),
+            index,
+            len,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn custom_ids(state: &GalleryState) -> Vec<(String, bool)> {
+        let components = state.components();
+        let [Component::ActionRow(row)] = components.as_slice() else {
+            panic!("expected a single action row");
+        };
+
+        row.components
+            .iter()
+            .map(|c| match c {
+                Component::Button(b) => (b.custom_id.clone().unwrap(), b.disabled),
+                _ => panic!("expected a button"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn gallery_round_trip() {
+        let state = GalleryState::new("123:456", 3);
+        let ids = custom_ids(&state);
+        assert!(ids[0].1, "previous is disabled on the first image");
+        assert!(!ids[2].1);
+
+        let next = GalleryState::from_interaction(&ids[2].0).unwrap();
+        assert_eq!(
+            next,
+            GalleryState {
+                index: 1,
+                ..state.clone()
+            }
+        );
+
+        let last = GalleryState::from_interaction(&custom_ids(&next)[2].0).unwrap();
+        assert_eq!(last.index, 2);
+        assert!(custom_ids(&last)[2].1, "next is disabled on the last image");
+
+        assert_eq!(GalleryState::from_interaction(&ids[1].0), None);
+        assert_eq!(GalleryState::from_interaction("something:else"), None);
+    }
+}
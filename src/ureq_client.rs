@@ -0,0 +1,126 @@
+//! A synchronous client on top of [`ureq`], behind the `ureq` feature.
+//!
+//! It makes the same requests as [`crate::client::Client`], with the
+//! request construction and parsing from [`crate::sans_io`], but
+//! without an async runtime: with the default features disabled (and
+//! only `ureq` enabled), neither tokio nor reqwest is a dependency.
+
+use std::io::Read;
+
+use crate::details::{GifDetails, ImageDetails};
+use crate::{
+    sans_io, Category, HeaderDeserializeUrlEncodedError, NekosBestError, NekosBestResponse,
+    NekosBestResponseSingle, SearchQuery, BASE_URL, USER_AGENT,
+};
+
+/// A synchronous client, see the [module documentation](self).
+#[derive(Debug, Clone)]
+pub struct UreqClient {
+    agent: ureq::Agent,
+    base_url: String,
+    user_agent: String,
+}
+
+impl Default for UreqClient {
+    fn default() -> Self {
+        Self::new(ureq::Agent::new())
+    }
+}
+
+impl UreqClient {
+    pub fn new(agent: ureq::Agent) -> Self {
+        Self::with_base_url(agent, BASE_URL)
+    }
+
+    pub fn with_base_url(agent: ureq::Agent, base_url: impl Into<String>) -> Self {
+        Self {
+            agent,
+            base_url: base_url.into().trim_end_matches('/').to_owned(),
+            user_agent: USER_AGENT.to_owned(),
+        }
+    }
+
+    /// Sends the requests with `user_agent` as their `User-Agent`, instead
+    /// of [`USER_AGENT`].
+    pub fn with_user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = user_agent.into();
+        self
+    }
+
+    fn fetch(&self, url: &str) -> Result<ureq::Response, NekosBestError> {
+        let request = self.agent.get(url).set("User-Agent", &self.user_agent);
+        match request.call() {
+            Ok(resp) => Ok(resp),
+            Err(ureq::Error::Status(status, resp)) => {
+                sans_io::check_status(status, resp.header("retry-after"))?;
+                Err(NekosBestError::Status(status))
+            }
+            Err(e) => Err(NekosBestError::Backend(Box::new(e))),
+        }
+    }
+
+    fn fetch_parse(&self, url: &str) -> Result<NekosBestResponse, NekosBestError> {
+        let resp = self.fetch(url)?;
+        let status = resp.status();
+        let mut body = Vec::new();
+        resp.into_reader().read_to_end(&mut body)?;
+
+        sans_io::parse_response(&body).map_err(|e| e.with_response(url, status, &body))
+    }
+
+    /// Gets a single image, like [`crate::get_with_client`].
+    pub fn get(
+        &self,
+        category: impl Into<Category>,
+    ) -> Result<NekosBestResponseSingle, NekosBestError> {
+        let url = sans_io::category_url(&self.base_url, category.into());
+        let mut response = self.fetch_parse(&url)?;
+        response.0.pop().ok_or(NekosBestError::NotFound)
+    }
+
+    /// Gets `amount` images, like [`crate::get_with_client_amount`].
+    ///
+    /// # Errors
+    /// [`NekosBestError::InvalidAmount`] if `amount` is not in the
+    /// `1..=`[`crate::MAX_AMOUNT`] range, see [`sans_io::check_amount`].
+    pub fn get_amount(
+        &self,
+        category: impl Into<Category>,
+        amount: impl Into<u8>,
+    ) -> Result<NekosBestResponse, NekosBestError> {
+        let amount = sans_io::check_amount(amount.into())?;
+        let url = sans_io::category_amount_url(&self.base_url, category.into(), amount);
+        self.fetch_parse(&url)
+    }
+
+    /// Searches for images, like [`crate::search_with_client`].
+    ///
+    /// The rate limit of the search endpoint isn't tracked; hitting it
+    /// results in [`NekosBestError::RateLimited`].
+    pub fn search(&self, query: SearchQuery) -> Result<NekosBestResponse, NekosBestError> {
+        let url = sans_io::search_url(&self.base_url, &query);
+        self.fetch_parse(&url)
+    }
+
+    /// Gets the details of the image at `url`, like
+    /// [`crate::get_with_client_image_details`].
+    pub fn image_details(&self, url: &str) -> Result<ImageDetails, NekosBestError> {
+        let resp = self.fetch(url)?;
+        sans_io::parse_image_details(|name| header(&resp, name))
+    }
+
+    /// Gets the details of the gif at `url`, like
+    /// [`crate::get_with_client_gif_details`].
+    pub fn gif_details(&self, url: &str) -> Result<GifDetails, NekosBestError> {
+        let resp = self.fetch(url)?;
+        sans_io::parse_gif_details(|name| header(&resp, name))
+    }
+}
+
+fn header<'a>(
+    resp: &'a ureq::Response,
+    name: &str,
+) -> Result<&'a str, HeaderDeserializeUrlEncodedError> {
+    resp.header(name)
+        .ok_or(HeaderDeserializeUrlEncodedError::MissingHeader)
+}
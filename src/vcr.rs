@@ -0,0 +1,252 @@
+//! Recording the responses of the API to fixture files, and replaying
+//! them, so that code using the client can be tested without network
+//! access, see [`crate::client::ClientBuilder::cassette`].
+//!
+//! Only available with the `test-util` feature, and not on wasm32.
+//!
+//! ```no_run
+//! # async fn f() -> Result<(), nekosbest::NekosBestError> {
+//! use nekosbest::{client::Client, vcr::Cassette, Category};
+//!
+//! // Record once, against the real API...
+//! let client = Client::builder()
+//!     .cassette(Cassette::record("tests/fixtures/hug.json"))
+//!     .build()?;
+//! nekosbest::get_with_client(&client, Category::Hug).await?;
+//!
+//! // ...then replay in CI.
+//! let client = Client::builder()
+//!     .cassette(Cassette::replay("tests/fixtures/hug.json")?)
+//!     .build()?;
+//! nekosbest::get_with_client(&client, Category::Hug).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+#[cfg(feature = "blocking")]
+use nb_blocking_util::blocking;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+use crate::client::{Client, ReqwestRequestBuilder, ReqwestResponse};
+use crate::NekosBestError;
+
+/// A fixture file of requests and their responses.
+///
+/// When recording, the requests are sent to the API, and the file is
+/// rewritten after every response. When replaying, nothing is sent: each
+/// request gets the first response recorded for the same method and url
+/// that wasn't replayed yet, so repeated requests replay in the order they
+/// were recorded.
+#[derive(Debug)]
+pub struct Cassette {
+    path: PathBuf,
+    recording: bool,
+    state: Mutex<State>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    interactions: Vec<Interaction>,
+    replayed: Vec<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Interaction {
+    method: String,
+    url: String,
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: Body,
+}
+
+/// Bodies are kept as text when possible, to keep the fixtures readable.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+enum Body {
+    Text(String),
+    Binary(Vec<u8>),
+}
+
+impl Body {
+    fn new(bytes: Vec<u8>) -> Self {
+        String::from_utf8(bytes).map_or_else(|e| Self::Binary(e.into_bytes()), Self::Text)
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        match self {
+            Self::Text(text) => text.into_bytes(),
+            Self::Binary(bytes) => bytes,
+        }
+    }
+}
+
+impl Cassette {
+    /// A cassette recording to `path`, replacing anything recorded there
+    /// before.
+    pub fn record(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            recording: true,
+            state: Mutex::default(),
+        }
+    }
+
+    /// A cassette replaying what was recorded to `path`.
+    ///
+    /// # Errors
+    /// [`NekosBestError::IO`] if the file can't be read, and
+    /// [`NekosBestError::Decoding`] if it isn't a cassette.
+    pub fn replay(path: impl Into<PathBuf>) -> Result<Self, NekosBestError> {
+        let path = path.into();
+        let interactions: Vec<Interaction> = serde_json::from_slice(&std::fs::read(&path)?)?;
+
+        Ok(Self {
+            path,
+            recording: false,
+            state: Mutex::new(State {
+                replayed: vec![false; interactions.len()],
+                interactions,
+            }),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording
+    }
+
+    /// How many responses are on the cassette.
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap().interactions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Sends the request through `client` and records the response, or
+    /// replays it.
+    #[cfg_attr(feature = "blocking", blocking)]
+    pub(crate) async fn send(
+        &self,
+        client: &Client,
+        req: ReqwestRequestBuilder,
+    ) -> Result<ReqwestResponse, NekosBestError> {
+        let request = req
+            .try_clone()
+            .expect("requests to the API have no streaming bodies")
+            .build()?;
+        let (method, url) = (request.method().to_string(), request.url().to_string());
+
+        if !self.recording {
+            return self.replay_response(&method, &url);
+        }
+
+        let resp = client.send_live(req).await?;
+        let status = resp.status().as_u16();
+        let headers = resp
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_owned())))
+            .collect();
+        let body = resp.bytes().await?;
+        let interaction = Interaction {
+            method,
+            url,
+            status,
+            headers,
+            body: Body::new(body.to_vec()),
+        };
+
+        let mut state = self.state.lock().unwrap();
+        state.interactions.push(interaction.clone());
+        state.replayed.push(true);
+        std::fs::write(&self.path, serde_json::to_vec_pretty(&state.interactions)?)?;
+
+        Ok(to_response(interaction))
+    }
+
+    fn replay_response(&self, method: &str, url: &str) -> Result<ReqwestResponse, NekosBestError> {
+        let mut state = self.state.lock().unwrap();
+        let State {
+            interactions,
+            replayed,
+        } = &mut *state;
+
+        let index = interactions
+            .iter()
+            .zip(replayed.iter())
+            .position(|(i, replayed)| !replayed && i.method == method && i.url == url)
+            .ok_or_else(|| NekosBestError::NotRecorded {
+                method: method.to_owned(),
+                url: url.to_owned(),
+            })?;
+        replayed[index] = true;
+
+        Ok(to_response(interactions[index].clone()))
+    }
+}
+
+fn to_response(interaction: Interaction) -> ReqwestResponse {
+    let mut builder = http::Response::builder().status(interaction.status);
+    for (name, value) in &interaction.headers {
+        builder = builder.header(name, value);
+    }
+
+    builder
+        .body(interaction.body.into_bytes())
+        .expect("recorded responses are valid")
+        .into()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Category;
+
+    #[cfg(not(feature = "blocking"))]
+    #[tokio::test]
+    async fn replay() {
+        let path = std::env::temp_dir().join(format!("nekosbest-vcr-test-{}", std::process::id()));
+        let interaction = |name: &str| Interaction {
+            method: "GET".to_owned(),
+            url: "http://127.0.0.1:1/hug".to_owned(),
+            status: 200,
+            headers: vec![("content-type".to_owned(), "application/json".to_owned())],
+            body: Body::Text(format!(
+                r#"{{"results":[{{"anime_name":"a","url":"http://localhost/{name}"}}]}}"#
+            )),
+        };
+        std::fs::write(
+            &path,
+            serde_json::to_vec(&[interaction("first"), interaction("second")]).unwrap(),
+        )
+        .unwrap();
+
+        let cassette = Cassette::replay(&path).unwrap();
+        assert_eq!(cassette.len(), 2);
+        let client = Client::builder()
+            .base_url("http://127.0.0.1:1")
+            .cassette(cassette)
+            .build()
+            .unwrap();
+
+        for name in ["first", "second"] {
+            let image = crate::get_with_client(&client, Category::Hug)
+                .await
+                .unwrap();
+            assert_eq!(image.url, format!("http://localhost/{name}"));
+        }
+        assert!(matches!(
+            crate::get_with_client(&client, Category::Hug).await,
+            Err(NekosBestError::NotRecorded { .. })
+        ));
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,198 @@
+//! Posting images to Discord and Slack webhooks, without a bot framework.
+
+#[cfg(feature = "blocking")]
+use nb_blocking_util::blocking;
+use reqwest::IntoUrl;
+
+use crate::client::{default_client, Client};
+use crate::details::Details;
+use crate::{get_with_client, Category, NekosBestError, NekosBestResponseSingle};
+
+/// The payload of a Discord webhook message showing the image in `response`,
+/// as an embed with the attribution.
+pub fn webhook_payload(
+    category: Category,
+    response: &NekosBestResponseSingle,
+) -> serde_json::Value {
+    let mut embed = serde_json::json!({
+        "title": category.to_url_name(),
+        "image": { "url": response.url },
+    });
+
+    match &response.details {
+        Details::Image(image) => {
+            embed["author"] = serde_json::json!({
+                "name": image.artist.name,
+                "url": image.artist.href.as_str(),
+            });
+            if let Some(source) = &image.source {
+                embed["url"] = source.url.as_str().into();
+            }
+        }
+        Details::Gif(gif) => {
+            embed["footer"] = serde_json::json!({ "text": gif.anime_name });
+        }
+    }
+
+    serde_json::json!({ "embeds": [embed] })
+}
+
+/// The payload of a Slack incoming webhook message showing the image in
+/// `response` as blocks, with the attribution in a context block.
+pub fn slack_payload(category: Category, response: &NekosBestResponseSingle) -> serde_json::Value {
+    let name = category.to_url_name();
+    let credit = match &response.details {
+        Details::Image(image) => {
+            let mut credit = format!(
+                "Art by <{}|{}>",
+                image.artist.href,
+                escape_slack(&image.artist.name)
+            );
+            if let Some(source) = &image.source {
+                credit.push_str(&format!(" · <{}|source>", source.url));
+            }
+            credit
+        }
+        Details::Gif(gif) => format!("From {}", escape_slack(&gif.anime_name)),
+    };
+
+    serde_json::json!({
+        "text": name,
+        "blocks": [
+            {
+                "type": "image",
+                "image_url": response.url,
+                "alt_text": name,
+                "title": { "type": "plain_text", "text": name },
+            },
+            {
+                "type": "context",
+                "elements": [{ "type": "mrkdwn", "text": credit }],
+            },
+        ],
+    })
+}
+
+/// Escapes the characters with special meaning in Slack's `mrkdwn`.
+fn escape_slack(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg_attr(feature = "blocking", blocking)]
+pub(crate) async fn post_payload(
+    client: &Client,
+    webhook_url: impl IntoUrl,
+    payload: &serde_json::Value,
+) -> Result<(), NekosBestError> {
+    client
+        .client
+        .post(webhook_url)
+        .json(payload)
+        .send()
+        .await?
+        .error_for_status()?;
+
+    Ok(())
+}
+
+/// Gets an image from `category` and posts it to the Discord webhook at
+/// `webhook_url`, with a supplied client.
+///
+/// Returns the image that was posted.
+#[cfg_attr(feature = "blocking", blocking)]
+pub async fn post_to_webhook_with_client(
+    client: &Client,
+    webhook_url: impl IntoUrl,
+    category: impl Into<Category>,
+) -> Result<NekosBestResponseSingle, NekosBestError> {
+    let category = category.into();
+    let response = get_with_client(client, category).await?;
+
+    post_payload(client, webhook_url, &webhook_payload(category, &response)).await?;
+    Ok(response)
+}
+
+/// Gets an image from `category` and posts it to the Discord webhook at
+/// `webhook_url`, with the default client.
+#[cfg_attr(feature = "blocking", blocking)]
+pub async fn post_to_webhook(
+    webhook_url: impl IntoUrl,
+    category: impl Into<Category>,
+) -> Result<NekosBestResponseSingle, NekosBestError> {
+    post_to_webhook_with_client(&default_client(), webhook_url, category).await
+}
+
+/// Gets an image from `category` and posts it to the Slack incoming
+/// webhook at `webhook_url`, with a supplied client.
+///
+/// Returns the image that was posted.
+#[cfg_attr(feature = "blocking", blocking)]
+pub async fn post_to_slack_webhook_with_client(
+    client: &Client,
+    webhook_url: impl IntoUrl,
+    category: impl Into<Category>,
+) -> Result<NekosBestResponseSingle, NekosBestError> {
+    let category = category.into();
+    let response = get_with_client(client, category).await?;
+    post_payload(client, webhook_url, &slack_payload(category, &response)).await?;
+    Ok(response)
+}
+
+/// Gets an image from `category` and posts it to the Slack incoming
+/// webhook at `webhook_url`, with the default client.
+#[cfg_attr(feature = "blocking", blocking)]
+pub async fn post_to_slack_webhook(
+    webhook_url: impl IntoUrl,
+    category: impl Into<Category>,
+) -> Result<NekosBestResponseSingle, NekosBestError> {
+    post_to_slack_webhook_with_client(&default_client(), webhook_url, category).await
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn payload() {
+        let image = NekosBestResponseSingle::test_image(
+            "https://nekos.best/api/v2/neko/a.png",
+            "someone",
+            "https://example.com/artist",
+        );
+        assert_eq!(
+            webhook_payload(Category::Neko, &image),
+            serde_json::json!({ "embeds": [{
+                "title": "neko",
+                "url": "https://example.com/source",
+                "image": { "url": "https://nekos.best/api/v2/neko/a.png" },
+                "author": { "name": "someone", "url": "https://example.com/artist" },
+            }] })
+        );
+
+        let gif = NekosBestResponseSingle::test_gif("https://nekos.best/api/v2/pat/a.gif", "Senko");
+        assert_eq!(
+            webhook_payload(Category::Pat, &gif)["embeds"][0]["footer"]["text"],
+            "Senko"
+        );
+    }
+
+    #[test]
+    fn slack() {
+        let image = NekosBestResponseSingle::test_image(
+            "https://nekos.best/api/v2/neko/a.png",
+            "<someone>",
+            "https://example.com/artist",
+        );
+        let payload = slack_payload(Category::Neko, &image);
+        assert_eq!(
+            payload["blocks"][0]["image_url"],
+            "https://nekos.best/api/v2/neko/a.png"
+        );
+        assert_eq!(
+            payload["blocks"][1]["elements"][0]["text"],
+            "Art by <https://example.com/artist|&lt;someone&gt;> · <https://example.com/source|source>"
+        );
+    }
+}
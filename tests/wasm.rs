@@ -0,0 +1,18 @@
+//! Checks that the crate works in the browser.
+//!
+//! Run with `wasm-pack test --headless --firefox -- --no-default-features --features reqwest`.
+
+#![cfg(target_arch = "wasm32")]
+
+use wasm_bindgen_test::*;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+#[wasm_bindgen_test]
+async fn get_neko() {
+    let client = nekosbest::client::Client::new(nekosbest::client::ClientConfig::default());
+    let resp = nekosbest::get_with_client(&client, nekosbest::Category::Neko)
+        .await
+        .unwrap();
+    assert!(resp.details.is_image());
+}